@@ -1,7 +1,8 @@
 use crate::shared::{self, OperationWait};
 use anyhow::{bail, Result};
-use std::{ffi::OsString, path::PathBuf};
-use velopack::{constants, locator, locator::VelopackLocator};
+use semver::Version;
+use std::{ffi::OsString, fs, path::PathBuf};
+use velopack::{bundle, bundle::BundleZip, constants, locator, locator::VelopackLocator, SignatureVerifier};
 
 #[cfg(target_os = "linux")]
 use super::apply_linux_impl::apply_package_impl;
@@ -21,6 +22,71 @@ pub enum HookRunMode {
     PostOnly,
 }
 
+/// If the installed app trusts any package signing keys, checks that the package was signed by one of them.
+/// Packages which fail verification are deleted, so we don't try to apply them again on the next start.
+pub(crate) fn verify_package_signature(locator: &VelopackLocator, bundle: &BundleZip, package: &PathBuf) -> Result<()> {
+    let trusted_keys = locator.get_manifest().get_signing_public_keys();
+    if trusted_keys.is_empty() {
+        return Ok(());
+    }
+
+    let verifier = SignatureVerifier::new(&trusted_keys)?;
+    if let Err(e) = bundle.verify_signature(&verifier) {
+        error!(
+            "Package {:?} failed signature verification, deleting it to prevent update loop: {}",
+            package, e
+        );
+        let _ = fs::remove_file(package);
+        return Err(e.into());
+    }
+
+    info!("Package signature verified successfully.");
+    Ok(())
+}
+
+/// Refuses to apply a package older than the current version, or the highest version which has ever been applied,
+/// so that a stale package or a local attacker can not silently roll the app back.
+fn verify_package_is_not_downgrade(locator: &VelopackLocator, package: &PathBuf) -> Result<()> {
+    // packages which can not be read are rejected (and deleted) when they are applied.
+    let Ok(package_version) = bundle::load_bundle_from_file(package)
+        .and_then(|mut b| b.read_manifest())
+        .map(|m| m.version)
+    else {
+        return Ok(());
+    };
+    verify_version_is_not_downgrade(locator, &package_version)
+}
+
+/// Returns an error if the package version is older than the current version, or the highest version which has
+/// ever been applied to this app.
+pub fn verify_version_is_not_downgrade(locator: &VelopackLocator, package_version: &Version) -> Result<()> {
+    let current_version = locator.get_manifest_version();
+    let minimum_version = match locator.get_highest_applied_version() {
+        Some(highest) if highest > current_version => highest,
+        _ => current_version,
+    };
+    if *package_version < minimum_version {
+        bail!(
+            "Package version {} is older than version {} which has already been applied. Use --allowDowngrade to apply it anyway.",
+            package_version,
+            minimum_version
+        );
+    }
+    Ok(())
+}
+
+/// Records the version of a package which has just been applied. If the downgrade was allowed, the mark is lowered
+/// to the applied version, so the release being rolled back from (eg. a retracted one) does not block later updates.
+pub fn record_applied_package_version(applied_locator: &VelopackLocator, allow_downgrade: bool) -> Result<()> {
+    let version = applied_locator.get_manifest_version();
+    if allow_downgrade {
+        applied_locator.reset_applied_version(&version)?;
+    } else {
+        applied_locator.record_applied_version(&version)?;
+    }
+    Ok(())
+}
+
 pub fn apply(
     locator: &VelopackLocator,
     restart: bool,
@@ -28,6 +94,7 @@ pub fn apply(
     package: Option<&PathBuf>,
     exe_args: Option<Vec<OsString>>,
     hook_mode: HookRunMode,
+    allow_downgrade: bool,
 ) -> Result<VelopackLocator> {
     shared::operation_wait(wait);
 
@@ -42,12 +109,24 @@ pub fn apply(
                 locator.get_manifest_version_full_string(),
                 package
             );
+            if !allow_downgrade {
+                if let Err(e) = verify_package_is_not_downgrade(locator, &package) {
+                    if restart {
+                        shared::start_package(locator, exe_args, Some(constants::HOOK_ENV_RESTART))?;
+                    }
+                    bail!("Error applying package: {}", e);
+                }
+            }
+
             match apply_package_impl(locator, &package, hook_mode) {
                 Ok(applied_locator) => {
                     info!(
                         "Package version {} applied successfully.",
                         applied_locator.get_manifest_version_full_string()
                     );
+                    if let Err(e) = record_applied_package_version(&applied_locator, allow_downgrade) {
+                        warn!("Failed to record applied version: {}", e);
+                    }
                     // if successful, we want to restart the new version of the app, which could have different metadata
                     if restart {
                         shared::start_package(&applied_locator, exe_args, Some(constants::HOOK_ENV_RESTART))?;
@@ -18,6 +18,7 @@ pub fn apply_package_impl(locator: &VelopackLocator, pkg: &PathBuf, _hook_mode:
         let _ = fs::remove_file(pkg);
         e
     })?;
+    super::apply::verify_package_signature(locator, &bundle, pkg)?;
     let temp_path = locator.get_temp_dir_rand16().to_string_lossy().to_string();
     let appimage_path = locator.get_appimage_path().to_string_lossy().to_string();
     let script_path = format!("/var/tmp/velopack_update_{}.sh", manifest.id);
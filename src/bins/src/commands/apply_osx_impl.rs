@@ -18,6 +18,7 @@ pub fn apply_package_impl(locator: &VelopackLocator, pkg: &PathBuf, _hook_mode:
         let _ = fs::remove_file(pkg);
         e
     })?;
+    super::apply::verify_package_signature(locator, &bundle, pkg)?;
     let new_locator = locator.clone_self_with_new_manifest(&manifest);
 
     // show progress dialog
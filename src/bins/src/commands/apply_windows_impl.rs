@@ -46,6 +46,7 @@ pub fn apply_package_impl(old_locator: &VelopackLocator, package: &PathBuf, hook
         let _ = fs::remove_file(package);
         e
     })?;
+    super::apply::verify_package_signature(old_locator, &bundle, package)?;
     let new_locator = old_locator.clone_self_with_new_manifest(&new_app_manifest);
 
     if !windows::is_directory_writable(&root_path) {
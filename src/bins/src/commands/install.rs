@@ -233,6 +233,10 @@ fn install_impl(pkg: &mut BundleZip, locator: &VelopackLocator, tx: &std::sync::
     let _ = tx.send(100);
     windows::registry::write_uninstall_entry(locator)?;
 
+    if let Err(e) = locator.record_applied_version(&locator.get_manifest_version()) {
+        warn!("Failed to record installed version: {}", e);
+    }
+
     if !dialogs::get_silent() {
         info!("Starting app...");
         shared::start_package(locator, start_args, Some(constants::HOOK_ENV_FIRSTRUN))?;
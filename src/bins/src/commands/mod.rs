@@ -7,6 +7,9 @@ pub use start::*;
 mod patch;
 pub use patch::*;
 
+mod verify;
+pub use verify::*;
+
 #[cfg(target_os = "linux")]
 mod apply_linux_impl;
 #[cfg(target_os = "macos")]
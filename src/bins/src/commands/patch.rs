@@ -5,6 +5,7 @@ use std::{
     fs, io,
     path::{Path, PathBuf},
 };
+use velopack::{misc, sanitize};
 
 pub fn zstd_patch_single<P1: AsRef<Path>, P2: AsRef<Path>, P3: AsRef<Path>>(old_file: P1, patch_file: P2, output_file: P3) -> Result<()> {
     let old_file = old_file.as_ref();
@@ -101,12 +102,14 @@ pub fn delta<P1: AsRef<Path>, P2: AsRef<Path>, P3: AsRef<Path>>(
                 if file_name_str.ends_with(".zsdiff") || file_name_str.ends_with(".diff") || file_name_str.ends_with(".bsdiff") {
                     // this is a zsdiff patch, we need to apply it to the old file
                     let file_without_extension = relative_path.with_extension("");
-                    // let shasum_path = delta_dir.join(relative_path).with_extension("shasum");
-                    let old_file_path = work_dir.join(&file_without_extension);
+                    let mut shasum_path = delta_dir.join(&file_without_extension).into_os_string();
+                    shasum_path.push(".shasum");
+                    let shasum_path = PathBuf::from(shasum_path);
+                    let old_file_path = sanitize::resolve_entry_path(&work_dir, &file_without_extension)?;
                     let patch_file_path = delta_dir.join(relative_path);
                     let output_file_path = delta_dir.join(&file_without_extension);
 
-                    visited_paths.insert(file_without_extension);
+                    visited_paths.insert(file_without_extension.clone());
 
                     if fs::metadata(&patch_file_path)?.len() == 0 {
                         // file has not changed, so we can continue.
@@ -120,13 +123,15 @@ pub fn delta<P1: AsRef<Path>, P2: AsRef<Path>, P3: AsRef<Path>>(
                         bail!("Unsupported patch format: {:?}", relative_path);
                     }
 
+                    verify_patched_file(&output_file_path, &shasum_path, &file_without_extension)?;
+
                     fs::rename(&output_file_path, &old_file_path)?;
                 } else if file_name_str.ends_with(".shasum") {
                     // skip shasum files
                 } else {
                     // if this file is inside the lib folder without a known extension, it is a new file
                     let file_path = delta_dir.join(relative_path);
-                    let dest_path = work_dir.join(relative_path);
+                    let dest_path = sanitize::resolve_entry_path(&work_dir, relative_path)?;
                     info!("{}: new file: {:?}", i, relative_path);
                     fs::create_dir_all(dest_path.parent().ok_or(anyhow!("Failed to get parent"))?)?;
                     fs::copy(&file_path, &dest_path)?;
@@ -135,7 +140,7 @@ pub fn delta<P1: AsRef<Path>, P2: AsRef<Path>, P3: AsRef<Path>>(
             } else {
                 // if this file is not inside the lib folder, we always copy it over
                 let file_path = delta_dir.join(relative_path);
-                let dest_path = work_dir.join(relative_path);
+                let dest_path = sanitize::resolve_entry_path(&work_dir, relative_path)?;
                 info!("{}: copying metadata file: {:?}", i, relative_path);
                 fs::copy(&file_path, &dest_path)?;
                 visited_paths.insert(relative_path.clone());
@@ -161,25 +166,101 @@ pub fn delta<P1: AsRef<Path>, P2: AsRef<Path>, P3: AsRef<Path>>(
     Ok(())
 }
 
-// NOTE: this is some code to do checksum verification, but it is not being used
-// by the current implementation because zstd patching already has checksum verification
-//
-// let actual_checksum = get_sha1(&output_file_path);
-// let expected_checksum = load_release_entry_shasum(&shasum_path)?;
-//
-// if !actual_checksum.eq_ignore_ascii_case(&expected_checksum) {
-//     bail!("Checksum mismatch for: {:?}. Expected: {}, Actual: {}", relative_path, expected_checksum, actual_checksum);
-// }
-// fn load_release_entry_shasum(file: &PathBuf) -> Result<String> {
-//     let raw_text = fs::read_to_string(file)?.trim().to_string();
-//     let first_word = raw_text.splitn(2, ' ').next().unwrap();
-//     let cleaned = first_word.trim().trim_matches(|c: char| !c.is_ascii_hexdigit());
-//     Ok(cleaned.to_string())
-// }
-//
-// fn get_sha1(file: &PathBuf) -> String {
-//     let file_bytes = fs::read(file).unwrap();
-//     let mut sha1 = sha1_smol::Sha1::new();
-//     sha1.update(&file_bytes);
-//     sha1.digest().to_string()
-// }
+/// Verifies a patched file against the `.shasum` file from the delta package, which contains the expected SHA1
+/// (or SHA256) and optionally the size of the new file. vpk writes a `.shasum` next to every zsdiff patch, and the
+/// delta package itself is verified against the release feed, so a missing or empty `.shasum` fails the patch.
+fn verify_patched_file(file: &Path, shasum_path: &Path, relative_path: &Path) -> Result<()> {
+    if !shasum_path.exists() {
+        bail!("Delta package has no shasum for patched file: {:?}", relative_path);
+    }
+
+    let (expected_checksum, expected_size) = load_release_entry_shasum(shasum_path)?;
+    if expected_checksum.is_empty() {
+        bail!("Delta package has an empty shasum for patched file: {:?}", relative_path);
+    }
+
+    if let Some(expected_size) = expected_size {
+        let actual_size = fs::metadata(file)?.len();
+        if actual_size != expected_size {
+            bail!(
+                "Size mismatch for: {:?}. Expected: {}, Actual: {}",
+                relative_path,
+                expected_size,
+                actual_size
+            );
+        }
+    }
+
+    let (sha1, sha256) = misc::calculate_sha1_sha256(file)?;
+    let actual_checksum = if expected_checksum.len() == 64 {
+        sha256
+    } else {
+        sha1
+    };
+    if !actual_checksum.eq_ignore_ascii_case(&expected_checksum) {
+        bail!(
+            "Checksum mismatch for: {:?}. Expected: {}, Actual: {}",
+            relative_path,
+            expected_checksum,
+            actual_checksum
+        );
+    }
+    Ok(())
+}
+
+/// Parses a `.shasum` file in the legacy release entry format: `{HASH} {FILENAME} {SIZE}`.
+fn load_release_entry_shasum(file: &Path) -> Result<(String, Option<u64>)> {
+    let raw_text = fs::read_to_string(file)?;
+    let raw_text = raw_text.trim_start_matches('\u{feff}').trim();
+    let mut words = raw_text.split_whitespace();
+    let checksum = words
+        .next()
+        .unwrap_or_default()
+        .trim_matches(|c: char| !c.is_ascii_hexdigit())
+        .to_string();
+    let size = words.nth(1).and_then(|s| s.parse::<u64>().ok());
+    Ok((checksum, size))
+}
+
+#[test]
+fn test_load_release_entry_shasum() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.exe.shasum");
+
+    fs::write(&path, "\u{feff}78A312BB505535A613B7402204464CEF97C77415 app.exe.shasum 229888").unwrap();
+    let (checksum, size) = load_release_entry_shasum(&path).unwrap();
+    assert_eq!(checksum, "78A312BB505535A613B7402204464CEF97C77415");
+    assert_eq!(size, Some(229888));
+
+    fs::write(&path, "").unwrap();
+    assert_eq!(load_release_entry_shasum(&path).unwrap(), (String::new(), None));
+}
+
+#[test]
+fn test_verify_patched_file_names_failing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("app.dll");
+    let shasum = dir.path().join("app.dll.shasum");
+    fs::write(&file, b"hello").unwrap();
+    let relative = Path::new("lib/app/app.dll");
+
+    // sha1 of "hello"
+    fs::write(&shasum, "AAF4C61DDCC5E8A2DABEDE0F3B482CD9AEA9434D app.dll 5").unwrap();
+    verify_patched_file(&file, &shasum, relative).unwrap();
+
+    fs::write(&shasum, "2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824 app.dll 5").unwrap();
+    verify_patched_file(&file, &shasum, relative).unwrap();
+
+    fs::write(&shasum, "0000000000000000000000000000000000000000 app.dll 5").unwrap();
+    let err = verify_patched_file(&file, &shasum, relative).unwrap_err().to_string();
+    assert!(err.contains("lib/app/app.dll"), "{}", err);
+
+    fs::write(&shasum, "AAF4C61DDCC5E8A2DABEDE0F3B482CD9AEA9434D app.dll 6").unwrap();
+    assert!(verify_patched_file(&file, &shasum, relative).is_err());
+
+    fs::write(&shasum, "").unwrap();
+    assert!(verify_patched_file(&file, &shasum, relative).is_err());
+
+    fs::remove_file(&shasum).unwrap();
+    assert!(verify_patched_file(&file, &shasum, relative).is_err());
+}
@@ -0,0 +1,34 @@
+use anyhow::{anyhow, bail, Result};
+use velopack::{bundle, integrity, locator::VelopackLocator, IntegrityReport};
+
+/// Compares the installed files against the package the current version was installed from, and optionally
+/// restores any which are missing or modified. Returns the report from before the repair.
+pub fn verify(locator: &VelopackLocator, repair: bool) -> Result<IntegrityReport> {
+    let package = integrity::find_installed_package(locator).ok_or_else(|| {
+        anyhow!(
+            "The package for version {} was not found in '{:?}', so the installation can not be verified.",
+            locator.get_manifest_version_full_string(),
+            locator.get_packages_dir()
+        )
+    })?;
+
+    let report = integrity::verify_installation(locator, &package)?;
+    if !repair || report.is_intact() {
+        return Ok(report);
+    }
+
+    let _mutex = locator.try_get_exclusive_lock()?;
+    let bundle = bundle::load_bundle_from_file(&package)?;
+    super::verify_package_signature(locator, &bundle, &package)?;
+    integrity::repair_installed_files(locator, &bundle, &report)?;
+
+    let after = integrity::verify_installation(locator, &package)?;
+    if !after.is_intact() {
+        bail!(
+            "{} files are still missing and {} files are still modified after the repair.",
+            after.missing_files.len(),
+            after.modified_files.len()
+        );
+    }
+    Ok(report)
+}
@@ -7,15 +7,15 @@
 // except according to those terms.
 
 use std::{
-    borrow::Cow,
     fs::File,
-    io::{ErrorKind, Read, Seek, SeekFrom},
+    io::{Read, Seek, SeekFrom},
     path::{Path, PathBuf},
     sync::Mutex,
 };
 
 use anyhow::{Context, Result};
 use rayon::prelude::*;
+use velopack::sanitize;
 use zip::{read::ZipFile, ZipArchive};
 
 use super::{cloneable_seekable_reader::CloneableSeekableReader, progress_updater::ProgressUpdater, UnzipProgressReporter};
@@ -105,6 +105,7 @@ impl UnzipEngine {
     // Perform the unzip.
     pub fn unzip(mut self, options: UnzipOptions) -> Result<()> {
         log::debug!("Starting extract");
+        sanitize::validate_entry_names(&self.zipfile.list()?)?;
         options.progress_reporter.total_bytes_expected(self.compressed_length);
         let errors = self.zipfile.unzip(options, &self.directory_creator);
         // Return the first error code, if any.
@@ -247,12 +248,7 @@ fn extract_file<R: Read>(
     progress_reporter: &dyn UnzipProgressReporter,
     directory_creator: &DirectoryCreator,
 ) -> Result<(), anyhow::Error> {
-    let name = file
-        .enclosed_name()
-        .as_deref()
-        .map(Path::to_string_lossy)
-        .unwrap_or_else(|| Cow::Borrowed("<unprintable>"))
-        .to_string();
+    let name = file.name().to_string();
     extract_file_inner(file, output_directory, progress_reporter, directory_creator).with_context(|| format!("Failed to extract {name}"))
 }
 
@@ -263,12 +259,10 @@ fn extract_file_inner<R: Read>(
     progress_reporter: &dyn UnzipProgressReporter,
     directory_creator: &DirectoryCreator,
 ) -> Result<()> {
-    let name = file
-        .enclosed_name()
-        .ok_or_else(|| std::io::Error::new(ErrorKind::Unsupported, "path not safe to extract"))?;
+    let name = sanitize::sanitize_entry_name(file.name())?;
     let display_name = name.display().to_string();
     let out_path = match output_directory {
-        Some(output_directory) => output_directory.join(name),
+        Some(output_directory) => sanitize::resolve_entry_path(output_directory, &name)?,
         None => name,
     };
     progress_reporter.extraction_starting(&display_name);
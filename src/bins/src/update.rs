@@ -23,6 +23,7 @@ fn root_command() -> Command {
         .arg(arg!(-w --wait "Wait for the parent process to terminate before applying the update").hide(true))
         .arg(arg!(--waitPid <PID> "Wait for the specified process to terminate before applying the update").value_parser(value_parser!(u32)))
         .arg(arg!(-p --package <FILE> "Update package to apply").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--allowDowngrade "Apply the package even if it is older than a version which has already been applied"))
         .arg(arg!([EXE_ARGS] "Arguments to pass to the started executable. Must be preceded by '--'.").required(false).last(true).num_args(0..).value_parser(value_parser!(OsString)))
     )
     .subcommand(Command::new("start")
@@ -40,6 +41,10 @@ fn root_command() -> Command {
         .arg(arg!(--delta <FILE> "The delta bundle to apply to the base package").required(true).action(ArgAction::Append).value_parser(value_parser!(PathBuf)))
         .arg(arg!(--output <FILE> "The file to create with the patch applied").required(true).value_parser(value_parser!(PathBuf)))
     )
+    .subcommand(Command::new("verify")
+        .about("Checks the installed files against the package they were installed from")
+        .arg(arg!(--repair "Restore any missing or modified files from the package"))
+    )
     .arg(arg!(--verbose "Print debug messages to console / log").global(true))
     .arg(arg!(-s --silent "Don't show any prompts / dialogs").global(true))
     .arg(arg!(--rootDir <PATH> "Override the default locator root directory").alias("root").global(true).value_parser(value_parser!(PathBuf)))
@@ -210,6 +215,7 @@ fn main_inner() -> Result<()> {
         "start" => start(location_context, subcommand_matches).map_err(|e| anyhow!("Start error: {}", e)),
         "apply" => apply(location_context, subcommand_matches).map_err(|e| anyhow!("Apply error: {}", e)),
         "patch" => patch(location_context, subcommand_matches).map_err(|e| anyhow!("Patch error: {}", e)),
+        "verify" => verify(location_context, subcommand_matches).map_err(|e| anyhow!("Verify error: {}", e)),
         _ => bail!("Unknown subcommand '{subcommand}'. Try `--help` for more information."),
     };
 
@@ -254,6 +260,41 @@ fn patch(_context: LocationContext, matches: &ArgMatches) -> Result<()> {
     Ok(())
 }
 
+fn verify(context: LocationContext, matches: &ArgMatches) -> Result<()> {
+    let repair = get_flag_or_false(matches, "repair");
+    info!("Command: Verify");
+    info!("    Repair: {:?}", repair);
+
+    let locator = auto_locate_app_manifest(context)?;
+    let report = commands::verify(&locator, repair)?;
+    for file in &report.missing_files {
+        warn!("Missing: {}", file);
+    }
+    for file in &report.modified_files {
+        warn!("Modified: {}", file);
+    }
+    for file in &report.extra_files {
+        info!("Extra: {}", file);
+    }
+
+    if report.is_intact() {
+        info!("All installed files are intact.");
+    } else if repair {
+        info!(
+            "Repaired {} missing and {} modified files.",
+            report.missing_files.len(),
+            report.modified_files.len()
+        );
+    } else {
+        bail!(
+            "{} files are missing and {} files are modified.",
+            report.missing_files.len(),
+            report.modified_files.len()
+        );
+    }
+    Ok(())
+}
+
 fn get_exe_args(matches: &ArgMatches) -> Option<Vec<OsString>> {
     matches.get_many::<OsString>("EXE_ARGS").map(|v| v.map(|f| f.to_os_string()).collect())
 }
@@ -268,17 +309,19 @@ fn get_apply_args(matches: &ArgMatches) -> (OperationWait, bool, Option<&PathBuf
 
 fn apply(context: LocationContext, matches: &ArgMatches) -> Result<()> {
     let (wait, restart, package, exe_args) = get_apply_args(matches);
+    let allow_downgrade = get_flag_or_false(matches, "allowDowngrade");
     info!("Command: Apply");
     info!("    Restart: {:?}", restart);
     info!("    Wait: {:?}", wait);
     info!("    Package: {:?}", package);
+    info!("    Allow Downgrade: {:?}", allow_downgrade);
     info!("    Exe Args: {:?}", exe_args);
 
     let locator = auto_locate_app_manifest(context)?;
     // Note: lock is NOT acquired here. It's acquired inside apply_package_impl
     // AFTER the self-elevation check, to avoid deadlock when the non-elevated
     // parent spawns an elevated child (both would try to lock the same file).
-    let _ = commands::apply(&locator, restart, wait, package, exe_args, commands::HookRunMode::All)?;
+    let _ = commands::apply(&locator, restart, wait, package, exe_args, commands::HookRunMode::All, allow_downgrade)?;
     Ok(())
 }
 
@@ -499,3 +542,15 @@ fn test_start_command_supports_legacy_commands() {
     assert_eq!(legacy_args, Some(&"-- -c \" asda --aasd".into()));
     assert_eq!(exe_args, None);
 }
+
+#[cfg(target_os = "windows")]
+#[test]
+fn test_cli_parse_verify_repair() {
+    let command = vec!["Update.exe", "verify", "--repair"];
+    let matches = try_parse_command_line_matches(command.iter().map(|s| s.to_string()).collect()).unwrap();
+    assert!(get_flag_or_false(matches.subcommand_matches("verify").unwrap(), "repair"));
+
+    let command = vec!["Update.exe", "verify"];
+    let matches = try_parse_command_line_matches(command.iter().map(|s| s.to_string()).collect()).unwrap();
+    assert!(!get_flag_or_false(matches.subcommand_matches("verify").unwrap(), "repair"));
+}
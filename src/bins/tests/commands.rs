@@ -62,6 +62,7 @@ pub fn test_install_apply_uninstall() {
         Some(&nupkg_apply),
         None,
         commands::HookRunMode::None,
+        false,
     )
     .unwrap();
 
@@ -174,6 +175,7 @@ pub fn test_apply_corrupt_package_is_deleted() {
         Some(&corrupt_pkg),
         None,
         commands::HookRunMode::None,
+        false,
     );
     assert!(result.is_err(), "Apply should fail with a corrupt package");
 
@@ -216,6 +218,7 @@ pub fn test_apply_locked_dir_does_not_delete_package() {
         Some(&update_pkg),
         None,
         commands::HookRunMode::None,
+        false,
     );
     assert!(result.is_err(), "Apply should fail when current dir is locked");
 
@@ -223,6 +226,85 @@ pub fn test_apply_locked_dir_does_not_delete_package() {
     assert!(update_pkg.exists(), "Valid package should be preserved when install phase fails");
 }
 
+#[cfg(target_os = "windows")]
+#[test]
+#[serial_test::file_serial(shortcuts)]
+pub fn test_apply_refuses_older_package_unless_downgrade_allowed() {
+    dialogs::set_silent(true);
+    let fixtures = find_fixtures();
+
+    let nupkg = fixtures.join("AvaloniaCrossPlat-1.0.15-win-full.nupkg");
+    let tmp_dir = tempdir().unwrap();
+    let tmp_buf = tmp_dir.path().to_path_buf();
+    let mut tmp_zip = load_bundle_from_file(nupkg).unwrap();
+    commands::install(&mut tmp_zip, Some(&tmp_buf), None).unwrap();
+
+    let locator = auto_locate_app_manifest(LocationContext::FromSpecifiedRootDir(tmp_buf.clone(), None)).unwrap();
+    assert_eq!(Some(semver::Version::parse("1.0.15").unwrap()), locator.get_highest_applied_version());
+
+    let older_pkg = fixtures.join("AvaloniaCrossPlat-1.0.11-win-full.nupkg");
+    let result = commands::apply(
+        &locator,
+        false,
+        shared::OperationWait::NoWait,
+        Some(&older_pkg),
+        None,
+        commands::HookRunMode::None,
+        false,
+    );
+    assert!(result.is_err(), "Apply should refuse an older package");
+    assert!(older_pkg.exists(), "The older package should not be deleted");
+
+    let applied = commands::apply(
+        &locator,
+        false,
+        shared::OperationWait::NoWait,
+        Some(&older_pkg),
+        None,
+        commands::HookRunMode::None,
+        true,
+    )
+    .unwrap();
+    assert_eq!(semver::Version::parse("1.0.11").unwrap(), applied.get_manifest_version());
+    assert_eq!(Some(semver::Version::parse("1.0.11").unwrap()), applied.get_highest_applied_version());
+}
+
+#[test]
+pub fn test_downgrade_check_uses_highest_applied_version() {
+    use velopack::bundle::Manifest;
+    use velopack::locator::{VelopackLocator, VelopackLocatorConfig};
+
+    let tmp_dir = tempdir().unwrap();
+    let paths = VelopackLocatorConfig {
+        PackagesDir: tmp_dir.path().join("packages"),
+        ..Default::default()
+    };
+    let manifest = Manifest {
+        version: semver::Version::parse("1.0.11").unwrap(),
+        ..Default::default()
+    };
+    let locator = VelopackLocator::new_with_manifest(paths, manifest);
+    let v = |s: &str| semver::Version::parse(s).unwrap();
+
+    commands::verify_version_is_not_downgrade(&locator, &v("1.0.11")).unwrap();
+    assert!(commands::verify_version_is_not_downgrade(&locator, &v("1.0.10")).is_err());
+
+    // a newer version was applied at some point, so it is the minimum even though the current version is older
+    locator.record_applied_version(&v("1.0.15")).unwrap();
+    assert!(commands::verify_version_is_not_downgrade(&locator, &v("1.0.12")).is_err());
+    commands::verify_version_is_not_downgrade(&locator, &v("1.0.15")).unwrap();
+
+    // an allowed downgrade lowers the mark, so updates newer than the downgraded version are accepted again
+    commands::record_applied_package_version(&locator, true).unwrap();
+    assert_eq!(Some(v("1.0.11")), locator.get_highest_applied_version());
+    commands::verify_version_is_not_downgrade(&locator, &v("1.0.12")).unwrap();
+
+    // a normal apply never lowers the mark
+    locator.record_applied_version(&v("1.0.15")).unwrap();
+    commands::record_applied_package_version(&locator, false).unwrap();
+    assert_eq!(Some(v("1.0.15")), locator.get_highest_applied_version());
+}
+
 #[test]
 pub fn test_delta_apply_legacy() {
     dialogs::set_silent(true);
@@ -254,3 +336,105 @@ pub fn test_delta_apply_legacy() {
         assert!(extracted.exists());
     }
 }
+
+fn create_test_zip(path: &Path, files: &[(&str, &[u8])]) {
+    create_test_zip_with_method(path, files, zip::CompressionMethod::Deflated);
+}
+
+fn create_test_zip_with_method(path: &Path, files: &[(&str, &[u8])], method: zip::CompressionMethod) {
+    use std::io::Write;
+    let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+    for (name, contents) in files {
+        zip.start_file(*name, zip::write::SimpleFileOptions::default().compression_method(method))
+            .unwrap();
+        zip.write_all(contents).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+fn read_test_zip(path: &Path) -> Vec<(String, Vec<u8>)> {
+    use std::io::Read;
+    let mut zip = zip::ZipArchive::new(fs::File::open(path).unwrap()).unwrap();
+    let mut files = Vec::new();
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i).unwrap();
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).unwrap();
+        files.push((file.name().to_owned(), contents));
+    }
+    files.sort();
+    files
+}
+
+fn create_test_delta(dir: &Path, shasum: Option<&str>) -> (PathBuf, PathBuf) {
+    let base = dir.join("TestApp-1.0.0-full.nupkg");
+    create_test_zip(&base, &[("TestApp.nuspec", b"<package />"), ("lib/app/app.txt", b"old contents")]);
+
+    let patch = zstd::bulk::Compressor::with_dictionary(3, b"old contents")
+        .unwrap()
+        .compress(b"new contents")
+        .unwrap();
+    let delta = dir.join("TestApp-1.0.1-delta.nupkg");
+    let mut entries: Vec<(&str, &[u8])> = vec![("TestApp.nuspec", b"<package />"), ("lib/app/app.txt.zsdiff", &patch)];
+    if let Some(shasum) = shasum {
+        entries.push(("lib/app/app.txt.shasum", shasum.as_bytes()));
+    }
+    create_test_zip(&delta, &entries);
+    (base, delta)
+}
+
+#[test]
+pub fn test_delta_verifies_patched_files() {
+    let tmp_dir = tempdir().unwrap();
+    let output = tmp_dir.path().join("TestApp-1.0.1-full.nupkg");
+
+    // sha1 of "new contents"
+    let (base, delta) = create_test_delta(tmp_dir.path(), Some("7FFA72B76D5D66DA37F4B614B7A822C01F23C183 app.txt 12"));
+    commands::delta(&base, vec![&delta], tmp_dir.path().join("work1"), &output).unwrap();
+    assert!(output.exists());
+
+    // a patched file which does not match its shasum fails, naming the file
+    let (base, delta) = create_test_delta(tmp_dir.path(), Some("0000000000000000000000000000000000000000 app.txt 12"));
+    let result = commands::delta(&base, vec![&delta], tmp_dir.path().join("work2"), &output);
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("app.txt"), "{}", err);
+
+    // an empty or missing shasum does not skip verification
+    let (base, delta) = create_test_delta(tmp_dir.path(), Some(""));
+    let err = commands::delta(&base, vec![&delta], tmp_dir.path().join("work3"), &output)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("app.txt"), "{}", err);
+
+    let (base, delta) = create_test_delta(tmp_dir.path(), None);
+    let err = commands::delta(&base, vec![&delta], tmp_dir.path().join("work4"), &output)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("app.txt"), "{}", err);
+}
+
+#[test]
+pub fn test_delta_output_matches_full_package_file_by_file() {
+    let tmp_dir = tempdir().unwrap();
+    let output = tmp_dir.path().join("TestApp-1.0.1-full.nupkg");
+    let (base, delta) = create_test_delta(tmp_dir.path(), Some("7FFA72B76D5D66DA37F4B614B7A822C01F23C183 app.txt 12"));
+
+    // the published full package is written by vpk with a different zip writer
+    let published = tmp_dir.path().join("published").join("TestApp-1.0.1-full.nupkg");
+    fs::create_dir_all(published.parent().unwrap()).unwrap();
+    create_test_zip_with_method(
+        &published,
+        &[("TestApp.nuspec", b"<package />"), ("lib/app/app.txt", b"new contents")],
+        zip::CompressionMethod::Stored,
+    );
+
+    commands::delta(&base, vec![&delta], tmp_dir.path().join("work"), &output).unwrap();
+
+    let (_, published_sha256) = velopack::misc::calculate_sha1_sha256(&published).unwrap();
+    let (_, output_sha256) = velopack::misc::calculate_sha1_sha256(&output).unwrap();
+    assert_ne!(
+        published_sha256, output_sha256,
+        "The patched package is re-compressed, so its hash can not match"
+    );
+    assert_eq!(read_test_zip(&output), read_test_zip(&published));
+}
@@ -0,0 +1,146 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+use velopack_bins::*;
+
+/// Archive entry names which must never be extracted, each paired with a description for the assertion message.
+const MALICIOUS_ENTRIES: &[(&str, &str)] = &[
+    ("../evil.txt", "parent directory"),
+    ("lib/app/../../../evil.txt", "nested parent directory"),
+    ("lib\\..\\..\\evil.txt", "parent directory with backslashes"),
+    ("/tmp/evil.txt", "absolute unix path"),
+    ("\\evil.txt", "rooted windows path"),
+    ("C:/evil.txt", "drive letter"),
+    ("C:evil.txt", "drive relative path"),
+    ("lib/app.exe:hidden", "alternate data stream"),
+];
+
+fn create_zip(path: &Path, files: &[(&str, &[u8])]) {
+    let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+    for (name, contents) in files {
+        zip.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+        zip.write_all(contents).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+fn create_malicious_corpus(dir: &Path) -> Vec<(PathBuf, String)> {
+    let mut corpus = Vec::new();
+    for (i, (name, description)) in MALICIOUS_ENTRIES.iter().enumerate() {
+        let path = dir.join(format!("malicious_{}.zip", i));
+        create_zip(&path, &[("TestApp.nuspec", b"<package />"), (name, b"evil")]);
+        corpus.push((path, description.to_string()));
+    }
+
+    // names which only differ by case are distinct files on case-sensitive file systems
+    if cfg!(any(target_os = "windows", target_os = "macos")) {
+        let path = dir.join("case_collision.zip");
+        create_zip(&path, &[("lib/app/App.dll", b"good"), ("lib/app/app.dll", b"evil")]);
+        corpus.push((path, "case colliding entries".to_string()));
+    }
+
+    let path = dir.join("duplicate.zip");
+    create_zip(&path, &[("lib/app/app.dll", b"good"), ("lib/./app/app.dll", b"evil")]);
+    corpus.push((path, "duplicate entries".to_string()));
+
+    corpus
+}
+
+#[test]
+pub fn test_extract_rejects_malicious_zips() {
+    let tmp_dir = tempdir().unwrap();
+    for (zip_path, description) in create_malicious_corpus(tmp_dir.path()) {
+        let target = tmp_dir.path().join("extract").join(zip_path.file_stem().unwrap());
+        fs::create_dir_all(&target).unwrap();
+        let result = shared::fastzip::extract_to_directory(&zip_path, &target, None);
+        assert!(result.is_err(), "Extracting a zip with {} should fail", description);
+    }
+    assert!(!tmp_dir.path().join("evil.txt").exists());
+    assert!(!tmp_dir.path().join("extract").join("evil.txt").exists());
+}
+
+#[test]
+pub fn test_extract_allows_entries_differing_by_case_on_case_sensitive_platforms() {
+    let tmp_dir = tempdir().unwrap();
+    let zip_path = tmp_dir.path().join("case.zip");
+    create_zip(&zip_path, &[("lib/app/App.dll", b"upper"), ("lib/app/app.dll", b"lower")]);
+    let target = tmp_dir.path().join("extract");
+    let result = shared::fastzip::extract_to_directory(&zip_path, &target, None);
+    if cfg!(any(target_os = "windows", target_os = "macos")) {
+        assert!(result.is_err());
+    } else {
+        result.unwrap();
+        assert_eq!(fs::read(target.join("lib").join("app").join("App.dll")).unwrap(), b"upper");
+        assert_eq!(fs::read(target.join("lib").join("app").join("app.dll")).unwrap(), b"lower");
+    }
+}
+
+#[test]
+pub fn test_extract_allows_normal_zip() {
+    let tmp_dir = tempdir().unwrap();
+    let zip_path = tmp_dir.path().join("normal.zip");
+    create_zip(
+        &zip_path,
+        &[
+            ("TestApp.nuspec", b"<package />"),
+            ("lib/app/app.dll", b"good"),
+            ("lib\\app\\other.dll", b"good"),
+        ],
+    );
+    let target = tmp_dir.path().join("extract");
+    shared::fastzip::extract_to_directory(&zip_path, &target, None).unwrap();
+    assert_eq!(fs::read(target.join("lib").join("app").join("app.dll")).unwrap(), b"good");
+    assert_eq!(fs::read(target.join("lib").join("app").join("other.dll")).unwrap(), b"good");
+}
+
+#[cfg(unix)]
+#[test]
+pub fn test_extract_does_not_write_through_symlinks() {
+    let tmp_dir = tempdir().unwrap();
+    let outside = tmp_dir.path().join("outside");
+    let target = tmp_dir.path().join("extract");
+    fs::create_dir_all(&outside).unwrap();
+    fs::create_dir_all(&target).unwrap();
+    std::os::unix::fs::symlink(&outside, target.join("lib")).unwrap();
+
+    let zip_path = tmp_dir.path().join("symlink.zip");
+    create_zip(&zip_path, &[("lib/evil.txt", b"evil")]);
+    assert!(shared::fastzip::extract_to_directory(&zip_path, &target, None).is_err());
+    assert!(!outside.join("evil.txt").exists());
+}
+
+#[test]
+pub fn test_delta_rejects_malicious_zips() {
+    let tmp_dir = tempdir().unwrap();
+    let base = tmp_dir.path().join("TestApp-1.0.0-full.nupkg");
+    create_zip(&base, &[("TestApp.nuspec", b"<package />"), ("lib/app/app.dll", b"good")]);
+
+    for (i, (zip_path, description)) in create_malicious_corpus(tmp_dir.path()).into_iter().enumerate() {
+        let output = tmp_dir.path().join(format!("output_{}.nupkg", i));
+        let result = commands::delta(&base, vec![&zip_path], tmp_dir.path().join(format!("work_{}", i)), &output);
+        assert!(result.is_err(), "Applying a delta with {} should fail", description);
+    }
+    assert!(!tmp_dir.path().join("evil.txt").exists());
+}
+
+#[cfg(not(target_os = "linux"))]
+#[test]
+pub fn test_bundle_extract_rejects_malicious_zips() {
+    let tmp_dir = tempdir().unwrap();
+    for (zip_path, description) in create_malicious_corpus(tmp_dir.path()) {
+        let target = tmp_dir.path().join("current");
+        let bundle = velopack::bundle::load_bundle_from_file(&zip_path).unwrap();
+        let result = bundle.extract_lib_contents_to_path(&target, |_| {});
+        assert!(result.is_err(), "Extracting a bundle with {} should fail", description);
+    }
+
+    let zip_path = tmp_dir.path().join("symlink.zip");
+    create_zip(
+        &zip_path,
+        &[("TestApp.nuspec", b"<package />"), ("lib/app/escape.__symlink", b"../../../outside")],
+    );
+    let bundle = velopack::bundle::load_bundle_from_file(&zip_path).unwrap();
+    assert!(bundle.extract_lib_contents_to_path(tmp_dir.path().join("current2"), |_| {}).is_err());
+}
@@ -10,11 +10,13 @@
 var templatesDir = Path.Combine(scriptsDir, "Templates");
 var files = Directory.EnumerateFiles(librustDir, "*.rs", SearchOption.AllDirectories);
 
+// structs are generated in this order, so a struct must be listed after any structs it contains
 string[] desiredStructs = [
+    "VelopackLocatorConfig",
     "VelopackAsset",
     "UpdateInfo",
+    "HttpClientConfig",
     "UpdateOptions",
-    "VelopackLocatorConfig",
 ];
 
 List<RustStruct> availableStructs = new();
@@ -34,6 +36,8 @@
     }
 }
 
+availableStructs = availableStructs.OrderBy(s => Array.IndexOf(desiredStructs, s.Name)).ToList();
+
 if (desiredStructs.Length != availableStructs.Count) {
     Console.WriteLine("Not all structs were found.");
     Console.WriteLine("Desired structs: " + string.Join(", ", desiredStructs));
@@ -52,6 +56,7 @@
     TypeMap.RustStruct("VelopackAsset", "vpkc_asset_t"),
     TypeMap.RustStruct("UpdateInfo", "vpkc_update_info_t"),
     TypeMap.RustStruct("UpdateOptions", "vpkc_update_options_t"),
+    TypeMap.RustStruct("HttpClientConfig", "vpkc_http_client_config_t"),
     TypeMap.RustStruct("VelopackLocatorConfig", "vpkc_locator_config_t"),
     TypeMap.SystemType("String", "char", "string", "c_char"),
     TypeMap.SystemType("PathBuf", "char", "string", "c_char"),
@@ -71,13 +76,14 @@
             field_name = f.Name,
             field_optional = f.Optional,
             field_vector = f.Vec,
+            field_map = f.Map,
             field_rust_type = f.Type,
             field_c_type = types[f.Type].interopType,
             field_cpp_type = types[f.Type].cppType,
             field_node_type = types[f.Type].nodeType,
             field_system = types[f.Type].system,
             field_primitive = types[f.Type].primitive,
-            field_normal = !f.Vec && !types[f.Type].primitive,
+            field_normal = !f.Vec && !f.Map && !types[f.Type].primitive,
         };
         return field;
     }).ToArray();
@@ -215,6 +221,7 @@ class RustStruct_Field
     public bool field_primitive;
     public bool field_optional;
     public bool field_vector;
+    public bool field_map;
     public bool field_system;
     public bool field_normal;
     public bool field_primitive_or_system => field_primitive || field_system;
@@ -11,6 +11,7 @@ public class RustField
     public string Type { get; set; }
     public bool Optional { get; set; }
     public bool Vec { get; set; }
+    public bool Map { get; set; }
 }
 
 public class RustStruct
@@ -48,7 +49,7 @@ public static class StructParser
         .Match(Span.EqualTo("pub"), RustToken.KeywordPub)
         .Match(Span.EqualTo("struct"), RustToken.KeywordStruct)
         .Match(Span.EqualTo("impl"), RustToken.KeywordImpl)
-        .Match(Span.Regex(@"[a-zA-Z_][a-zA-Z0-9_<>]*"), RustToken.Identifier)
+        .Match(Span.Regex(@"[a-zA-Z_][a-zA-Z0-9_]*(<([a-zA-Z0-9_<>]|, )*>)?"), RustToken.Identifier)
         .Match(Character.EqualTo('{'), RustToken.OpenBrace)
         .Match(Character.EqualTo('}'), RustToken.CloseBrace)
         .Match(Character.EqualTo(':'), RustToken.Colon)
@@ -159,6 +160,13 @@ public static IEnumerable<RustStruct> ParseStructs(string code)
                     f.Optional = true;
                 }
 
+                var match3 = Regex.Match(f.Type, @"^HashMap<String, (.*)>$");
+                // If the field type is a HashMap, extract the value type and set Map to true (keys are always strings)
+                if (match3.Success) {
+                    f.Type = match3.Groups[1].Value;
+                    f.Map = true;
+                }
+
                 var match2 = Regex.Match(f.Type, @"Vec<(.*)>");
                 // If the field type is an Vec, extract the inner type and set Vec to true
                 if (match2.Success) {
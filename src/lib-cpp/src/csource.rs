@@ -114,9 +114,17 @@ mod tests {
             FileName: "TestApp-2.0.0-full.nupkg".to_string(),
             SHA1: "abc123".to_string(),
             SHA256: "def456".to_string(),
+            Hashes: HashMap::new(),
             Size: 1048576,
             NotesMarkdown: String::new(),
             NotesHtml: String::new(),
+            Arch: None,
+            OS: None,
+            MinOSVersion: None,
+            RolloutPercentage: 0,
+            IsMandatory: false,
+            Retracted: false,
+            BaseVersion: None,
         }
     }
 
@@ -290,3 +298,31 @@ mod tests {
         assert!(progress.contains(&100), "Should contain final 100 progress");
     }
 }
+
+#[derive(Clone)]
+pub struct CTokenProvider {
+    pub p_user_data: *mut c_void,
+    pub cb_token_provider: vpkc_token_provider_delegate_t,
+    pub cb_free_token: vpkc_free_token_t,
+}
+
+unsafe impl Send for CTokenProvider {}
+unsafe impl Sync for CTokenProvider {}
+
+impl CTokenProvider {
+    pub fn get_token(&self) -> Result<String, Error> {
+        if let Some(cb_token_provider) = self.cb_token_provider {
+            let token_ptr = (cb_token_provider)(self.p_user_data);
+            let token = c_to_String(token_ptr)
+                .map_err(|_| Error::Other("User vpkc_token_provider_delegate_t returned a null pointer instead of a token".to_string()))?;
+            if let Some(cb_free_token) = self.cb_free_token {
+                (cb_free_token)(self.p_user_data, token_ptr); // Free the C string returned by the callback
+            } else {
+                log::error!("User vpkc_free_token_t is null, this may be a memory leak");
+            }
+            Ok(token)
+        } else {
+            Err(Error::Other("User vpkc_token_provider_delegate_t is null".to_string()))
+        }
+    }
+}
@@ -50,6 +50,85 @@ pub extern "C" fn vpkc_new_source_http_url(psz_http_url: *const c_char) -> *mut
     }
 }
 
+/// Create a new HttpSource update source for a given HTTP URL, which sends extra headers and/or credentials with every request.
+/// If more than one authentication method is provided, the token provider takes precedence over the bearer token, which
+/// takes precedence over basic authentication. Note that the callbacks must be valid for the lifetime of any UpdateManager's
+/// that use this source.
+/// @param psz_http_url The URL to a remote update server.
+/// @param p_header_names Optional array of extra header names to send with every request (can be null).
+/// @param p_header_values Optional array of extra header values, in the same order as p_header_names (can be null).
+/// @param c_headers The number of elements in p_header_names and p_header_values.
+/// @param psz_username Optional username for HTTP basic authentication (can be null).
+/// @param psz_password Optional password for HTTP basic authentication (can be null).
+/// @param psz_bearer_token Optional static bearer token (can be null).
+/// @param cb_token_provider Optional callback which returns a bearer token before every request (can be null).
+/// @param cb_free_token A callback to free the memory allocated by `cb_token_provider`. Required if cb_token_provider is provided.
+/// @param p_user_data Optional user data to be passed to the callbacks.
+/// @returns A new vpkc_update_source_t instance, or null on error. If null, the error will be available via `vpkc_get_last_error`.
+#[no_mangle]
+#[logfn(Trace)]
+#[logfn_inputs(Trace)]
+pub extern "C" fn vpkc_new_source_http_url_with_auth(
+    psz_http_url: *const c_char,
+    p_header_names: *mut *mut c_char,
+    p_header_values: *mut *mut c_char,
+    c_headers: size_t,
+    psz_username: *const c_char,
+    psz_password: *const c_char,
+    psz_bearer_token: *const c_char,
+    cb_token_provider: vpkc_token_provider_delegate_t,
+    cb_free_token: vpkc_free_token_t,
+    p_user_data: *mut c_void,
+) -> *mut vpkc_update_source_t {
+    let update_url = match c_to_String(psz_http_url) {
+        Ok(url) => url,
+        Err(_) => {
+            log::error!("psz_http_url is null");
+            set_last_error("psz_http_url must not be null");
+            return ptr::null_mut();
+        }
+    };
+
+    let header_names = c_to_String_vec(p_header_names, c_headers);
+    let header_values = c_to_String_vec(p_header_values, c_headers);
+    let (header_names, header_values) = match (header_names, header_values) {
+        (Ok(names), Ok(values)) if names.len() == values.len() => (names, values),
+        _ => {
+            log::error!("p_header_names and p_header_values must both contain c_headers elements");
+            set_last_error("p_header_names and p_header_values must both contain c_headers elements");
+            return ptr::null_mut();
+        }
+    };
+
+    if cb_token_provider.is_some() && cb_free_token.is_none() {
+        log::error!("cb_free_token must not be null");
+        set_last_error("cb_free_token must not be null");
+        return ptr::null_mut();
+    }
+
+    let mut source = sources::HttpSource::new(update_url);
+    for (name, value) in header_names.iter().zip(header_values.iter()) {
+        source = source.with_header(name, value);
+    }
+    if let Ok(username) = c_to_String(psz_username) {
+        let password = c_to_String(psz_password).unwrap_or_default();
+        source = source.with_basic_auth(username, password);
+    }
+    if let Ok(token) = c_to_String(psz_bearer_token) {
+        source = source.with_bearer_token(token);
+    }
+    if cb_token_provider.is_some() {
+        let provider = CTokenProvider {
+            p_user_data,
+            cb_token_provider,
+            cb_free_token,
+        };
+        source = source.with_token_provider(move || provider.get_token());
+    }
+
+    UpdateSourceRawPtr::new(Box::new(source))
+}
+
 /// Create a new GithubSource update source for a GitHub repository.
 /// @param psz_repo_url The GitHub repository URL (e.g. "https://github.com/user/repo").
 /// @param psz_access_token Optional access token for private repositories (can be null).
@@ -293,6 +372,43 @@ pub extern "C" fn vpkc_is_portable(p_manager: *mut vpkc_update_manager_t) -> boo
     }
 }
 
+/// Leaves background download mode, so that any download in progress continues at the maximum download rate.
+/// This has no effect unless `BackgroundDownloadBytesPerSecond` was set in the update options.
+/// @param p_manager The update manager instance.
+#[no_mangle]
+#[logfn(Trace)]
+#[logfn_inputs(Trace)]
+pub extern "C" fn vpkc_boost_downloads(p_manager: *mut vpkc_update_manager_t) {
+    if let Some(manager) = p_manager.to_opaque_ref() {
+        manager.boost_downloads();
+    }
+}
+
+/// Changes the maximum download rate. This applies immediately, including to any download which is already in progress.
+/// @param p_manager The update manager instance.
+/// @param bytes_per_second The maximum download rate in bytes per second, or 0 for unlimited.
+#[no_mangle]
+#[logfn(Trace)]
+#[logfn_inputs(Trace)]
+pub extern "C" fn vpkc_set_maximum_download_rate(p_manager: *mut vpkc_update_manager_t, bytes_per_second: u64) {
+    if let Some(manager) = p_manager.to_opaque_ref() {
+        manager.set_maximum_download_rate(bytes_per_second);
+    }
+}
+
+/// Cancels the download which is currently in progress (if any). `vpkc_download_updates` will return false as soon
+/// as possible, and the last error will indicate that the operation was cancelled. A partially downloaded package is
+/// kept, so the download can be resumed by calling `vpkc_download_updates` again.
+/// @param p_manager The update manager instance.
+#[no_mangle]
+#[logfn(Trace)]
+#[logfn_inputs(Trace)]
+pub extern "C" fn vpkc_cancel_downloads(p_manager: *mut vpkc_update_manager_t) {
+    if let Some(manager) = p_manager.to_opaque_ref() {
+        manager.cancel_downloads();
+    }
+}
+
 /// Returns an asset if there is an update downloaded which still needs to be applied.
 /// You can pass this asset to `vpkc_wait_exit_then_apply_updates` to apply the update.
 /// @param p_manager The update manager instance.
@@ -318,7 +434,7 @@ pub extern "C" fn vpkc_update_pending_restart(p_manager: *mut vpkc_update_manage
 /// UpdateInfo object containing the latest available release, and any delta updates that can be applied if they are available.
 /// @param p_manager The update manager instance.
 /// @param p_update A pointer to where the new vpkc_update_info_t* instance will be stored if an update is available.
-/// @returns A `vpkc_update_check_t` value indicating the result of the check. If an update is available, the value will be `HasUpdate` and `p_update` will be populated.
+/// @returns A `vpkc_update_check_t` value indicating the result of the check. If an update is available, the value will be `HasUpdate` and `p_update` will be populated. If there is a newer release which can not run on this machine (and no compatible update), the value will be `UPDATE_INCOMPATIBLE`. If the current version is no longer supported, the value will be `UPDATE_AVAILABLE` and the populated update will have `IsRequired` set.
 #[no_mangle]
 #[logfn(Trace)]
 #[logfn_inputs(Trace)]
@@ -331,6 +447,7 @@ pub extern "C" fn vpkc_check_for_updates(p_manager: *mut vpkc_update_manager_t,
             }
             Ok(UpdateCheck::RemoteIsEmpty) => vpkc_update_check_t::REMOTE_IS_EMPTY,
             Ok(UpdateCheck::NoUpdateAvailable) => vpkc_update_check_t::NO_UPDATE_AVAILABLE,
+            Ok(UpdateCheck::UpdateIncompatible(_)) => vpkc_update_check_t::UPDATE_INCOMPATIBLE,
             Err(e) => {
                 set_last_error(&format!("{:?}", e));
                 vpkc_update_check_t::UPDATE_ERROR
@@ -451,6 +568,36 @@ pub extern "C" fn vpkc_wait_exit_then_apply_updates(
     })
 }
 
+/// This will launch the Velopack updater and tell it to wait for this program to exit gracefully, as described in
+/// `vpkc_wait_exit_then_apply_updates`. Unlike that function, this takes the UpdateInfo from `vpkc_check_for_updates`,
+/// so the updater is allowed to apply an older version if the update is a downgrade (eg. to replace a retracted release).
+/// @param p_manager The update manager instance.
+/// @param p_update The update info to apply, from `vpkc_check_for_updates`.
+/// @param b_silent True to attempt to apply the update without showing any UI.
+/// @param b_restart True to restart the app after the update is applied.
+/// @param p_restart_args An array of command line arguments to pass to the new process when it's restarted.
+/// @param c_restart_args The number of arguments in `p_restart_args`.
+/// @returns true on success, false on failure. If false, the error will be available via `vpkc_get_last_error`.
+#[no_mangle]
+#[logfn(Trace)]
+#[logfn_inputs(Trace)]
+pub extern "C" fn vpkc_wait_exit_then_apply_update_info(
+    p_manager: *mut vpkc_update_manager_t,
+    p_update: *mut vpkc_update_info_t,
+    b_silent: bool,
+    b_restart: bool,
+    p_restart_args: *mut *mut c_char,
+    c_restart_args: size_t,
+) -> bool {
+    wrap_error(|| {
+        let manager = p_manager.to_opaque_ref().ok_or(anyhow!("pManager must not be null"))?;
+        let update = c_to_UpdateInfo(p_update)?;
+        let restart_args = c_to_String_vec(p_restart_args, c_restart_args)?;
+        manager.wait_exit_then_apply_updates(&update, b_silent, b_restart, &restart_args)?;
+        Ok(())
+    })
+}
+
 /// This will launch the Velopack updater and optionally wait for a program to exit gracefully.
 /// This method is unsafe because it does not necessarily wait for any / the correct process to exit
 /// before applying updates. The `vpkc_wait_exit_then_apply_updates` method is recommended for most use cases.
@@ -716,6 +863,7 @@ pub extern "C" fn vpkc_set_logger(cb_log: vpkc_log_callback_t, p_user_data: *mut
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
 
     #[test]
     fn noteshtml_roundtrip_through_c_structs() {
@@ -726,9 +874,17 @@ mod tests {
             FileName: "TestApp-2.0.0-full.nupkg".to_string(),
             SHA1: "abc123".to_string(),
             SHA256: "def456".to_string(),
+            Hashes: HashMap::from([("blake3".to_string(), "789abc".to_string()), ("sha512".to_string(), "def012".to_string())]),
             Size: 1048576,
             NotesMarkdown: "# Release v2".to_string(),
             NotesHtml: "<h1>Release v2</h1>".to_string(),
+            Arch: Some("arm64".to_string()),
+            OS: None,
+            MinOSVersion: Some("10.0.19041".to_string()),
+            RolloutPercentage: 25,
+            IsMandatory: true,
+            Retracted: true,
+            BaseVersion: Some("1.0.0".to_string()),
         };
 
         let c_asset = unsafe { allocate_VelopackAsset(&asset) };
@@ -741,6 +897,14 @@ mod tests {
         let roundtripped = c_to_VelopackAsset(c_asset).unwrap();
         assert_eq!(roundtripped.NotesMarkdown, "# Release v2");
         assert_eq!(roundtripped.NotesHtml, "<h1>Release v2</h1>");
+        assert_eq!(roundtripped.Hashes, asset.Hashes);
+        assert_eq!(roundtripped.Arch.as_deref(), Some("arm64"));
+        assert_eq!(roundtripped.OS, None);
+        assert_eq!(roundtripped.MinOSVersion.as_deref(), Some("10.0.19041"));
+        assert_eq!(roundtripped.RolloutPercentage, 25);
+        assert!(roundtripped.IsMandatory);
+        assert!(roundtripped.Retracted);
+        assert_eq!(roundtripped.BaseVersion.as_deref(), Some("1.0.0"));
 
         unsafe { free_VelopackAsset(c_asset) };
     }
@@ -765,13 +929,24 @@ mod tests {
                 FileName: "App-3.0.0-full.nupkg".to_string(),
                 SHA1: "sha1".to_string(),
                 SHA256: "sha256".to_string(),
+                Hashes: HashMap::new(),
                 Size: 5000,
                 NotesMarkdown: "## v3 notes".to_string(),
                 NotesHtml: "<h2>v3 notes</h2>".to_string(),
+                Arch: None,
+                OS: None,
+                MinOSVersion: None,
+                RolloutPercentage: 0,
+                IsMandatory: false,
+                Retracted: false,
+                BaseVersion: None,
             },
             BaseRelease: None,
             DeltasToTarget: Vec::new(),
             IsDowngrade: false,
+            IsRequired: true,
+            DownloadSize: 5000,
+            StrategyReason: "full".to_string(),
         };
 
         let c_update = unsafe { allocate_UpdateInfo(&update) };
@@ -782,6 +957,9 @@ mod tests {
         assert_eq!(roundtripped.TargetFullRelease.NotesHtml, "<h2>v3 notes</h2>");
         assert!(roundtripped.BaseRelease.is_none());
         assert!(roundtripped.DeltasToTarget.is_empty());
+        assert!(roundtripped.IsRequired);
+        assert_eq!(roundtripped.DownloadSize, 5000);
+        assert_eq!(roundtripped.StrategyReason, "full");
 
         unsafe { free_UpdateInfo(c_update) };
     }
@@ -26,6 +26,15 @@ impl UpdateSource for SharedSource {
     ) -> Result<(), velopack::Error> {
         self.0.download_release_entry(asset, local_file, progress_sender)
     }
+
+    fn download_release_entry_with_hashes(
+        &self,
+        asset: &velopack::VelopackAsset,
+        local_file: &std::path::Path,
+        progress_sender: Option<std::sync::mpsc::Sender<i16>>,
+    ) -> Result<Option<velopack::FileHashes>, velopack::Error> {
+        self.0.download_release_entry_with_hashes(asset, local_file, progress_sender)
+    }
 }
 
 pub trait RawPtrExt<'a, T>: Sized {
@@ -1,7 +1,7 @@
 import * as addon from "./load";
 
-import type { UpdateInfo, UpdateOptions, VelopackLocatorConfig, VelopackAsset } from "./types";
-export { UpdateInfo, UpdateOptions, VelopackLocatorConfig, VelopackAsset };
+import type { UpdateInfo, UpdateOptions, HttpClientConfig, VelopackLocatorConfig, VelopackAsset } from "./types";
+export { UpdateInfo, UpdateOptions, HttpClientConfig, VelopackLocatorConfig, VelopackAsset };
 
 type UpdateManagerOpaque = {};
 declare module "./load" {
@@ -9,6 +9,8 @@ declare module "./load" {
     urlOrPath: string,
     options: string | null,
     locator: string | null,
+    httpAuth?: string | null,
+    tokenProvider?: (() => string) | null,
   ): UpdateManagerOpaque;
 
   function js_get_current_version(um: UpdateManagerOpaque): string;
@@ -17,6 +19,15 @@ declare module "./load" {
 
   function js_is_portable(um: UpdateManagerOpaque): boolean;
 
+  function js_boost_downloads(um: UpdateManagerOpaque): void;
+
+  function js_set_maximum_download_rate(
+    um: UpdateManagerOpaque,
+    bytesPerSecond: number,
+  ): void;
+
+  function js_cancel_downloads(um: UpdateManagerOpaque): void;
+
   function js_update_pending_restart(
     um: UpdateManagerOpaque,
   ): string | null;
@@ -192,6 +203,41 @@ export class VelopackApp {
   }
 }
 
+/**
+ * Extra headers and credentials for HttpSource to send with every request.
+ */
+export type HttpSourceOptions = {
+  /** Extra headers to send with every request (e.g. an API key). */
+  headers?: Record<string, string>;
+  /** Username for HTTP basic authentication. */
+  username?: string;
+  /** Password for HTTP basic authentication. */
+  password?: string;
+  /** A static bearer token. Takes precedence over basic authentication. */
+  bearerToken?: string;
+  /**
+   * Returns a bearer token, and is called before every request so that short-lived tokens can be refreshed.
+   * Takes precedence over bearerToken and basic authentication.
+   */
+  tokenProvider?: () => string;
+};
+
+/**
+ * An update source which retrieves updates from a static file host or other web server,
+ * and can send extra headers and credentials with every request.
+ */
+export class HttpSource {
+  /**
+   * Create a new HttpSource.
+   * @param url The base URL of the update server.
+   * @param options Extra headers and credentials to send with every request.
+   */
+  constructor(
+    readonly url: string,
+    readonly options: HttpSourceOptions = {},
+  ) {}
+}
+
 /**
  * Provides functionality for checking for updates, downloading updates, and applying updates to the current application.
  */
@@ -200,20 +246,31 @@ export class UpdateManager {
 
   /**
    * Create a new UpdateManager instance.
-   * @param urlOrPath Location of the update server or path to the local update directory.
+   * @param urlOrPath Location of the update server or path to the local update directory, or an HttpSource.
    * @param options Optional extra configuration for update manager.
    * @param locator Override the default locator configuration (usually used for testing / mocks).
    */
   constructor(
-    urlOrPath: string,
+    urlOrPath: string | HttpSource,
     options?: UpdateOptions,
     locator?: VelopackLocatorConfig,
   ) {
-    this.opaque = addon.js_new_update_manager(
-      urlOrPath,
-      options ? JSON.stringify(options) : "",
-      locator ? JSON.stringify(locator) : null,
-    );
+    if (urlOrPath instanceof HttpSource) {
+      const { tokenProvider, ...auth } = urlOrPath.options;
+      this.opaque = addon.js_new_update_manager(
+        urlOrPath.url,
+        options ? JSON.stringify(options) : "",
+        locator ? JSON.stringify(locator) : null,
+        JSON.stringify(auth),
+        tokenProvider ?? null,
+      );
+    } else {
+      this.opaque = addon.js_new_update_manager(
+        urlOrPath,
+        options ? JSON.stringify(options) : "",
+        locator ? JSON.stringify(locator) : null,
+      );
+    }
   }
 
   /**
@@ -238,6 +295,31 @@ export class UpdateManager {
     return addon.js_is_portable(this.opaque);
   }
 
+  /**
+   * Leaves background download mode, so that any download in progress continues at the maximum download rate.
+   * This has no effect unless BackgroundDownloadBytesPerSecond was set in the UpdateOptions.
+   */
+  boostDownloads(): void {
+    addon.js_boost_downloads(this.opaque);
+  }
+
+  /**
+   * Changes the maximum download rate in bytes per second (0 is unlimited). This applies immediately,
+   * including to any download which is already in progress.
+   */
+  setMaximumDownloadRate(bytesPerSecond: number): void {
+    addon.js_set_maximum_download_rate(this.opaque, bytesPerSecond);
+  }
+
+  /**
+   * Cancels the download which is currently in progress (if any). The promise returned by downloadUpdateAsync
+   * will be rejected as soon as possible, and a partially downloaded package is kept so the download can be
+   * resumed by calling downloadUpdateAsync again.
+   */
+  cancelDownloads(): void {
+    addon.js_cancel_downloads(this.opaque);
+  }
+
   /**
    * Returns an VelopackAsset object if there is an update downloaded which still needs to be applied.
    * You can pass the VelopackAsset object to waitExitThenApplyUpdate to apply the update.
@@ -253,6 +335,7 @@ export class UpdateManager {
   /**
    * Checks for updates, returning None if there are none available. If there are updates available, this method will return an
    * UpdateInfo object containing the latest available release, and any delta updates that can be applied if they are available.
+   * If the current version is no longer supported, the returned UpdateInfo will have IsRequired set.
    */
   checkForUpdatesAsync(): Promise<UpdateInfo | null> {
     let json: Promise<string | null> = addon.js_check_for_updates_async(
@@ -303,14 +386,15 @@ export class UpdateManager {
       throw new Error("update is required");
     }
 
-    // the backend API only accepts VelopackAsset, so we need to extract it from UpdateInfo
-    if ("TargetFullRelease" in update && typeof update.TargetFullRelease === "object") {
-      update = update.TargetFullRelease;
-    }
+    // the backend API only accepts UpdateInfo (which carries flags such as IsDowngrade), so wrap a plain VelopackAsset
+    const updateInfo =
+      "TargetFullRelease" in update && typeof update.TargetFullRelease === "object"
+        ? update
+        : { TargetFullRelease: update };
 
     addon.js_wait_exit_then_apply_update(
       this.opaque,
-      JSON.stringify(update),
+      JSON.stringify(updateInfo),
       silent,
       restart,
       restartArgs,
@@ -1,7 +1,9 @@
 use locator::*;
 use neon::prelude::*;
 use semver::Version;
+use serde::Deserialize;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -33,6 +35,61 @@ fn args_get_locator(cx: &mut FunctionContext, i: usize) -> NeonResult<Option<Vel
     Ok(None)
 }
 
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct HttpSourceAuth {
+    headers: HashMap<String, String>,
+    username: Option<String>,
+    password: Option<String>,
+    bearer_token: Option<String>,
+}
+
+fn args_get_http_source(cx: &mut FunctionContext, url: &str, i: usize) -> NeonResult<Option<HttpSource>> {
+    let arg_auth = match cx.argument_opt(i) {
+        Some(js_value) if js_value.is_a::<JsString, _>(cx) => js_value.downcast_or_throw::<JsString, _>(cx)?.value(cx),
+        _ => return Ok(None),
+    };
+    let auth = serde_json::from_str::<HttpSourceAuth>(&arg_auth).or_else(|e| cx.throw_error(e.to_string()))?;
+
+    let mut source = HttpSource::new(url);
+    for (name, value) in auth.headers {
+        source = source.with_header(name, value);
+    }
+    if let Some(username) = auth.username {
+        source = source.with_basic_auth(username, auth.password.unwrap_or_default());
+    }
+    if let Some(token) = auth.bearer_token {
+        source = source.with_bearer_token(token);
+    }
+
+    if let Some(js_value) = cx.argument_opt(i + 1) {
+        if let Ok(provider) = js_value.downcast::<JsFunction, _>(cx) {
+            let provider = Arc::new(provider.root(cx));
+            let channel = cx.channel();
+            // UpdateManager only uses the source from background threads, so it is safe to block until the
+            // main JS thread has called the provider.
+            source = source.with_token_provider(move || {
+                let provider = provider.clone();
+                channel
+                    .send(move |mut cx| {
+                        let token = cx.try_catch(|cx| {
+                            let this = cx.undefined();
+                            let token = provider.to_inner(cx).call(cx, this, Vec::<Handle<JsValue>>::new())?;
+                            Ok(token.downcast_or_throw::<JsString, _>(cx)?.value(cx))
+                        });
+                        Ok(token.ok())
+                    })
+                    .join()
+                    .ok()
+                    .flatten()
+                    .ok_or_else(|| Error::Other("tokenProvider did not return a token".to_owned()))
+            });
+        }
+    }
+
+    Ok(Some(source))
+}
+
 fn args_array_to_vec_string(cx: &mut FunctionContext, arg: Handle<JsArray>) -> NeonResult<Vec<String>> {
     let mut vec: Vec<String> = Vec::new();
     for i in 0..arg.len(cx) {
@@ -59,8 +116,11 @@ fn js_new_update_manager(mut cx: FunctionContext) -> JsResult<BoxedUpdateManager
         options = Some(new_opt);
     }
 
-    let source = AutoSource::new(&arg_source);
-    let manager = UpdateManager::new(source, options, locator).or_else(|e| cx.throw_error(e.to_string()))?;
+    let manager = match args_get_http_source(&mut cx, &arg_source, 3)? {
+        Some(source) => UpdateManager::new(source, options, locator),
+        None => UpdateManager::new(AutoSource::new(&arg_source), options, locator),
+    };
+    let manager = manager.or_else(|e| cx.throw_error(e.to_string()))?;
     let wrapper = UpdateManagerWrapper { manager };
     Ok(cx.boxed(RefCell::new(wrapper)))
 }
@@ -86,6 +146,28 @@ fn js_is_portable(mut cx: FunctionContext) -> JsResult<JsBoolean> {
     Ok(cx.boolean(is_portable))
 }
 
+fn js_boost_downloads(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let mgr_boxed = cx.argument::<BoxedUpdateManager>(0)?;
+    let mgr_ref = &mgr_boxed.borrow().manager;
+    mgr_ref.boost_downloads();
+    Ok(cx.undefined())
+}
+
+fn js_set_maximum_download_rate(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let mgr_boxed = cx.argument::<BoxedUpdateManager>(0)?;
+    let bytes_per_second = cx.argument::<JsNumber>(1)?.value(&mut cx);
+    let mgr_ref = &mgr_boxed.borrow().manager;
+    mgr_ref.set_maximum_download_rate(bytes_per_second.max(0.0) as u64);
+    Ok(cx.undefined())
+}
+
+fn js_cancel_downloads(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let mgr_boxed = cx.argument::<BoxedUpdateManager>(0)?;
+    let mgr_ref = &mgr_boxed.borrow().manager;
+    mgr_ref.cancel_downloads();
+    Ok(cx.undefined())
+}
+
 fn js_update_pending_restart(mut cx: FunctionContext) -> JsResult<JsValue> {
     let mgr_boxed = cx.argument::<BoxedUpdateManager>(0)?;
     let mgr_ref = &mgr_boxed.borrow().manager;
@@ -209,13 +291,13 @@ fn js_wait_exit_then_apply_update(mut cx: FunctionContext) -> JsResult<JsUndefin
     let arg_silent = cx.argument::<JsBoolean>(2)?.value(&mut cx);
     let arg_restart = cx.argument::<JsBoolean>(3)?.value(&mut cx);
 
-    let asset = serde_json::from_str::<VelopackAsset>(&arg_update).or_else(|e| cx.throw_error(e.to_string()))?;
+    let update = serde_json::from_str::<UpdateInfo>(&arg_update).or_else(|e| cx.throw_error(e.to_string()))?;
 
     let arg_restart_args = cx.argument::<JsArray>(4)?;
     let restart_args = args_array_to_vec_string(&mut cx, arg_restart_args)?;
 
     mgr_ref
-        .wait_exit_then_apply_updates(asset, arg_silent, arg_restart, restart_args)
+        .wait_exit_then_apply_updates(update, arg_silent, arg_restart, restart_args)
         .or_else(|e| cx.throw_error(e.to_string()))?;
     Ok(cx.undefined())
 }
@@ -298,6 +380,9 @@ fn main(mut cx: ModuleContext) -> NeonResult<()> {
     cx.export_function("js_get_current_version", js_get_current_version)?;
     cx.export_function("js_get_app_id", js_get_app_id)?;
     cx.export_function("js_is_portable", js_is_portable)?;
+    cx.export_function("js_boost_downloads", js_boost_downloads)?;
+    cx.export_function("js_set_maximum_download_rate", js_set_maximum_download_rate)?;
+    cx.export_function("js_cancel_downloads", js_cancel_downloads)?;
     cx.export_function("js_update_pending_restart", js_update_pending_restart)?;
     cx.export_function("js_check_for_updates_async", js_check_for_updates_async)?;
     cx.export_function("js_download_update_async", js_download_update_async)?;
@@ -13,7 +13,7 @@ use manager::UpdateManagerWrapper;
 mod sources;
 use sources::{PyGiteaSource, PyGithubSource, PyGitlabSource, PyHttpSource};
 
-use ::velopack::VelopackAsset;
+use ::velopack::UpdateInfo;
 
 #[derive(FromPyObject)]
 #[allow(clippy::large_enum_variant)]
@@ -23,10 +23,14 @@ pub enum PyUpdateInfoOrAsset {
 }
 
 impl PyUpdateInfoOrAsset {
-    pub fn into_asset(self) -> VelopackAsset {
+    /// Converts to an UpdateInfo, so that flags such as IsDowngrade are kept when the update is applied.
+    pub fn into_update_info(self) -> UpdateInfo {
         match self {
-            PyUpdateInfoOrAsset::UpdateInfo(update_info) => update_info.TargetFullRelease.into(),
-            PyUpdateInfoOrAsset::Asset(asset) => asset.into(),
+            PyUpdateInfoOrAsset::UpdateInfo(update_info) => update_info.into(),
+            PyUpdateInfoOrAsset::Asset(asset) => UpdateInfo {
+                TargetFullRelease: asset.into(),
+                ..Default::default()
+            },
         }
     }
 }
@@ -44,6 +48,7 @@ fn velopack(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyVelopackAsset>()?;
     m.add_class::<PyUpdateInfo>()?;
     m.add_class::<PyUpdateOptions>()?;
+    m.add_class::<PyHttpClientConfig>()?;
     m.add_class::<PyVelopackLocatorConfig>()?;
 
     // update sources
@@ -3,7 +3,7 @@ use pyo3::prelude::*;
 use std::sync::mpsc;
 use std::thread;
 
-use velopack::{UpdateCheck, UpdateInfo, UpdateManager as VelopackUpdateManagerRust, VelopackAsset};
+use velopack::{UpdateCheck, UpdateInfo, UpdateManager as VelopackUpdateManagerRust};
 
 use crate::{sources::PySourceArg, types::*, PyUpdateInfoOrAsset};
 
@@ -36,6 +36,18 @@ impl UpdateManagerWrapper {
         self.inner.get_is_portable()
     }
 
+    pub fn boost_downloads(&self) {
+        self.inner.boost_downloads()
+    }
+
+    pub fn set_maximum_download_rate(&self, bytes_per_second: u64) {
+        self.inner.set_maximum_download_rate(bytes_per_second)
+    }
+
+    pub fn cancel_downloads(&self) {
+        self.inner.cancel_downloads()
+    }
+
     pub fn get_update_pending_restart(&self) -> Option<PyVelopackAsset> {
         let pending = self.inner.get_update_pending_restart();
         pending.map(Into::into)
@@ -51,11 +63,12 @@ impl UpdateManagerWrapper {
             }
             UpdateCheck::NoUpdateAvailable => Ok(None),
             UpdateCheck::RemoteIsEmpty => Ok(None),
+            UpdateCheck::UpdateIncompatible(_) => Ok(None),
         }
     }
 
     #[pyo3(signature = (update_info, progress_callback = None))]
-    pub fn download_updates(&mut self, py: Python, update_info: PyUpdateInfo, progress_callback: Option<Py<PyAny>>) -> Result<()> {
+    pub fn download_updates(&self, py: Python, update_info: PyUpdateInfo, progress_callback: Option<Py<PyAny>>) -> Result<()> {
         let rust_update_info: UpdateInfo = update_info.into();
         let inner = self.inner.clone();
 
@@ -88,20 +101,20 @@ impl UpdateManagerWrapper {
     }
 
     pub fn apply_updates_and_restart(&mut self, update: PyUpdateInfoOrAsset) -> Result<()> {
-        let asset: VelopackAsset = update.into_asset();
-        self.inner.apply_updates_and_restart(&asset)?;
+        let update: UpdateInfo = update.into_update_info();
+        self.inner.apply_updates_and_restart(&update)?;
         Ok(())
     }
 
     pub fn apply_updates_and_restart_with_args(&mut self, update: PyUpdateInfoOrAsset, restart_args: Vec<String>) -> Result<()> {
-        let asset: VelopackAsset = update.into_asset();
-        self.inner.apply_updates_and_restart_with_args(&asset, restart_args)?;
+        let update: UpdateInfo = update.into_update_info();
+        self.inner.apply_updates_and_restart_with_args(&update, restart_args)?;
         Ok(())
     }
 
     pub fn apply_updates_and_exit(&mut self, update: PyUpdateInfoOrAsset) -> Result<()> {
-        let asset: VelopackAsset = update.into_asset();
-        self.inner.apply_updates_and_exit(&asset)?;
+        let update: UpdateInfo = update.into_update_info();
+        self.inner.apply_updates_and_exit(&update)?;
         Ok(())
     }
 
@@ -113,9 +126,9 @@ impl UpdateManagerWrapper {
         restart: bool,
         restart_args: Option<Vec<String>>,
     ) -> Result<()> {
-        let asset: VelopackAsset = update.into_asset();
+        let update: UpdateInfo = update.into_update_info();
         let args = restart_args.unwrap_or_default();
-        self.inner.wait_exit_then_apply_updates(&asset, silent, restart, args)?;
+        self.inner.wait_exit_then_apply_updates(&update, silent, restart, args)?;
         Ok(())
     }
 }
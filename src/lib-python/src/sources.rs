@@ -1,5 +1,8 @@
 use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
 use velopack::sources::{AutoSource, GiteaSource, GithubSource, GitlabSource, HttpSource, UpdateSource};
+use velopack::Error;
 
 /// Retrieves available releases from a GitHub repository. Supports both github.com
 /// and GitHub Enterprise instances.
@@ -92,15 +95,63 @@ impl PyGiteaSource {
 #[derive(Clone)]
 pub struct PyHttpSource {
     pub url: String,
+    pub headers: Option<HashMap<String, String>>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub bearer_token: Option<String>,
+    pub token_provider: Option<Arc<Py<PyAny>>>,
 }
 
 #[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
 #[pymethods]
 impl PyHttpSource {
     /// Create a new HttpSource with the specified base URL.
+    /// - `url`: The base URL of the update server.
+    /// - `headers`: Optional extra headers to send with every request (e.g. an API key).
+    /// - `username`: Optional username for HTTP basic authentication.
+    /// - `password`: Optional password for HTTP basic authentication.
+    /// - `bearer_token`: Optional static bearer token. Takes precedence over basic authentication.
+    /// - `token_provider`: Optional callable returning a bearer token. It is called before every request, so it can
+    ///   refresh short-lived tokens. Takes precedence over `bearer_token` and basic authentication.
     #[new]
-    pub fn new(url: String) -> Self {
-        PyHttpSource { url }
+    #[pyo3(signature = (url, headers = None, username = None, password = None, bearer_token = None, token_provider = None))]
+    pub fn new(
+        url: String,
+        headers: Option<HashMap<String, String>>,
+        username: Option<String>,
+        password: Option<String>,
+        bearer_token: Option<String>,
+        token_provider: Option<Py<PyAny>>,
+    ) -> Self {
+        PyHttpSource {
+            url,
+            headers,
+            username,
+            password,
+            bearer_token,
+            token_provider: token_provider.map(Arc::new),
+        }
+    }
+}
+
+impl PyHttpSource {
+    fn into_source(self) -> HttpSource {
+        let mut source = HttpSource::new(&self.url);
+        for (name, value) in self.headers.unwrap_or_default() {
+            source = source.with_header(name, value);
+        }
+        if let Some(username) = self.username {
+            source = source.with_basic_auth(username, self.password.unwrap_or_default());
+        }
+        if let Some(token) = self.bearer_token {
+            source = source.with_bearer_token(token);
+        }
+        if let Some(provider) = self.token_provider {
+            source = source.with_token_provider(move || {
+                Python::attach(|py| provider.call0(py)?.extract::<String>(py)).map_err(|e| Error::Other(format!("token_provider failed: {}", e)))
+            });
+        }
+        source
     }
 }
 
@@ -121,7 +172,7 @@ impl PySourceArg {
             PySourceArg::Github(s) => Box::new(GithubSource::new(&s.repo_url, s.access_token, s.prerelease)),
             PySourceArg::Gitlab(s) => Box::new(GitlabSource::new(&s.repo_url, s.access_token, s.prerelease)),
             PySourceArg::Gitea(s) => Box::new(GiteaSource::new(&s.repo_url, s.access_token, s.prerelease)),
-            PySourceArg::Http(s) => Box::new(HttpSource::new(&s.url)),
+            PySourceArg::Http(s) => Box::new(s.into_source()),
             PySourceArg::Auto(s) => Box::new(AutoSource::new(&s)),
         }
     }
@@ -3,6 +3,7 @@
 use std::io::Cursor;
 use std::{
     cell::RefCell,
+    collections::HashSet,
     fs::{self, File},
     io::{Read, Seek, Write},
     path::{Path, PathBuf},
@@ -11,11 +12,16 @@ use std::{
 
 use regex::Regex;
 use semver::Version;
+use sha2::Digest;
 use xml::reader::XmlEvent;
 use xml::EventReader;
 use zip::ZipArchive;
 
-use crate::{misc, Error};
+use crate::integrity::{self, FileManifestEntry};
+use crate::{misc, signing::SignatureVerifier, Error};
+
+#[cfg(not(target_os = "linux"))]
+use crate::sanitize;
 
 #[cfg(target_os = "macos")]
 use std::os::unix::fs::PermissionsExt;
@@ -23,6 +29,12 @@ use std::os::unix::fs::PermissionsExt;
 #[cfg(target_os = "windows")]
 use normpath::PathExt;
 
+/// The name of the zip entry which holds the embedded (base64 encoded Ed25519) package signature.
+pub const PACKAGE_SIGNATURE_ENTRY_NAME: &str = "velopack.sig";
+
+/// The name of the zip entry which lists the SHA256 of every file in the package, in the same format as the signature payload.
+pub const PACKAGE_FILE_MANIFEST_ENTRY_NAME: &str = "velopack.files";
+
 pub trait ReadSeek: Read + Seek {}
 
 impl<T: Read + Seek> ReadSeek for T {}
@@ -137,6 +149,13 @@ impl BundleZip<'_> {
         None
     }
 
+    /// Returns the unix permission bits stored in the zip entry at the specified index, if it has any.
+    pub fn get_zip_file_unix_mode(&self, index: usize) -> Result<Option<u32>, Error> {
+        let mut archive = self.zip.borrow_mut();
+        let file = archive.by_index_raw(index)?;
+        Ok(file.unix_mode().map(|mode| mode & 0o777))
+    }
+
     pub fn extract_zip_idx_to_path<T: AsRef<Path>>(&self, index: usize, path: T) -> Result<(), Error> {
         self.extract_zip_idx_to_path_with_progress(index, path, |_| {})
     }
@@ -218,6 +237,135 @@ impl BundleZip<'_> {
         Ok(app)
     }
 
+    /// Returns the data which is covered by the embedded package signature. This is one line per file in the
+    /// package (except the signature itself), sorted by name, in the format `{sha256}  {name}\n`.
+    /// Only the name and contents of each entry are signed. Symlinks are stored as an entry containing the link
+    /// target, so they are covered, but other entry metadata (such as unix permissions) is not.
+    /// Packages with an entry name containing control characters (such as a line break), or with more than one
+    /// entry of the same name, can not be signed or verified, since their payload would be ambiguous.
+    pub fn get_signature_payload(&self) -> Result<Vec<u8>, Error> {
+        self.validate_signed_entry_names()?;
+        let entries = self.calculate_file_manifest(|name| name != PACKAGE_SIGNATURE_ENTRY_NAME)?;
+        Ok(integrity::format_file_manifest(&entries).into_bytes())
+    }
+
+    fn validate_signed_entry_names(&self) -> Result<(), Error> {
+        let mut seen = HashSet::new();
+        for name in self.read_central_directory_names()? {
+            if String::from_utf8_lossy(&name).chars().any(char::is_control) {
+                return Err(Error::InvalidPackage(format!(
+                    "Package entry {:?} has a name containing control characters",
+                    String::from_utf8_lossy(&name)
+                )));
+            }
+            if !seen.insert(name.clone()) {
+                return Err(Error::InvalidPackage(format!(
+                    "Package contains more than one entry named '{}'",
+                    String::from_utf8_lossy(&name)
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the raw name of every entry in the zip central directory. Unlike the names listed by `ZipArchive`,
+    /// this includes every entry which shares its name with another (the archive only keeps the last of them).
+    fn read_central_directory_names(&self) -> Result<Vec<Vec<u8>>, Error> {
+        const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+        let mut reader: Box<dyn ReadSeek> = match (self.zip_range, &self.file_path) {
+            (Some(zip_range), _) => Box::new(Cursor::new(zip_range)),
+            (None, Some(file_path)) => Box::new(misc::retry_io(|| File::open(file_path))?),
+            (None, None) => return Err(Error::InvalidPackage("(zip bundle has no source)".to_owned())),
+        };
+        reader.seek(std::io::SeekFrom::Start(self.zip.borrow().central_directory_start()))?;
+
+        let mut names = Vec::new();
+        let mut header = [0u8; 46];
+        loop {
+            match reader.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+            if u32::from_le_bytes([header[0], header[1], header[2], header[3]]) != CENTRAL_HEADER_SIGNATURE {
+                break;
+            }
+            let name_len = u16::from_le_bytes([header[28], header[29]]) as usize;
+            let extra_len = u16::from_le_bytes([header[30], header[31]]) as i64;
+            let comment_len = u16::from_le_bytes([header[32], header[33]]) as i64;
+            let mut name = vec![0u8; name_len];
+            reader.read_exact(&mut name)?;
+            reader.seek(std::io::SeekFrom::Current(extra_len + comment_len))?;
+            names.push(name);
+        }
+        Ok(names)
+    }
+
+    /// Returns the SHA256 of every file in the package. If the package contains a file manifest
+    /// (added by newer versions of vpk) it is returned, otherwise it is calculated from the package contents.
+    pub fn get_file_manifest(&self) -> Result<Vec<FileManifestEntry>, Error> {
+        if self.find_zip_file(|n| n == PACKAGE_FILE_MANIFEST_ENTRY_NAME).is_some() {
+            let contents = self.read_zip_file_string(PACKAGE_FILE_MANIFEST_ENTRY_NAME)?;
+            return integrity::parse_file_manifest(&contents);
+        }
+        debug!("Package has no file manifest, calculating it from the package contents.");
+        self.calculate_file_manifest(|name| name != PACKAGE_SIGNATURE_ENTRY_NAME && name != PACKAGE_FILE_MANIFEST_ENTRY_NAME)
+    }
+
+    fn calculate_file_manifest<F: Fn(&str) -> bool>(&self, predicate: F) -> Result<Vec<FileManifestEntry>, Error> {
+        let mut entries: Vec<FileManifestEntry> = Vec::new();
+        let mut archive = self.zip.borrow_mut();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            let name = file.name().to_string();
+            if file.is_dir() || !predicate(&name) {
+                continue;
+            }
+            let mut sha256 = sha2::Sha256::new();
+            let mut buffer = [0u8; 64 * 1024];
+            loop {
+                let len = file.read(&mut buffer)?;
+                if len == 0 {
+                    break;
+                }
+                sha256.update(&buffer[..len]);
+            }
+            entries.push(FileManifestEntry { name, sha256: misc::to_hex(&sha256.finalize()) });
+        }
+
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    /// Reads the contents of the named zip entry as a UTF-8 string.
+    pub fn read_zip_file_string(&self, name: &str) -> Result<String, Error> {
+        let idx = self
+            .find_zip_file(|n| n == name)
+            .ok_or_else(|| Error::InvalidPackage(format!("Package entry '{}' was not found", name)))?;
+        let mut contents = String::new();
+        let mut archive = self.zip.borrow_mut();
+        archive.by_index(idx)?.read_to_string(&mut contents)?;
+        Ok(contents)
+    }
+
+    /// Verifies that the package contains an embedded signature made by one of the trusted keys,
+    /// and that none of the files in the package have been modified since it was signed.
+    pub fn verify_signature(&self, verifier: &SignatureVerifier) -> Result<(), Error> {
+        let name = match &self.file_path {
+            Some(p) => p.file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_default(),
+            None => "(embedded bundle)".to_owned(),
+        };
+
+        let signature_idx = self
+            .find_zip_file(|n| n == PACKAGE_SIGNATURE_ENTRY_NAME)
+            .ok_or_else(|| Error::SignatureInvalid(name.clone(), "the package is not signed".to_owned()))?;
+
+        let mut signature = String::new();
+        self.zip.borrow_mut().by_index(signature_idx)?.read_to_string(&mut signature)?;
+        let payload = self.get_signature_payload()?;
+        verifier.verify(&name, &payload, &signature)
+    }
+
     pub fn len(&self) -> usize {
         let archive = self.zip.borrow();
         archive.len()
@@ -239,7 +387,7 @@ impl BundleZip<'_> {
     }
 
     #[cfg(not(target_os = "linux"))]
-    fn create_symlink(link_path: &PathBuf, target_path: &PathBuf) -> Result<(), Error> {
+    pub(crate) fn create_symlink(link_path: &PathBuf, target_path: &PathBuf) -> Result<(), Error> {
         #[cfg(target_os = "windows")]
         {
             let absolute_path = link_path.parent().unwrap().join(target_path);
@@ -303,6 +451,7 @@ impl BundleZip<'_> {
         let current_path = current_path.as_ref();
         let files = self.get_file_names()?;
         let num_files = files.len();
+        sanitize::validate_entry_names(&files)?;
 
         info!("Extracting {} app files to '{:?}'...", num_files, current_path);
         let re = Regex::new(r"lib[\\\/][^\\\/]*[\\\/]").unwrap();
@@ -330,11 +479,11 @@ impl BundleZip<'_> {
             }
 
             let file_path_in_zip = re.replace(key, "").to_string();
-            let file_path_on_disk = Path::new(&current_path).join(&file_path_in_zip);
+            let file_path_on_disk = sanitize::resolve_entry_path(current_path, &sanitize::sanitize_entry_name(&file_path_in_zip)?)?;
 
             if symlink_regex.is_match(&file_path_in_zip) {
                 let sym_key = symlink_regex.replace(&file_path_in_zip, "").to_string();
-                let file_path_on_disk = Path::new(&current_path).join(&sym_key);
+                let file_path_on_disk = sanitize::resolve_entry_path(current_path, &sanitize::sanitize_entry_name(&sym_key)?)?;
                 symlinks.push((i, file_path_on_disk));
                 continue;
             }
@@ -375,6 +524,7 @@ impl BundleZip<'_> {
             info!("    {} Creating symlink '{:?}' -> '{}'", i, link_path, contents);
 
             let contents = contents.trim_end_matches('/');
+            sanitize::validate_symlink_target(current_path, &link_path, contents)?;
             #[cfg(target_os = "windows")]
             let contents = contents.replace("/", "\\");
             let contents = PathBuf::from(contents);
@@ -412,6 +562,18 @@ pub struct Manifest {
     pub release_notes: String,
     pub release_notes_html: String,
     pub splash_progress_color: String,
+    pub signing_public_keys: String,
+}
+
+impl Manifest {
+    /// Returns the base64 encoded Ed25519 public keys which are trusted to sign packages for this app.
+    pub fn get_signing_public_keys(&self) -> Vec<String> {
+        self.signing_public_keys
+            .split(|c: char| c == ';' || c == ',' || c.is_whitespace())
+            .filter(|k| !k.is_empty())
+            .map(|k| k.to_owned())
+            .collect()
+    }
 }
 
 /// Parse manifest object from an XML string.
@@ -465,6 +627,8 @@ pub fn read_manifest_from_string(xml: &str) -> Result<Manifest, Error> {
                     obj.release_notes_html = text;
                 } else if el_name == "splashProgressColor" {
                     obj.splash_progress_color = text;
+                } else if el_name == "signingPublicKeys" {
+                    obj.signing_public_keys = text;
                 }
             }
             Ok(XmlEvent::EndElement { .. }) => {
@@ -656,3 +820,113 @@ Notes with special chars: & < > " '
     assert_eq!(manifest.channel, "stable");
     assert!(manifest.release_notes.contains("& < > \" '"));
 }
+
+#[test]
+fn test_read_manifest_signing_public_keys() {
+    let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://schemas.microsoft.com/packaging/2010/07/nuspec.xsd">
+<metadata>
+<id>SignedApp</id>
+<version>1.0.0</version>
+<mainExe>app.exe</mainExe>
+<signingPublicKeys>key1; key2,key3</signingPublicKeys>
+</metadata>
+</package>"#;
+
+    let manifest = read_manifest_from_string(xml).unwrap();
+    assert_eq!(manifest.get_signing_public_keys(), vec!["key1", "key2", "key3"]);
+    assert!(Manifest::default().get_signing_public_keys().is_empty());
+}
+
+#[cfg(test)]
+pub(crate) fn create_test_bundle(files: &[(&str, &[u8])]) -> Vec<u8> {
+    use zip::write::SimpleFileOptions;
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, contents) in files {
+        zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+        zip.write_all(contents).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+#[test]
+fn test_verify_package_signature() {
+    use base64::Engine;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    let b64 = base64::engine::general_purpose::STANDARD;
+
+    let trusted = Ed25519KeyPair::from_seed_unchecked(&[1; 32]).unwrap();
+    let untrusted = Ed25519KeyPair::from_seed_unchecked(&[2; 32]).unwrap();
+    let verifier = SignatureVerifier::new(&[b64.encode(trusted.public_key().as_ref())]).unwrap();
+
+    let files: [(&str, &[u8]); 2] = [("lib/app/app.exe", b"app contents"), ("TestApp.nuspec", b"<package />")];
+    let unsigned = create_test_bundle(&files);
+    let payload = load_bundle_from_memory(&unsigned).unwrap().get_signature_payload().unwrap();
+    let payload_str = String::from_utf8(payload.clone()).unwrap();
+    assert!(payload_str.starts_with(&format!("{}  TestApp.nuspec\n", misc::to_hex(&sha2::Sha256::digest(b"<package />")))));
+    assert!(matches!(load_bundle_from_memory(&unsigned).unwrap().verify_signature(&verifier), Err(Error::SignatureInvalid(..))));
+
+    let signature = b64.encode(trusted.sign(&payload).as_ref());
+    let signed = create_test_bundle(&[files[0], files[1], (PACKAGE_SIGNATURE_ENTRY_NAME, signature.as_bytes())]);
+    load_bundle_from_memory(&signed).unwrap().verify_signature(&verifier).unwrap();
+
+    let signature = b64.encode(untrusted.sign(&payload).as_ref());
+    let wrong_key = create_test_bundle(&[files[0], files[1], (PACKAGE_SIGNATURE_ENTRY_NAME, signature.as_bytes())]);
+    assert!(matches!(load_bundle_from_memory(&wrong_key).unwrap().verify_signature(&verifier), Err(Error::SignatureInvalid(..))));
+
+    let signature = b64.encode(trusted.sign(&payload).as_ref());
+    let tampered =
+        create_test_bundle(&[("lib/app/app.exe", b"evil contents"), files[1], (PACKAGE_SIGNATURE_ENTRY_NAME, signature.as_bytes())]);
+    assert!(matches!(load_bundle_from_memory(&tampered).unwrap().verify_signature(&verifier), Err(Error::SignatureInvalid(..))));
+}
+
+#[test]
+fn test_signature_payload_rejects_ambiguous_entry_names() {
+    use base64::Engine;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let trusted = Ed25519KeyPair::from_seed_unchecked(&[1; 32]).unwrap();
+    let verifier = SignatureVerifier::new(&[b64.encode(trusted.public_key().as_ref())]).unwrap();
+
+    let nuspec: (&str, &[u8]) = ("TestApp.nuspec", b"<package />");
+    let newline = create_test_bundle(&[("lib/app/app.exe\n0000  lib/app/other.exe", b"app contents"), nuspec]);
+    let result = load_bundle_from_memory(&newline).unwrap().get_signature_payload();
+    assert!(matches!(result, Err(Error::InvalidPackage(_))));
+
+    // the zip writer refuses duplicate names, so the second entry is renamed to match the first afterwards.
+    let signature = b64.encode(trusted.sign(b"").as_ref());
+    let files: [(&str, &[u8]); 4] = [
+        ("lib/app/app.exe", b"app contents"),
+        ("lib/app/app.exf", b"evil contents"),
+        nuspec,
+        (PACKAGE_SIGNATURE_ENTRY_NAME, signature.as_bytes()),
+    ];
+    let mut duplicate = create_test_bundle(&files);
+    let renamed = b"lib/app/app.exf";
+    while let Some(i) = duplicate.windows(renamed.len()).position(|w| w == renamed) {
+        duplicate[i..i + renamed.len()].copy_from_slice(b"lib/app/app.exe");
+    }
+    let result = load_bundle_from_memory(&duplicate).unwrap().get_signature_payload();
+    assert!(matches!(result, Err(Error::InvalidPackage(_))));
+
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let path = tmp_dir.path().join("duplicate.nupkg");
+    fs::write(&path, &duplicate).unwrap();
+    let result = load_bundle_from_file(&path).unwrap().verify_signature(&verifier);
+    assert!(matches!(result, Err(Error::InvalidPackage(_))));
+}
+
+#[test]
+fn test_get_file_manifest() {
+    let files: [(&str, &[u8]); 2] = [("lib/app/app.exe", b"app contents"), ("TestApp.nuspec", b"<package />")];
+    let calculated = load_bundle_from_memory(&create_test_bundle(&files)).unwrap().get_file_manifest().unwrap();
+    assert_eq!(calculated.len(), 2);
+    assert_eq!(calculated[0].name, "TestApp.nuspec");
+    assert_eq!(calculated[1].name, "lib/app/app.exe");
+    assert_eq!(calculated[1].sha256, misc::to_hex(&sha2::Sha256::digest(b"app contents")));
+
+    let embedded = format!("{}  lib/app/app.exe\n", "AB".repeat(32));
+    let bundle = create_test_bundle(&[files[0], files[1], (PACKAGE_FILE_MANIFEST_ENTRY_NAME, embedded.as_bytes())]);
+    let manifest = load_bundle_from_memory(&bundle).unwrap().get_file_manifest().unwrap();
+    assert_eq!(manifest, vec![FileManifestEntry { name: "lib/app/app.exe".to_owned(), sha256: "AB".repeat(32) }]);
+}
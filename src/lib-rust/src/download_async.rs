@@ -0,0 +1,219 @@
+use std::path::Path;
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+
+use crate::download::{
+    check_feed_response, finish_feed_response, get_conditional_headers, get_download_progress,
+    get_download_hasher, get_download_total_size, get_offline_fallback, get_resumable_length, get_resume_validator, get_resume_validator_path,
+    get_status_error, start_download_response, CancellationToken, DownloadPacer, DownloadStart, DownloadThrottle, FeedCache, FeedResponse, HttpClientConfig,
+};
+use crate::hashes::{FileHashes, HashAlgorithm, MultiHasher};
+use crate::Error;
+
+/// Downloads a file from a URL with custom headers and HTTP client configuration, and writes it to a file while
+/// reporting progress from 0-100. This is the non-blocking equivalent of `download::download_url_to_file_with_config`,
+/// and interrupted downloads are resumed in the same way.
+pub async fn download_url_to_file_with_config<A, S: AsRef<Path>>(
+    url: &str,
+    file_path: S,
+    headers: &[(&str, &str)],
+    config: &HttpClientConfig,
+    progress: A,
+) -> Result<(), Error>
+where
+    A: FnMut(i16) + Send,
+{
+    download_url_to_file_throttled(url, file_path, headers, config, None, None, progress).await
+}
+
+/// Downloads a file as described in `download_url_to_file_with_config`, limiting the transfer rate to the current
+/// rate of the specified DownloadThrottle (if any), and stopping if the CancellationToken (if any) is cancelled.
+pub async fn download_url_to_file_throttled<A, S: AsRef<Path>>(
+    url: &str,
+    file_path: S,
+    headers: &[(&str, &str)],
+    config: &HttpClientConfig,
+    throttle: Option<&DownloadThrottle>,
+    cancel: Option<&CancellationToken>,
+    progress: A,
+) -> Result<(), Error>
+where
+    A: FnMut(i16) + Send,
+{
+    download_url_to_file_with_hashes(url, file_path, headers, config, throttle, cancel, &[], progress).await?;
+    Ok(())
+}
+
+/// Downloads a file as described in `download_url_to_file_throttled`, hashing it as it is written. This is the
+/// non-blocking equivalent of `download::download_url_to_file_with_hashes`.
+#[allow(clippy::too_many_arguments)]
+pub async fn download_url_to_file_with_hashes<A, S: AsRef<Path>>(
+    url: &str,
+    file_path: S,
+    headers: &[(&str, &str)],
+    config: &HttpClientConfig,
+    throttle: Option<&DownloadThrottle>,
+    cancel: Option<&CancellationToken>,
+    algorithms: &[HashAlgorithm],
+    mut progress: A,
+) -> Result<Option<FileHashes>, Error>
+where
+    A: FnMut(i16) + Send,
+{
+    let file_path = file_path.as_ref();
+    let validator_path = get_resume_validator_path(file_path);
+    let client = get_download_client(config)?;
+
+    let mut resume_from = get_resumable_length(file_path, &validator_path);
+    CancellationToken::check(cancel)?;
+    let (mut response, file, mut downloaded) = loop {
+        let mut req = client.get(url);
+        for &(name, value) in headers {
+            req = req.header(name, value);
+        }
+
+        let validator = get_resume_validator(file_path, &validator_path, resume_from);
+        if let Some(validator) = &validator {
+            req = req.header("Range", format!("bytes={}-", resume_from)).header("If-Range", validator);
+        }
+
+        let response = req.send().await?;
+        match start_download_response(url, file_path, resume_from, validator.is_some(), response.status(), response.headers())? {
+            DownloadStart::AlreadyComplete => {
+                progress(100);
+                return Ok(None);
+            }
+            DownloadStart::Restart => resume_from = 0,
+            DownloadStart::Write(file, downloaded) => break (response, file, downloaded),
+        }
+    };
+
+    let total_size = get_download_total_size(response.status(), response.headers(), downloaded);
+    let mut file = tokio::fs::File::from_std(file);
+    let mut last_progress = 0;
+    let mut pacer = DownloadPacer::new(throttle);
+    let mut hasher = get_download_hasher(downloaded, algorithms);
+
+    while let Some(chunk) = response.chunk().await? {
+        if let Err(e) = CancellationToken::check(cancel) {
+            file.flush().await?;
+            info!("Download of '{:?}' was cancelled after {} bytes.", file_path, downloaded);
+            return Err(e);
+        }
+        file.write_all(&chunk).await?;
+        if let Some(hasher) = &mut hasher {
+            hasher.update(&chunk);
+        }
+        downloaded += chunk.len() as u64;
+
+        let delay = pacer.add_transferred(chunk.len());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+
+        if let Some(total) = total_size {
+            let new_progress = get_download_progress(downloaded, total);
+            if new_progress > last_progress {
+                last_progress = new_progress;
+                progress(last_progress);
+            }
+        }
+    }
+
+    file.flush().await?;
+    let _ = tokio::fs::remove_file(&validator_path).await;
+    Ok(hasher.map(MultiHasher::finalize))
+}
+
+/// Downloads a file from a URL with custom headers and HTTP client configuration, and returns it as a string.
+pub async fn download_url_as_string_with_config(url: &str, headers: &[(&str, &str)], config: &HttpClientConfig) -> Result<String, Error> {
+    let client = get_download_client(config)?;
+    let mut req = client.get(url);
+    for &(name, value) in headers {
+        req = req.header(name, value);
+    }
+    let response = req.send().await?;
+    if !response.status().is_success() {
+        return Err(get_status_error(url, response.status(), response.headers()));
+    }
+    Ok(response.text().await?)
+}
+
+/// Downloads a file from a URL and returns it as a string, using the specified FeedCache (if any) as described in
+/// `download::download_url_as_string_cached`.
+pub async fn download_url_as_string_cached(
+    url: &str,
+    headers: &[(&str, &str)],
+    config: &HttpClientConfig,
+    cache: Option<&FeedCache>,
+) -> Result<String, Error> {
+    download_page_as_string_cached(url, headers, config, cache).await.map(|(body, _)| body)
+}
+
+/// Downloads one page of a paginated API and returns it as a string, along with the URL of the next page, as
+/// described in `download::download_page_as_string_cached`.
+pub async fn download_page_as_string_cached(
+    url: &str,
+    headers: &[(&str, &str)],
+    config: &HttpClientConfig,
+    cache: Option<&FeedCache>,
+) -> Result<(String, Option<String>), Error> {
+    let cached = cache.and_then(|c| c.load(url));
+    let result = async {
+        let client = get_download_client(config)?;
+        let mut req = client.get(url);
+        for &(name, value) in headers {
+            req = req.header(name, value);
+        }
+        for (name, value) in get_conditional_headers(cached.as_ref()) {
+            req = req.header(name, value);
+        }
+        Ok::<_, Error>(req.send().await?)
+    }
+    .await;
+
+    let response = match result {
+        Ok(response) => response,
+        Err(e) => return get_offline_fallback(url, cache, cached.as_ref(), e),
+    };
+
+    match check_feed_response(url, cache, cached.as_ref(), response.status(), response.headers())? {
+        FeedResponse::Cached(body, next_url) => Ok((body, next_url)),
+        FeedResponse::Modified(entry) => {
+            let body = response.text().await?;
+            Ok(finish_feed_response(cache, entry, body))
+        }
+    }
+}
+
+fn get_download_client(config: &HttpClientConfig) -> Result<reqwest::Client, Error> {
+    // by default, reqwest will read the proxy from the environment (HTTP_PROXY, HTTPS_PROXY, NO_PROXY, etc.)
+    let mut builder = reqwest::Client::builder();
+
+    if let Some(proxy) = config.ProxyUrl.as_deref().filter(|p| !p.is_empty()) {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+    if config.ConnectTimeoutSeconds > 0 {
+        builder = builder.connect_timeout(Duration::from_secs(config.ConnectTimeoutSeconds));
+    }
+    if config.ResponseTimeoutSeconds > 0 {
+        builder = builder.read_timeout(Duration::from_secs(config.ResponseTimeoutSeconds));
+    }
+    if let Some(user_agent) = config.UserAgent.as_deref().filter(|u| !u.is_empty()) {
+        builder = builder.user_agent(user_agent);
+    }
+    if let Some(cert_file) = config.RootCertificatesFile.as_deref().filter(|c| !c.is_empty()) {
+        let pem = std::fs::read(cert_file)?;
+        let custom_certs = reqwest::Certificate::from_pem_bundle(&pem)?;
+        if custom_certs.is_empty() {
+            return Err(Error::Other(format!("No certificates found in root certificate file '{}'", cert_file)));
+        }
+        info!("Trusting {} additional root certificate(s) from '{}'", custom_certs.len(), cert_file);
+        for cert in custom_certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    Ok(builder.build()?)
+}
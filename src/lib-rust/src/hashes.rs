@@ -0,0 +1,189 @@
+use sha2::Digest;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::{misc, CancellationToken, Error};
+
+/// A checksum algorithm which can be used to verify a package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    /// SHA-1. This is only supported for compatibility with older feeds, and is not collision resistant.
+    Sha1,
+    /// SHA-256.
+    Sha256,
+    /// SHA-512.
+    Sha512,
+    /// BLAKE3, with the default 256 bit output.
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// All of the supported algorithms.
+    pub const ALL: [HashAlgorithm; 4] = [HashAlgorithm::Sha1, HashAlgorithm::Sha256, HashAlgorithm::Sha512, HashAlgorithm::Blake3];
+
+    /// The name of this algorithm, as used in `VelopackAsset.Hashes` and `UpdateOptions.MinimumHashAlgorithm`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    /// Parses an algorithm name (case-insensitive), returning None if the algorithm is not supported.
+    pub fn from_name(name: &str) -> Option<HashAlgorithm> {
+        let name = name.trim().replace('-', "");
+        HashAlgorithm::ALL.into_iter().find(|a| a.name().eq_ignore_ascii_case(&name))
+    }
+
+    /// The relative strength of this algorithm. Algorithms with the same strength are considered equivalent.
+    pub fn strength(&self) -> u32 {
+        match self {
+            HashAlgorithm::Sha1 => 1,
+            HashAlgorithm::Sha256 => 2,
+            HashAlgorithm::Sha512 => 3,
+            HashAlgorithm::Blake3 => 3,
+        }
+    }
+}
+
+/// Parses an entry from `VelopackAsset.Hashes`, which maps an algorithm name to a hex digest. Returns None if
+/// the digest is empty or the algorithm is not supported.
+pub fn parse_asset_hash(algorithm: &str, digest: &str) -> Option<(HashAlgorithm, String)> {
+    let digest = digest.trim();
+    if digest.is_empty() {
+        return None;
+    }
+    Some((HashAlgorithm::from_name(algorithm)?, digest.to_owned()))
+}
+
+/// Computes several digests over the same data at once.
+#[derive(Clone, Default)]
+pub struct MultiHasher {
+    sha1: Option<sha1::Sha1>,
+    sha256: Option<sha2::Sha256>,
+    sha512: Option<sha2::Sha512>,
+    blake3: Option<Box<blake3::Hasher>>,
+}
+
+impl MultiHasher {
+    /// Create a new hasher for the specified algorithms.
+    pub fn new(algorithms: &[HashAlgorithm]) -> MultiHasher {
+        let mut hasher = MultiHasher::default();
+        for algorithm in algorithms {
+            match algorithm {
+                HashAlgorithm::Sha1 => hasher.sha1 = Some(sha1::Sha1::new()),
+                HashAlgorithm::Sha256 => hasher.sha256 = Some(sha2::Sha256::new()),
+                HashAlgorithm::Sha512 => hasher.sha512 = Some(sha2::Sha512::new()),
+                HashAlgorithm::Blake3 => hasher.blake3 = Some(Box::new(blake3::Hasher::new())),
+            }
+        }
+        hasher
+    }
+
+    /// Feed more data into every digest.
+    pub fn update(&mut self, data: &[u8]) {
+        if let Some(h) = &mut self.sha1 {
+            h.update(data);
+        }
+        if let Some(h) = &mut self.sha256 {
+            h.update(data);
+        }
+        if let Some(h) = &mut self.sha512 {
+            h.update(data);
+        }
+        if let Some(h) = &mut self.blake3 {
+            h.update(data);
+        }
+    }
+
+    /// Finish computing, and return the (uppercase hex) digests.
+    pub fn finalize(self) -> FileHashes {
+        let mut digests = Vec::new();
+        if let Some(h) = self.sha1 {
+            digests.push((HashAlgorithm::Sha1, misc::to_hex(&h.finalize())));
+        }
+        if let Some(h) = self.sha256 {
+            digests.push((HashAlgorithm::Sha256, misc::to_hex(&h.finalize())));
+        }
+        if let Some(h) = self.sha512 {
+            digests.push((HashAlgorithm::Sha512, misc::to_hex(&h.finalize())));
+        }
+        if let Some(h) = self.blake3 {
+            digests.push((HashAlgorithm::Blake3, misc::to_hex(h.finalize().as_bytes())));
+        }
+        FileHashes { digests }
+    }
+}
+
+/// The digests computed by a `MultiHasher`.
+#[derive(Debug, Clone, Default)]
+pub struct FileHashes {
+    digests: Vec<(HashAlgorithm, String)>,
+}
+
+impl FileHashes {
+    /// Returns the (uppercase hex) digest for the specified algorithm, if it was computed.
+    pub fn get(&self, algorithm: HashAlgorithm) -> Option<&str> {
+        self.digests.iter().find(|(a, _)| *a == algorithm).map(|(_, d)| d.as_str())
+    }
+}
+
+/// Calculates the specified digests of a file, stopping with `Error::Cancelled` if the token (if any) is cancelled.
+pub fn calculate_file_hashes<P: AsRef<Path>>(
+    file: P,
+    algorithms: &[HashAlgorithm],
+    cancel: Option<&CancellationToken>,
+) -> Result<FileHashes, Error> {
+    let file = File::open(file)?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = MultiHasher::new(algorithms);
+
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        CancellationToken::check(cancel)?;
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    Ok(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calculate_file_hashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        std::fs::write(&path, b"hello").unwrap();
+
+        let hashes = calculate_file_hashes(&path, &HashAlgorithm::ALL, None).unwrap();
+        assert_eq!(hashes.get(HashAlgorithm::Sha1).unwrap(), "AAF4C61DDCC5E8A2DABEDE0F3B482CD9AEA9434D");
+        assert_eq!(hashes.get(HashAlgorithm::Sha256).unwrap(), "2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824");
+        assert_eq!(
+            hashes.get(HashAlgorithm::Sha512).unwrap(),
+            "9B71D224BD62F3785D96D46AD3EA3D73319BFBC2890CAADAE2DFF72519673CA72323C3D99BA5C11D7C7ACC6E14B8C5DA0C4663475C2E5C3ADEF46F73BCDEC043"
+        );
+        assert_eq!(hashes.get(HashAlgorithm::Blake3).unwrap(), "EA8F163DB38682925E4491C5E58D4BB3506EF8C14EB78A86E908C5624A67200F");
+
+        let hashes = calculate_file_hashes(&path, &[HashAlgorithm::Sha512], None).unwrap();
+        assert!(hashes.get(HashAlgorithm::Sha1).is_none());
+        assert!(hashes.get(HashAlgorithm::Sha512).is_some());
+    }
+
+    #[test]
+    fn test_parse_asset_hash() {
+        assert_eq!(parse_asset_hash("sha512", "ABCD"), Some((HashAlgorithm::Sha512, "ABCD".to_owned())));
+        assert_eq!(parse_asset_hash("BLAKE3", " abcd "), Some((HashAlgorithm::Blake3, "abcd".to_owned())));
+        assert_eq!(parse_asset_hash("SHA-256", "abcd"), Some((HashAlgorithm::Sha256, "abcd".to_owned())));
+        assert_eq!(parse_asset_hash("md5", "abcd"), None);
+        assert_eq!(parse_asset_hash("sha512", ""), None);
+    }
+}
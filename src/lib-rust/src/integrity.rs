@@ -0,0 +1,394 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sha2::Digest;
+
+use crate::bundle::{self, BundleZip};
+use crate::hashes::{self, HashAlgorithm};
+use crate::locator::{self, VelopackLocator};
+use crate::{misc, sanitize, Error};
+
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+
+/// A file listed in the file manifest of a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileManifestEntry {
+    /// The name of the file in the package, eg. 'lib/app/MyApp.exe'.
+    pub name: String,
+    /// The (uppercase hex) SHA256 of the file.
+    pub sha256: String,
+}
+
+/// Parses a package file manifest, which has one line per file in the format `{sha256}  {name}`.
+pub fn parse_file_manifest(text: &str) -> Result<Vec<FileManifestEntry>, Error> {
+    let mut entries = Vec::new();
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        let entry = line.split_once("  ").and_then(|(sha256, name)| {
+            let valid = sha256.len() == 64 && sha256.chars().all(|c| c.is_ascii_hexdigit()) && !name.is_empty();
+            valid.then(|| FileManifestEntry { name: name.to_owned(), sha256: sha256.to_ascii_uppercase() })
+        });
+        entries.push(entry.ok_or_else(|| Error::InvalidPackage(format!("Invalid file manifest line '{}'", line)))?);
+    }
+    Ok(entries)
+}
+
+/// Formats a package file manifest, as described in `parse_file_manifest`.
+pub fn format_file_manifest(entries: &[FileManifestEntry]) -> String {
+    entries.iter().map(|e| format!("{}  {}\n", e.sha256, e.name)).collect()
+}
+
+/// The result of comparing the installed files of an app against the package it was installed from.
+/// File names are relative to the current app directory (or the AppImage file name on Linux), with '/' separators.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntegrityReport {
+    /// Files which are in the package, but are not installed.
+    pub missing_files: Vec<String>,
+    /// Files which are installed, but are different to the file in the package.
+    pub modified_files: Vec<String>,
+    /// Files which are installed, but are not in the package. These are only reported, and are never removed
+    /// by a repair, because apps are allowed to create their own files in the current app directory.
+    pub extra_files: Vec<String>,
+}
+
+impl IntegrityReport {
+    /// Returns true if no installed files are missing or modified.
+    pub fn is_intact(&self) -> bool {
+        self.missing_files.is_empty() && self.modified_files.is_empty()
+    }
+}
+
+/// A file from a package, and where it is installed on disk.
+struct InstalledFile {
+    entry_name: String,
+    relative_name: String,
+    path: PathBuf,
+    is_symlink: bool,
+}
+
+/// Returns the full package in the packages directory which the current version was installed from, if there is one.
+pub fn find_installed_package(locator: &VelopackLocator) -> Option<PathBuf> {
+    let version = locator.get_manifest_version();
+    let id = locator.get_manifest_id();
+    locator::find_local_full_packages(&locator.get_packages_dir())
+        .into_iter()
+        .find(|(_, manifest)| manifest.version == version && manifest.id.eq_ignore_ascii_case(&id))
+        .map(|(path, _)| path)
+}
+
+/// Maps the files in a package to the paths they are installed to, following the same rules as
+/// `BundleZip::extract_lib_contents_to_path`. Files which are not installed are skipped.
+#[cfg(not(target_os = "linux"))]
+fn get_installed_files<S: AsRef<str>>(locator: &VelopackLocator, entry_names: &[S]) -> Result<Vec<InstalledFile>, Error> {
+    let current_dir = locator.get_current_bin_dir();
+    let mut files = Vec::new();
+    for entry_name in entry_names {
+        let entry_name = entry_name.as_ref();
+        let normalized = entry_name.replace('\\', "/");
+        let Some(relative_name) = normalized.strip_prefix("lib/").and_then(|n| n.split_once('/')).map(|(_, n)| n) else {
+            continue;
+        };
+        if relative_name.is_empty() || relative_name.ends_with('/') {
+            continue;
+        }
+        if relative_name.ends_with("Squirrel.exe") || relative_name.ends_with("_ExecutionStub.exe") {
+            continue;
+        }
+
+        let (relative_name, is_symlink) = match relative_name.strip_suffix(".__symlink") {
+            Some(link_name) => (link_name, true),
+            None => (relative_name, false),
+        };
+        let path = sanitize::resolve_entry_path(&current_dir, &sanitize::sanitize_entry_name(relative_name)?)?;
+        files.push(InstalledFile {
+            entry_name: entry_name.to_owned(),
+            relative_name: relative_name.to_owned(),
+            path,
+            is_symlink,
+        });
+    }
+    Ok(files)
+}
+
+/// On Linux, the only installed file is the AppImage.
+#[cfg(target_os = "linux")]
+fn get_installed_files<S: AsRef<str>>(locator: &VelopackLocator, entry_names: &[S]) -> Result<Vec<InstalledFile>, Error> {
+    let appimage_path = locator.get_appimage_path();
+    let relative_name = appimage_path.file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_default();
+    Ok(entry_names
+        .iter()
+        .map(|n| n.as_ref())
+        .filter(|n| n.ends_with(".AppImage"))
+        .take(1)
+        .map(|n| InstalledFile {
+            entry_name: n.to_owned(),
+            relative_name: relative_name.clone(),
+            path: appimage_path.clone(),
+            is_symlink: false,
+        })
+        .collect())
+}
+
+fn get_symlink_target(path: &Path) -> Option<String> {
+    let target = fs::read_link(path).ok()?;
+    Some(target.to_string_lossy().replace('\\', "/"))
+}
+
+fn is_installed_file_intact(file: &InstalledFile, expected_sha256: &str) -> Result<bool, Error> {
+    if file.is_symlink {
+        // the package contains the target of the link, which may have had a trailing '/' removed when it was created.
+        let target = get_symlink_target(&file.path).unwrap_or_default();
+        let matches = |t: &str| misc::to_hex(&sha2::Sha256::digest(t.as_bytes())).eq_ignore_ascii_case(expected_sha256);
+        return Ok(matches(&target) || matches(&format!("{}/", target)));
+    }
+    let hashes = hashes::calculate_file_hashes(&file.path, &[HashAlgorithm::Sha256], None)?;
+    Ok(hashes.get(HashAlgorithm::Sha256).is_some_and(|h| h.eq_ignore_ascii_case(expected_sha256)))
+}
+
+/// Returns every file (not directory) in the specified directory, relative to it, with '/' separators.
+/// Symlinks are returned as files and are not followed.
+#[cfg(not(target_os = "linux"))]
+fn find_files_recursive(root: &Path, dir: &Path, files: &mut Vec<String>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            find_files_recursive(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn find_extra_files(locator: &VelopackLocator, installed: &[InstalledFile]) -> Result<Vec<String>, Error> {
+    let current_dir = locator.get_current_bin_dir();
+    let expected: HashSet<String> = installed.iter().map(|f| f.relative_name.to_lowercase()).collect();
+    let mut files = Vec::new();
+    if current_dir.is_dir() {
+        find_files_recursive(&current_dir, &current_dir, &mut files)?;
+    }
+    // the app manifest (sq.version) is written to the current dir when installing, but is not always in the package.
+    Ok(files
+        .into_iter()
+        .filter(|f| !f.eq_ignore_ascii_case("sq.version") && !expected.contains(&f.to_lowercase()))
+        .collect())
+}
+
+#[cfg(target_os = "linux")]
+fn find_extra_files(_locator: &VelopackLocator, _installed: &[InstalledFile]) -> Result<Vec<String>, Error> {
+    Ok(Vec::new())
+}
+
+/// Compares the installed files of the current app against a package file manifest, and reports any files
+/// which are missing, modified, or were not installed from the package.
+pub fn verify_installed_files(locator: &VelopackLocator, manifest: &[FileManifestEntry]) -> Result<IntegrityReport, Error> {
+    let names: Vec<&str> = manifest.iter().map(|e| e.name.as_str()).collect();
+    let installed = get_installed_files(locator, &names)?;
+    info!("Verifying {} installed files...", installed.len());
+
+    let hashes: HashMap<&str, &str> = manifest.iter().map(|e| (e.name.as_str(), e.sha256.as_str())).collect();
+    let mut report = IntegrityReport::default();
+    for file in &installed {
+        let sha256 = hashes
+            .get(file.entry_name.as_str())
+            .ok_or_else(|| Error::InvalidPackage(format!("File manifest has no entry for '{}'", file.entry_name)))?;
+        let exists = if file.is_symlink { fs::symlink_metadata(&file.path).is_ok() } else { file.path.is_file() };
+        if !exists {
+            warn!("Installed file '{}' is missing.", file.relative_name);
+            report.missing_files.push(file.relative_name.clone());
+        } else if !is_installed_file_intact(file, sha256)? {
+            warn!("Installed file '{}' has been modified.", file.relative_name);
+            report.modified_files.push(file.relative_name.clone());
+        }
+    }
+
+    report.extra_files = find_extra_files(locator, &installed)?;
+    for file in &report.extra_files {
+        debug!("Installed file '{}' is not part of the package.", file);
+    }
+    Ok(report)
+}
+
+/// Verifies the installed files of the current app against the file manifest of the specified package.
+pub fn verify_installation(locator: &VelopackLocator, package: &Path) -> Result<IntegrityReport, Error> {
+    info!("Verifying installation against package '{:?}'", package);
+    let bundle = bundle::load_bundle_from_file(package)?;
+    let manifest = bundle.get_file_manifest()?;
+    verify_installed_files(locator, &manifest)
+}
+
+/// Restores the missing and modified files in the report from the package which the current app was installed from.
+/// Each file is extracted next to its destination and then renamed over it, so files which are in use can be replaced
+/// where the operating system allows it. Extra files are left alone.
+pub fn repair_installed_files(locator: &VelopackLocator, bundle: &BundleZip, report: &IntegrityReport) -> Result<(), Error> {
+    let names = bundle.get_file_names()?;
+    sanitize::validate_entry_names(&names)?;
+    let installed = get_installed_files(locator, &names)?;
+    let to_repair: HashSet<&str> = report.missing_files.iter().chain(report.modified_files.iter()).map(|f| f.as_str()).collect();
+
+    // symlinks are restored last, because their target must exist.
+    let (symlinks, files): (Vec<&InstalledFile>, Vec<&InstalledFile>) =
+        installed.iter().filter(|f| to_repair.contains(f.relative_name.as_str())).partition(|f| f.is_symlink);
+
+    for file in files {
+        info!("Restoring '{}' from package entry '{}'", file.relative_name, file.entry_name);
+        let index = bundle
+            .find_zip_file(|n| n == file.entry_name)
+            .ok_or_else(|| Error::InvalidPackage(format!("Package entry '{}' was not found", file.entry_name)))?;
+        let temp_path = get_temp_path(&file.path);
+        bundle.extract_zip_idx_to_path(index, &temp_path)?;
+        // keep the mode the file was packaged with, or failing that, the mode of the file being replaced.
+        #[cfg(unix)]
+        {
+            let mode = bundle.get_zip_file_unix_mode(index)?.or_else(|| file.path.metadata().ok().map(|m| m.permissions().mode() & 0o777));
+            if let Some(mode) = mode {
+                fs::set_permissions(&temp_path, fs::Permissions::from_mode(mode))?;
+            }
+        }
+        if let Err(e) = misc::retry_io(|| fs::rename(&temp_path, &file.path)) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e.into());
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    for file in symlinks {
+        info!("Restoring symlink '{}' from package entry '{}'", file.relative_name, file.entry_name);
+        let target = bundle.read_zip_file_string(&file.entry_name)?;
+        let target = target.trim_end_matches('/');
+        let current_dir = locator.get_current_bin_dir();
+        sanitize::validate_symlink_target(&current_dir, &file.path, target)?;
+        if fs::symlink_metadata(&file.path).is_ok() {
+            misc::retry_io(|| fs::remove_file(&file.path))?;
+        }
+        #[cfg(target_os = "windows")]
+        let target = target.replace('/', "\\");
+        BundleZip::create_symlink(&file.path, &PathBuf::from(target))?;
+    }
+    #[cfg(target_os = "linux")]
+    let _ = symlinks;
+
+    Ok(())
+}
+
+fn get_temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.tmp", misc::random_string(8)));
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_manifest() {
+        let hash = "2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824";
+        let text = format!("\u{feff}{}  lib/app/my app.exe\n\n{}  lib/app/b.dll\r\n", hash.to_lowercase(), hash);
+        let entries = parse_file_manifest(&text).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "lib/app/my app.exe");
+        assert_eq!(entries[0].sha256, hash);
+        assert_eq!(parse_file_manifest(&format_file_manifest(&entries)).unwrap(), entries);
+
+        assert!(parse_file_manifest("ABCD  lib/app/a.exe").is_err());
+        assert!(parse_file_manifest(&format!("{} lib/app/a.exe", hash)).is_err());
+        assert!(parse_file_manifest(&format!("{}  ", hash)).is_err());
+    }
+
+    fn create_test_install(dir: &Path) -> (VelopackLocator, PathBuf) {
+        let config = locator::VelopackLocatorConfig {
+            RootAppDir: dir.join("TestApp.AppImage"),
+            UpdateExePath: dir.join("Update"),
+            PackagesDir: dir.join("packages"),
+            ManifestPath: dir.join("current").join("sq.version"),
+            CurrentBinaryDir: dir.join("current"),
+            IsPortable: false,
+        };
+        let manifest = bundle::Manifest { id: "TestApp".to_owned(), version: semver::Version::new(1, 0, 0), ..Default::default() };
+        let nuspec = b"<package><metadata><id>TestApp</id><version>1.0.0</version></metadata></package>";
+        let files: [(&str, &[u8], u32); 5] = [
+            ("TestApp.nuspec", nuspec, 0o644),
+            ("lib/app/TestApp.AppImage", b"appimage", 0o750),
+            ("lib/app/test.exe", b"exe", 0o750),
+            ("lib/app/sub/a.dll", b"dll", 0o640),
+            ("lib/app/a.dll.__symlink", b"sub/a.dll", 0o644),
+        ];
+        let package = dir.join("packages").join("TestApp-1.0.0-full.nupkg");
+        fs::create_dir_all(dir.join("packages")).unwrap();
+        let mut zip = zip::ZipWriter::new(fs::File::create(&package).unwrap());
+        for (name, contents, mode) in files {
+            zip.start_file(name, zip::write::SimpleFileOptions::default().unix_permissions(mode)).unwrap();
+            std::io::Write::write_all(&mut zip, contents).unwrap();
+        }
+        zip.finish().unwrap();
+        (VelopackLocator::new_with_manifest(config, manifest), package)
+    }
+
+    #[test]
+    #[cfg(not(target_os = "linux"))]
+    fn test_verify_and_repair_installation() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (locator, package) = create_test_install(tmp.path());
+        assert_eq!(find_installed_package(&locator), Some(package.clone()));
+
+        let current = locator.get_current_bin_dir();
+        fs::create_dir_all(&current).unwrap();
+        fs::write(current.join("TestApp.AppImage"), b"appimage").unwrap();
+        fs::write(current.join("test.exe"), b"modified").unwrap();
+        fs::write(current.join("sq.version"), b"manifest").unwrap();
+        fs::write(current.join("settings.json"), b"{}").unwrap();
+
+        let report = verify_installation(&locator, &package).unwrap();
+        assert!(!report.is_intact());
+        assert_eq!(report.missing_files, vec!["a.dll", "sub/a.dll"]);
+        assert_eq!(report.modified_files, vec!["test.exe"]);
+        assert_eq!(report.extra_files, vec!["settings.json"]);
+
+        let bundle = bundle::load_bundle_from_file(&package).unwrap();
+        repair_installed_files(&locator, &bundle, &report).unwrap();
+        assert_eq!(fs::read(current.join("test.exe")).unwrap(), b"exe");
+        assert_eq!(fs::read(current.join("sub").join("a.dll")).unwrap(), b"dll");
+        assert_eq!(fs::read(current.join("a.dll")).unwrap(), b"dll");
+        assert!(current.join("settings.json").exists());
+        #[cfg(unix)]
+        {
+            assert_eq!(fs::metadata(current.join("test.exe")).unwrap().permissions().mode() & 0o777, 0o750);
+            assert_eq!(fs::metadata(current.join("sub").join("a.dll")).unwrap().permissions().mode() & 0o777, 0o640);
+        }
+
+        let report = verify_installation(&locator, &package).unwrap();
+        assert!(report.is_intact());
+        assert_eq!(report.extra_files, vec!["settings.json"]);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_verify_and_repair_installation() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (locator, package) = create_test_install(tmp.path());
+        assert_eq!(find_installed_package(&locator), Some(package.clone()));
+
+        let report = verify_installation(&locator, &package).unwrap();
+        assert_eq!(report.missing_files, vec!["TestApp.AppImage"]);
+
+        fs::write(locator.get_appimage_path(), b"modified").unwrap();
+        let report = verify_installation(&locator, &package).unwrap();
+        assert!(report.missing_files.is_empty());
+        assert_eq!(report.modified_files, vec!["TestApp.AppImage"]);
+        assert!(report.extra_files.is_empty());
+
+        let bundle = bundle::load_bundle_from_file(&package).unwrap();
+        repair_installed_files(&locator, &bundle, &report).unwrap();
+        assert_eq!(fs::read(locator.get_appimage_path()).unwrap(), b"appimage");
+        assert_eq!(fs::metadata(locator.get_appimage_path()).unwrap().permissions().mode() & 0o777, 0o750);
+        assert!(verify_installation(&locator, &package).unwrap().is_intact());
+    }
+}
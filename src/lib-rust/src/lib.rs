@@ -98,7 +98,13 @@ pub mod sources;
 
 #[cfg(target_os = "windows")]
 maybe_pub!(known_path, wide_strings);
-maybe_pub!(download, bundle, constants, lockfile, logging, misc);
+maybe_pub!(download, bundle, constants, hashes, integrity, lockfile, logging, misc, platform, sanitize, signing);
+#[cfg(feature = "async")]
+maybe_pub!(download_async);
+pub use download::{CancellationToken, DownloadThrottle, FeedCache, HttpClientConfig};
+pub use hashes::{FileHashes, HashAlgorithm};
+pub use integrity::IntegrityReport;
+pub use signing::SignatureVerifier;
 maybe_pub_os!(process, "process_win.rs", "process_unix.rs");
 
 #[macro_use]
@@ -111,6 +117,9 @@ pub enum NetworkError {
     Http(#[from] ureq::Error),
     #[error("Url error: {0}")]
     Url(#[from] url::ParseError),
+    #[cfg(feature = "async")]
+    #[error("Http error: {0}")]
+    AsyncHttp(#[from] reqwest::Error),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -122,8 +131,16 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error("Checksum did not match for {0} (expected {1}, actual {2})")]
     ChecksumInvalid(PathBuf, String, String),
+    #[error("{0} does not have a checksum which is at least as strong as {1}")]
+    ChecksumTooWeak(String, String),
     #[error("Size did not match for {0} (expected {1}, actual {2})")]
     SizeInvalid(PathBuf, u64, u64),
+    #[error("Signature of {0} is not valid: {1}")]
+    SignatureInvalid(String, String),
+    #[error("The release feed sequence went backwards (expected at least {0}, found {1}), it may have been rolled back")]
+    FeedRollback(u64, u64),
+    #[error("The release feed expired at {0}, it may be out of date")]
+    FeedExpired(String),
     #[error("Zip error: {0}")]
     Zip(#[from] zip::result::ZipError),
     #[error("Network error: {0}")]
@@ -138,6 +155,10 @@ pub enum Error {
     NotInstalled(String),
     #[error("This is not supported: {0}")]
     NotSupported(String),
+    #[error("The server rate limit was exceeded while requesting {0}")]
+    RateLimited(String, Option<std::time::Duration>),
+    #[error("The operation was cancelled")]
+    Cancelled,
     #[error("{0}")]
     Other(String),
     #[cfg(target_os = "windows")]
@@ -156,3 +177,10 @@ impl From<ureq::Error> for Error {
         Error::Network(Box::new(NetworkError::Http(err)))
     }
 }
+
+#[cfg(feature = "async")]
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Network(Box::new(NetworkError::AsyncHttp(err)))
+    }
+}
@@ -343,6 +343,33 @@ impl VelopackLocator {
         Ok(lock_file)
     }
 
+    /// Returns the highest version which has ever been applied to this app (by installing or updating), if one has been recorded.
+    /// This can be used to refuse older packages, so a stale package or local attacker can not silently roll the app back.
+    pub fn get_highest_applied_version(&self) -> Option<Version> {
+        let path = self.get_packages_dir().join(".appliedVersion");
+        let version = std::fs::read_to_string(path).ok()?;
+        Version::parse(version.trim()).ok()
+    }
+
+    /// Records that the specified version has been applied to this app, if it is higher than the highest applied version.
+    pub fn record_applied_version(&self, version: &Version) -> Result<(), Error> {
+        if self.get_highest_applied_version().is_some_and(|v| v >= *version) {
+            return Ok(());
+        }
+        self.reset_applied_version(version)
+    }
+
+    /// Sets the highest applied version to the specified version, even if it is lower than the current mark.
+    /// This should only be used when a downgrade was explicitly allowed (eg. to roll back from a retracted release),
+    /// otherwise the newer version would continue to block every older package.
+    pub fn reset_applied_version(&self, version: &Version) -> Result<(), Error> {
+        let packages_dir = self.get_packages_dir();
+        std::fs::create_dir_all(&packages_dir)?;
+        info!("Recording highest applied version {}", version);
+        std::fs::write(packages_dir.join(".appliedVersion"), version.to_string())?;
+        Ok(())
+    }
+
     fn get_or_create_staged_user_id(&self) -> String {
         let packages_dir = self.get_packages_dir();
         let beta_id_path = packages_dir.join(".betaId");
@@ -707,3 +734,41 @@ fn test_locator_staged_id_for_existing_user() {
 
     assert_eq!(expected_user_id, staged_user_id);
 }
+
+#[test]
+fn test_locator_highest_applied_version_only_increases() {
+    let tmp_dir = tempfile::TempDir::new().unwrap();
+    let paths = VelopackLocatorConfig {
+        PackagesDir: tmp_dir.path().join("packages"),
+        ..Default::default()
+    };
+
+    let locator = VelopackLocator::new_with_manifest(paths, Manifest::default());
+    assert_eq!(locator.get_highest_applied_version(), None);
+
+    locator.record_applied_version(&Version::parse("1.2.0").unwrap()).unwrap();
+    assert_eq!(locator.get_highest_applied_version(), Some(Version::parse("1.2.0").unwrap()));
+
+    locator.record_applied_version(&Version::parse("1.1.0").unwrap()).unwrap();
+    assert_eq!(locator.get_highest_applied_version(), Some(Version::parse("1.2.0").unwrap()));
+
+    locator.record_applied_version(&Version::parse("2.0.0-beta.1").unwrap()).unwrap();
+    assert_eq!(locator.get_highest_applied_version(), Some(Version::parse("2.0.0-beta.1").unwrap()));
+}
+
+#[test]
+fn test_locator_reset_applied_version_can_lower_mark() {
+    let tmp_dir = tempfile::TempDir::new().unwrap();
+    let paths = VelopackLocatorConfig {
+        PackagesDir: tmp_dir.path().join("packages"),
+        ..Default::default()
+    };
+
+    let locator = VelopackLocator::new_with_manifest(paths, Manifest::default());
+    locator.record_applied_version(&Version::parse("1.2.0").unwrap()).unwrap();
+    locator.reset_applied_version(&Version::parse("1.1.0").unwrap()).unwrap();
+    assert_eq!(locator.get_highest_applied_version(), Some(Version::parse("1.1.0").unwrap()));
+
+    locator.record_applied_version(&Version::parse("1.0.0").unwrap()).unwrap();
+    assert_eq!(locator.get_highest_applied_version(), Some(Version::parse("1.1.0").unwrap()));
+}
@@ -1,19 +1,26 @@
 use semver::Version;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::ffi::{OsStr, OsString};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use std::{fs, process::exit, sync::mpsc::Sender};
 
 use crate::{
     bundle::Manifest,
-    constants,
+    constants, download,
+    hashes::{self, HashAlgorithm},
+    integrity,
     locator::{self, LocationContext, VelopackLocator, VelopackLocatorConfig},
-    misc,
+    platform::PlatformInfo,
     sources::UpdateSource,
-    Error,
+    CancellationToken, DownloadThrottle, Error, FeedCache, FileHashes, HttpClientConfig, IntegrityReport, SignatureVerifier,
 };
 
+#[cfg(feature = "async")]
+use crate::sources::{self, AsyncSourceAdapter, AsyncUpdateSource, BlockingSourceAdapter};
+
 /// Configure how the update process should wait before applying updates.
 pub enum ApplyWaitMode {
     /// NOT RECOMMENDED: Will not wait for any process before continuing. This could result in the update process being
@@ -33,6 +40,17 @@ define_struct_case_insensitive! {
     pub struct VelopackAssetFeed {
         /// The list of assets in the (probably remote) update feed.
         pub Assets: Vec<VelopackAsset>,
+        /// An optional number which is increased every time the feed is published. UpdateManager will refuse
+        /// a feed with a lower sequence than the highest it has seen before, to protect against rollbacks.
+        pub Sequence: u64,
+        /// An optional RFC 3339 timestamp after which this feed should no longer be trusted, to protect
+        /// against a stale feed being served forever.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub Expires: Option<String>,
+        /// An optional version below which the app is no longer supported. If the current version is lower than this,
+        /// updates will be reported as required (see `UpdateInfo::IsRequired`).
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub MinimumSupportedVersion: Option<String>,
     }
 }
 
@@ -41,6 +59,45 @@ impl VelopackAssetFeed {
     pub fn find(&self, release_name: &str) -> Option<&VelopackAsset> {
         self.Assets.iter().find(|x| x.FileName.eq_ignore_ascii_case(release_name))
     }
+
+    /// Returns the minimum supported version of this feed, or None if it is not specified or is not a valid version.
+    pub fn get_minimum_supported_version(&self) -> Option<Version> {
+        let version = self.MinimumSupportedVersion.as_deref().map(str::trim).filter(|v| !v.is_empty())?;
+        match Version::parse(version) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                warn!("Ignoring invalid minimum supported version '{}' in feed: {}", version, e);
+                None
+            }
+        }
+    }
+
+    /// Returns the time at which this feed expires, or None if it does not expire.
+    pub fn get_expiry(&self) -> Result<Option<chrono::DateTime<chrono::FixedOffset>>, Error> {
+        match self.Expires.as_deref().map(str::trim) {
+            Some(expires) if !expires.is_empty() => chrono::DateTime::parse_from_rfc3339(expires)
+                .map(Some)
+                .map_err(|e| Error::Other(format!("Invalid release feed expiry '{}': {}", expires, e))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Adds the assets from another feed to this one. The merged feed keeps the highest sequence, the latest expiry,
+    /// and the highest minimum supported version.
+    pub(crate) fn merge(&mut self, other: VelopackAssetFeed) {
+        if let Some(other_minimum) = other.get_minimum_supported_version() {
+            if !matches!(self.get_minimum_supported_version(), Some(minimum) if minimum >= other_minimum) {
+                self.MinimumSupportedVersion = other.MinimumSupportedVersion.clone();
+            }
+        }
+        if let Ok(Some(other_expiry)) = other.get_expiry() {
+            if !matches!(self.get_expiry(), Ok(Some(expiry)) if expiry >= other_expiry) {
+                self.Expires = other.Expires;
+            }
+        }
+        self.Sequence = self.Sequence.max(other.Sequence);
+        self.Assets.extend(other.Assets);
+    }
 }
 
 define_struct_case_insensitive! {
@@ -60,12 +117,91 @@ define_struct_case_insensitive! {
         pub SHA1: String,
         /// The SHA256 checksum of the update package containing this release.
         pub SHA256: String,
+        /// Additional checksums of the update package, keyed by algorithm name (eg. {"sha512": "9B71...", "blake3": "EA8F..."}).
+        /// Supported algorithms are sha1, sha256, sha512 and blake3. Unsupported algorithms are ignored.
+        pub Hashes: HashMap<String, String>,
         /// The size in bytes of the update package containing this release.
         pub Size: u64,
         /// The release notes in markdown format, as passed to Velopack when packaging the release. This may be an empty string.
         pub NotesMarkdown: String,
         /// The release notes in HTML format, transformed from Markdown when packaging the release. This may be an empty string.
         pub NotesHtml: String,
+        /// The CPU architecture this release was built for (eg. 'x64' or 'arm64'). If not specified, it can run on any architecture.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub Arch: Option<String>,
+        /// The operating system this release was built for ('win', 'osx' or 'linux'). If not specified, it can run on any operating system.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub OS: Option<String>,
+        /// The minimum operating system version this release can run on (eg. '10.0.19041'). If not specified, it can run on any version.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub MinOSVersion: Option<String>,
+        /// The percentage of users (1-99) this release has been rolled out to so far. Users are bucketed by their staged user id,
+        /// so the same users stay in the rollout as the percentage is increased. 0 (the default) or 100 means everyone.
+        #[serde(skip_serializing_if = "is_zero")]
+        pub RolloutPercentage: u32,
+        /// True if this release must be installed by every user on an older version (eg. because of a security fix or a server
+        /// protocol change). Updating past this release will be reported as required (see `UpdateInfo::IsRequired`).
+        #[serde(skip_serializing_if = "is_false")]
+        pub IsMandatory: bool,
+        /// True if this release has been retracted by the publisher (eg. because it is broken). Retracted releases are never
+        /// offered as updates, and users running a retracted version will be offered the newest release which is not retracted,
+        /// even if it is a lower version.
+        #[serde(skip_serializing_if = "is_false")]
+        pub Retracted: bool,
+        /// For delta packages, the version this delta must be applied to. If not specified, it is applied to the release
+        /// immediately before it, but publishers can also ship cumulative deltas (eg. from 1.0.0 straight to 1.5.0).
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub BaseVersion: Option<String>,
+    }
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Returns the staged rollout bucket (0-99) for the specified staged user id. This is stable for a given user id.
+fn get_rollout_bucket(staged_user_id: &str) -> u32 {
+    use sha2::Digest;
+    let digest = sha2::Sha256::digest(staged_user_id.as_bytes());
+    u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]) % 100
+}
+
+impl VelopackAsset {
+    /// Returns every supported checksum of this asset (from Hashes, SHA256 and SHA1), strongest first.
+    pub fn get_checksums(&self) -> Vec<(HashAlgorithm, String)> {
+        let mut checksums: Vec<(HashAlgorithm, String)> = self.Hashes.iter().filter_map(|(a, d)| hashes::parse_asset_hash(a, d)).collect();
+        if !self.SHA256.trim().is_empty() {
+            checksums.push((HashAlgorithm::Sha256, self.SHA256.trim().to_owned()));
+        }
+        if !self.SHA1.trim().is_empty() {
+            checksums.push((HashAlgorithm::Sha1, self.SHA1.trim().to_owned()));
+        }
+        // Hashes is unordered, so algorithms of equal strength are ordered by name to keep the choice stable
+        checksums.sort_by_key(|(algorithm, _)| (std::cmp::Reverse(algorithm.strength()), algorithm.name()));
+        checksums
+    }
+
+    /// Returns the algorithms a downloaded copy of this asset should be hashed with so that it can be verified. Only the
+    /// strongest checksum is verified, so that is the only algorithm returned.
+    pub fn get_download_hash_algorithms(&self) -> Vec<HashAlgorithm> {
+        self.get_checksums().into_iter().take(1).map(|(algorithm, _)| algorithm).collect()
+    }
+
+    /// Returns the reason this release can not run on the specified machine, or None if it can.
+    pub fn get_incompatibility(&self, platform: &PlatformInfo) -> Option<String> {
+        platform.get_incompatibility(self.Arch.as_deref(), self.OS.as_deref(), self.MinOSVersion.as_deref())
+    }
+
+    /// Returns true if this release has been rolled out to the user with the specified staged user id.
+    pub fn is_rolled_out_to(&self, staged_user_id: &str) -> bool {
+        if self.RolloutPercentage == 0 || self.RolloutPercentage >= 100 {
+            return true;
+        }
+        get_rollout_bucket(staged_user_id) < self.RolloutPercentage
     }
 }
 
@@ -78,34 +214,54 @@ pub struct UpdateInfo {
     pub TargetFullRelease: VelopackAsset,
     /// The base release that this update is based on. This is only available if the update is a delta update.
     pub BaseRelease: Option<VelopackAsset>,
-    /// The list of delta updates that can be applied to the base version to get to the target version.
+    /// The list of delta updates that can be applied to the base version to get to the target version, in the order they are applied.
     pub DeltasToTarget: Vec<VelopackAsset>,
     /// True if the update is a version downgrade or lateral move (such as when switching channels to the same version number).
     /// In this case, only full updates are allowed, and any local packages on disk newer than the downloaded version will be
-    /// deleted.
+    /// deleted. This is also true for an update which is older than a retracted version that has already been applied.
+    /// The updater's downgrade protection is bypassed when an update with this flag is applied.
     pub IsDowngrade: bool,
+    /// True if the current version is no longer supported (it is older than the feed's minimum supported version, or there is
+    /// a newer mandatory release), so the app should not be used until this update has been applied.
+    pub IsRequired: bool,
+    /// The total size in bytes of the packages which will be downloaded for this update (the full package, or the deltas).
+    pub DownloadSize: u64,
+    /// Explains why this update will be applied with a full package or with deltas, for logging or diagnostics.
+    pub StrategyReason: String,
 }
 
 impl UpdateInfo {
-    pub(crate) fn new_full(target: VelopackAsset, is_downgrade: bool) -> UpdateInfo {
+    pub(crate) fn new_full(target: VelopackAsset, is_downgrade: bool, reason: String) -> UpdateInfo {
         UpdateInfo {
+            DownloadSize: target.Size,
             TargetFullRelease: target,
             BaseRelease: None,
             DeltasToTarget: Vec::new(),
             IsDowngrade: is_downgrade,
+            IsRequired: false,
+            StrategyReason: reason,
         }
     }
 
-    pub(crate) fn new_delta(target: VelopackAsset, base: VelopackAsset, deltas: Vec<VelopackAsset>) -> UpdateInfo {
+    pub(crate) fn new_delta(target: VelopackAsset, base: VelopackAsset, deltas: Vec<VelopackAsset>, reason: String) -> UpdateInfo {
         UpdateInfo {
+            DownloadSize: deltas.iter().map(|d| d.Size).sum(),
             TargetFullRelease: target,
             BaseRelease: Some(base),
             DeltasToTarget: deltas,
             IsDowngrade: false,
+            IsRequired: false,
+            StrategyReason: reason,
         }
     }
 }
 
+/// Each delta is applied by rewriting the whole package, so when deciding between deltas and a full update,
+/// applying each delta is estimated to cost as much as downloading this percentage of the full package.
+const DELTA_PATCH_COST_PERCENT: u64 = 5;
+
+const DOWNGRADE_STRATEGY_REASON: &str = "Downgrades and lateral moves (such as switching channels) can only be applied with a full package.";
+
 impl AsRef<VelopackAsset> for UpdateInfo {
     fn as_ref(&self) -> &VelopackAsset {
         &self.TargetFullRelease
@@ -118,6 +274,29 @@ impl AsRef<VelopackAsset> for VelopackAsset {
     }
 }
 
+/// An update which can be applied, either the `UpdateInfo` returned by `check_for_updates` or just the `VelopackAsset`
+/// to apply. Only an `UpdateInfo` can carry `IsDowngrade`, so pass that when it is available.
+pub trait UpdateToApply: AsRef<VelopackAsset> {
+    /// True if the updater's downgrade protection should be bypassed when applying this update.
+    fn is_downgrade(&self) -> bool {
+        false
+    }
+}
+
+impl UpdateToApply for UpdateInfo {
+    fn is_downgrade(&self) -> bool {
+        self.IsDowngrade
+    }
+}
+
+impl UpdateToApply for VelopackAsset {}
+
+impl<T: UpdateToApply> UpdateToApply for &T {
+    fn is_downgrade(&self) -> bool {
+        (*self).is_downgrade()
+    }
+}
+
 /// Options to customise the behaviour of UpdateManager.
 #[allow(non_snake_case)]
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -126,7 +305,9 @@ pub struct UpdateOptions {
     /// Allows UpdateManager to update to a version that's lower than the current version (i.e. downgrading).
     /// This could happen if a release has bugs and was retracted from the release feed, or if you're using
     /// ExplicitChannel to switch channels to another channel where the latest version on that
-    /// channel is lower than the current version.
+    /// channel is lower than the current version. Otherwise, versions older than the highest version which has
+    /// ever been applied are ignored, even if the local app manifest has been rolled back. If the current version has been
+    /// retracted from the feed, an older release will be offered regardless of this option.
     pub AllowVersionDowngrade: bool,
     /// **This option should usually be left None**.
     /// Overrides the default channel used to fetch updates.
@@ -137,14 +318,42 @@ pub struct UpdateOptions {
     /// without having to reinstall the application.
     pub ExplicitChannel: Option<String>,
     /// Sets the maximum number of deltas to consider before falling back to a full update.
-    /// The default is 10. Set to a negative number (eg. -1) to disable deltas.
+    /// The default is 10. Set to a negative number (eg. -1) to disable deltas. A full update is also used if the deltas
+    /// (including the estimated cost of applying them) would cost more than downloading the full package.
     pub MaximumDeltasBeforeFallback: i32,
+    /// Customises the HTTP client (proxy, timeouts, user agent, trusted root certificates) used by the update source.
+    /// This is ignored by sources which were explicitly given their own configuration.
+    pub HttpClient: Option<HttpClientConfig>,
+    /// Release feeds are cached in the packages directory, and are only used after the server confirms they have
+    /// not changed. Set this to a positive number of seconds (eg. 604800 for 7 days) to also use a cached feed no
+    /// older than this if the update server can not be reached. The default (0) disables this offline fallback.
+    pub MaximumCachedFeedAgeSeconds: i64,
+    /// Limits the rate at which updates are downloaded, in bytes per second. The default (0) is unlimited.
+    /// This can be changed while a download is in progress with `set_maximum_download_rate`.
+    pub MaximumDownloadBytesPerSecond: u64,
+    /// If set, downloads start in background mode and are limited to this many bytes per second, until
+    /// `boost_downloads` is called (eg. because the user asked to update now). The default (0) is disabled.
+    pub BackgroundDownloadBytesPerSecond: u64,
+    /// Base64 encoded Ed25519 public keys which are trusted to sign the release feed. If any keys are provided,
+    /// the release feed must have a valid detached signature (eg. 'releases.{channel}.json.sig') made by one of
+    /// them, otherwise checking for updates will fail. Several keys can be trusted at once to allow key rotation.
+    /// The signature is made over the feed file name and a newline, followed by the feed contents, so that a feed
+    /// signed for one channel can not be served as another.
+    pub TrustedFeedPublicKeys: Vec<String>,
+    /// If set (eg. 'sha256'), packages must have a checksum using this algorithm or a stronger one, otherwise
+    /// downloading the update will fail. This can be used to reject feeds which only provide SHA1 checksums.
+    /// Supported algorithms are sha1, sha256, sha512 and blake3.
+    pub MinimumHashAlgorithm: Option<String>,
 }
 
 struct UpdateManagerInner {
     options: UpdateOptions,
-    source: Box<dyn UpdateSource>,
+    source: Arc<dyn UpdateSource>,
+    #[cfg(feature = "async")]
+    async_source: Arc<dyn AsyncUpdateSource>,
     locator: VelopackLocator,
+    download_throttle: DownloadThrottle,
+    cancellation_token: CancellationToken,
 }
 
 /// Provides functionality for checking for updates, downloading updates, and applying updates to the current application.
@@ -161,6 +370,9 @@ pub enum UpdateCheck {
     NoUpdateAvailable,
     /// The remote feed had an update available
     UpdateAvailable(Box<UpdateInfo>),
+    /// The remote feed has a newer release, but it can not run on this machine (eg. it requires a different CPU architecture
+    /// or a newer operating system), and there is no newer release which can. This contains the newest incompatible release.
+    UpdateIncompatible(Box<VelopackAsset>),
 }
 
 impl UpdateManager {
@@ -191,10 +403,93 @@ impl UpdateManager {
     /// let um = UpdateManager::new_boxed(Box::new(source), None, None);
     /// ```
     pub fn new_boxed(
-        source: Box<dyn UpdateSource>,
+        mut source: Box<dyn UpdateSource>,
+        options: Option<UpdateOptions>,
+        locator: Option<VelopackLocatorConfig>,
+    ) -> Result<UpdateManager, Error> {
+        let (options, locator, feed_cache) = Self::load_options_and_locator(options, locator)?;
+        let download_throttle = Self::get_download_throttle(&options);
+        if let Some(http_config) = &options.HttpClient {
+            source.set_default_http_config(http_config);
+        }
+        source.set_feed_cache(&feed_cache);
+        source.set_download_throttle(&download_throttle);
+        let cancellation_token = CancellationToken::new();
+        source.set_cancellation_token(&cancellation_token);
+        if let Some(verifier) = Self::get_feed_verifier(&options)? {
+            if !source.set_feed_verifier(&verifier) {
+                return Err(Error::NotSupported(
+                    "TrustedFeedPublicKeys were provided, but the update source can not verify release feed signatures".to_owned(),
+                ));
+            }
+        }
+        let source: Arc<dyn UpdateSource> = Arc::from(source);
+        Ok(UpdateManager {
+            inner: Arc::new(UpdateManagerInner {
+                options,
+                #[cfg(feature = "async")]
+                async_source: Arc::new(AsyncSourceAdapter::new_shared(source.clone())),
+                source,
+                locator,
+                download_throttle,
+                cancellation_token,
+            }),
+        })
+    }
+
+    /// Create a new UpdateManager instance using the specified AsyncUpdateSource. The blocking methods of
+    /// the UpdateManager can still be used, in which case requests are made as described in `BlockingSourceAdapter`.
+    /// This will return an error if the application is not yet installed.
+    #[cfg(feature = "async")]
+    pub fn new_async<T: AsyncUpdateSource + 'static>(
+        source: T,
+        options: Option<UpdateOptions>,
+        locator: Option<VelopackLocatorConfig>,
+    ) -> Result<UpdateManager, Error> {
+        UpdateManager::new_async_boxed(Box::new(source), options, locator)
+    }
+
+    /// Create a new UpdateManager instance using the specified AsyncUpdateSource.
+    /// This will return an error if the application is not yet installed.
+    #[cfg(feature = "async")]
+    pub fn new_async_boxed(
+        mut source: Box<dyn AsyncUpdateSource>,
         options: Option<UpdateOptions>,
         locator: Option<VelopackLocatorConfig>,
     ) -> Result<UpdateManager, Error> {
+        let (options, locator, feed_cache) = Self::load_options_and_locator(options, locator)?;
+        let download_throttle = Self::get_download_throttle(&options);
+        if let Some(http_config) = &options.HttpClient {
+            source.set_default_http_config(http_config);
+        }
+        source.set_feed_cache(&feed_cache);
+        source.set_download_throttle(&download_throttle);
+        let cancellation_token = CancellationToken::new();
+        source.set_cancellation_token(&cancellation_token);
+        if let Some(verifier) = Self::get_feed_verifier(&options)? {
+            if !source.set_feed_verifier(&verifier) {
+                return Err(Error::NotSupported(
+                    "TrustedFeedPublicKeys were provided, but the update source can not verify release feed signatures".to_owned(),
+                ));
+            }
+        }
+        let async_source: Arc<dyn AsyncUpdateSource> = Arc::from(source);
+        Ok(UpdateManager {
+            inner: Arc::new(UpdateManagerInner {
+                options,
+                source: Arc::new(BlockingSourceAdapter::new_shared(async_source.clone())),
+                async_source,
+                locator,
+                download_throttle,
+                cancellation_token,
+            }),
+        })
+    }
+
+    fn load_options_and_locator(
+        options: Option<UpdateOptions>,
+        locator: Option<VelopackLocatorConfig>,
+    ) -> Result<(UpdateOptions, VelopackLocator, FeedCache), Error> {
         let locator = if let Some(config) = locator {
             warn!("Using explicit locator configuration, ignoring auto-locate.");
             VelopackLocator::new(&config)?
@@ -205,9 +500,49 @@ impl UpdateManager {
         if options.MaximumDeltasBeforeFallback == 0 {
             options.MaximumDeltasBeforeFallback = 10;
         }
-        Ok(UpdateManager {
-            inner: Arc::new(UpdateManagerInner { options, source, locator }),
-        })
+        Self::get_minimum_hash_algorithm(&options)?;
+        let feed_cache = FeedCache::new(locator.get_packages_dir().join(".feeds"), Self::get_max_offline_age(&options));
+        Ok((options, locator, feed_cache))
+    }
+
+    fn get_max_offline_age(options: &UpdateOptions) -> Option<Duration> {
+        u64::try_from(options.MaximumCachedFeedAgeSeconds)
+            .ok()
+            .filter(|s| *s > 0)
+            .map(Duration::from_secs)
+    }
+
+    fn get_minimum_hash_algorithm(options: &UpdateOptions) -> Result<Option<HashAlgorithm>, Error> {
+        match options.MinimumHashAlgorithm.as_deref().map(str::trim) {
+            Some(name) if !name.is_empty() => HashAlgorithm::from_name(name)
+                .map(Some)
+                .ok_or_else(|| Error::Other(format!("Unsupported MinimumHashAlgorithm '{}'", name))),
+            _ => Ok(None),
+        }
+    }
+
+    fn get_feed_verifier(options: &UpdateOptions) -> Result<Option<SignatureVerifier>, Error> {
+        if options.TrustedFeedPublicKeys.is_empty() {
+            return Ok(None);
+        }
+        info!(
+            "Release feeds must be signed by one of {} trusted key(s).",
+            options.TrustedFeedPublicKeys.len()
+        );
+        Ok(Some(SignatureVerifier::new(&options.TrustedFeedPublicKeys)?))
+    }
+
+    fn get_download_throttle(options: &UpdateOptions) -> DownloadThrottle {
+        let throttle = DownloadThrottle::new(options.MaximumDownloadBytesPerSecond);
+        if options.BackgroundDownloadBytesPerSecond > 0 {
+            info!(
+                "Downloads will start in background mode at {} bytes per second.",
+                options.BackgroundDownloadBytesPerSecond
+            );
+            throttle.with_background_rate(options.BackgroundDownloadBytesPerSecond)
+        } else {
+            throttle
+        }
     }
 
     fn get_practical_channel(&self) -> String {
@@ -251,6 +586,43 @@ impl UpdateManager {
         self.inner.locator.get_is_portable()
     }
 
+    /// Leave background download mode (see `UpdateOptions::BackgroundDownloadBytesPerSecond`), so that any
+    /// download in progress continues at the maximum download rate. This has no effect if not in background mode.
+    pub fn boost_downloads(&self) {
+        if self.inner.download_throttle.is_background() {
+            info!("Boosting downloads out of background mode.");
+            self.inner.download_throttle.boost();
+        }
+    }
+
+    /// Change the maximum download rate in bytes per second (0 is unlimited). This applies immediately,
+    /// including to any download which is already in progress.
+    pub fn set_maximum_download_rate(&self, bytes_per_second: u64) {
+        self.inner.download_throttle.set_bytes_per_second(bytes_per_second);
+    }
+
+    /// Cancel the download which is currently in progress (if any). The download will stop as soon as possible
+    /// and `download_updates` will return `Error::Cancelled`. If no download is in progress, the next call to
+    /// `download_updates` or `repair_installation` is cancelled instead. A partially downloaded package is kept,
+    /// so the download can be resumed by calling `download_updates` again.
+    pub fn cancel_downloads(&self) {
+        info!("Cancelling downloads.");
+        self.inner.cancellation_token.cancel();
+    }
+
+    /// Clears the cancellation once an operation has stopped because of it, so the next operation can run.
+    fn consume_cancellation<T>(&self, result: Result<T, Error>) -> Result<T, Error> {
+        if let Err(Error::Cancelled) = result {
+            self.inner.cancellation_token.reset();
+        }
+        result
+    }
+
+    /// Returns a handle which can be used to cancel downloads from another thread, see `cancel_downloads`.
+    pub fn get_cancellation_token(&self) -> CancellationToken {
+        self.inner.cancellation_token.clone()
+    }
+
     /// Returns None if there is no local package waiting to be applied. Returns a VelopackAsset
     /// if there is an update downloaded which has not yet been applied. In that case, the
     /// VelopackAsset can be applied by calling apply_updates_and_restart or wait_exit_then_apply_updates.
@@ -264,22 +636,96 @@ impl UpdateManager {
         None
     }
 
-    /// Get a list of available remote releases from the package source.
+    /// Get a list of available remote releases from the package source. Returns `Error::FeedRollback` if the feed
+    /// sequence is lower than one which has been seen before, or `Error::FeedExpired` if the feed has expired.
     pub fn get_release_feed(&self) -> Result<VelopackAssetFeed, Error> {
         let channel = self.get_practical_channel();
         let staged_user_id = self.inner.locator.get_staged_user_id();
-        self.inner
+        let feed = self
+            .inner
             .source
-            .get_release_feed(&channel, &self.inner.locator.get_manifest(), staged_user_id.as_str())
+            .get_release_feed(&channel, &self.inner.locator.get_manifest(), staged_user_id.as_str())?;
+        self.verify_feed_is_current(&channel, &feed)?;
+        Ok(feed)
+    }
+
+    /// Get a list of available remote releases from the package source, without blocking the current thread.
+    #[cfg(feature = "async")]
+    pub async fn get_release_feed_async(&self) -> Result<VelopackAssetFeed, Error> {
+        let channel = self.get_practical_channel();
+        let staged_user_id = self.inner.locator.get_staged_user_id();
+        let feed = self
+            .inner
+            .async_source
+            .get_release_feed_async(&channel, &self.inner.locator.get_manifest(), staged_user_id.as_str())
+            .await?;
+        self.verify_feed_is_current(&channel, &feed)?;
+        Ok(feed)
+    }
+
+    /// Rejects feeds which are expired, or older than the newest feed we have seen before. The highest feed
+    /// sequence is persisted (per channel) in the packages directory.
+    fn verify_feed_is_current(&self, channel: &str, feed: &VelopackAssetFeed) -> Result<(), Error> {
+        if !is_valid_channel_name(channel) {
+            return Err(Error::Other(format!(
+                "Channel {:?} is not valid, it may only contain letters, digits and '.', '-' or '_' separators.",
+                channel
+            )));
+        }
+
+        let packages_dir = self.inner.locator.get_packages_dir();
+        let sequence_path = packages_dir.join(format!(".feedSequence.{}", channel));
+        let highest_sequence = fs::read_to_string(&sequence_path)
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        if feed.Sequence < highest_sequence {
+            error!(
+                "Release feed sequence {} is lower than previously seen sequence {}.",
+                feed.Sequence, highest_sequence
+            );
+            return Err(Error::FeedRollback(highest_sequence, feed.Sequence));
+        }
+
+        if let Some(expiry) = feed.get_expiry()? {
+            if expiry < chrono::Utc::now() {
+                error!("Release feed expired at {}.", expiry.to_rfc3339());
+                return Err(Error::FeedExpired(expiry.to_rfc3339()));
+            }
+        }
+
+        if feed.Sequence > highest_sequence {
+            debug!("Saving new highest release feed sequence {} for channel '{}'.", feed.Sequence, channel);
+            if let Err(e) = fs::create_dir_all(&packages_dir).and_then(|_| fs::write(&sequence_path, feed.Sequence.to_string())) {
+                warn!("Failed to save release feed sequence to {:?}: {}", sequence_path, e);
+            }
+        }
+        Ok(())
     }
 
     /// Checks for updates, returning None if there are none available. If there are updates available, this method will return an
     /// UpdateInfo object containing the latest available release, and any delta updates that can be applied if they are available.
     pub fn check_for_updates(&self) -> Result<UpdateCheck, Error> {
+        let feed = self.get_release_feed()?;
+        self.check_for_updates_in_feed(feed)
+    }
+
+    /// Checks for updates without blocking the current thread. See `check_for_updates` for details.
+    #[cfg(feature = "async")]
+    pub async fn check_for_updates_async(&self) -> Result<UpdateCheck, Error> {
+        let feed = self.get_release_feed_async().await?;
+        self.check_for_updates_in_feed(feed)
+    }
+
+    fn check_for_updates_in_feed(&self, feed: VelopackAssetFeed) -> Result<UpdateCheck, Error> {
+        self.check_for_updates_in_feed_for_platform(feed, &PlatformInfo::current())
+    }
+
+    fn check_for_updates_in_feed_for_platform(&self, feed: VelopackAssetFeed, platform: &PlatformInfo) -> Result<UpdateCheck, Error> {
         let allow_downgrade = self.inner.options.AllowVersionDowngrade;
         let app_channel = self.inner.locator.get_manifest_channel();
         let app_version = self.inner.locator.get_manifest_version();
-        let feed = self.get_release_feed()?;
+        let minimum_supported_version = feed.get_minimum_supported_version();
         let assets = feed.Assets;
 
         let practical_channel = self.get_practical_channel();
@@ -291,10 +737,38 @@ impl UpdateManager {
 
         let mut latest: Option<&VelopackAsset> = None;
         let mut latest_version: Version = Version::parse("0.0.0")?;
+        let mut latest_incompatible: Option<(&VelopackAsset, Version)> = None;
+        let mut not_rolled_out = false;
+        let mut latest_mandatory: Option<&VelopackAsset> = None;
+        let mut retracted_versions: Vec<Version> = Vec::new();
+        let staged_user_id = self.inner.locator.get_staged_user_id();
         for asset in &assets {
             if let Ok(sv) = Version::parse(&asset.Version) {
                 if asset.Type.eq_ignore_ascii_case("Full") {
                     debug!("Found full release: {} ({}).", asset.FileName, sv);
+                    if asset.Retracted {
+                        debug!("Skipping release {} because it has been retracted.", asset.FileName);
+                        retracted_versions.push(sv);
+                        continue;
+                    }
+                    if let Some(reason) = asset.get_incompatibility(platform) {
+                        debug!("Skipping release {} because {}.", asset.FileName, reason);
+                        if latest_incompatible.as_ref().map_or(true, |(_, v)| sv > *v) {
+                            latest_incompatible = Some((asset, sv));
+                        }
+                        continue;
+                    }
+                    if sv > app_version && !asset.is_rolled_out_to(&staged_user_id) {
+                        debug!(
+                            "Skipping release {} because it has only been rolled out to {}% of users.",
+                            asset.FileName, asset.RolloutPercentage
+                        );
+                        not_rolled_out = true;
+                        continue;
+                    }
+                    if asset.IsMandatory && sv > app_version {
+                        latest_mandatory = Some(asset);
+                    }
                     if latest.is_none() || (sv > latest_version) {
                         latest = Some(asset);
                         latest_version = sv;
@@ -303,7 +777,31 @@ impl UpdateManager {
             }
         }
 
+        let is_retracted = retracted_versions.contains(&app_version);
+        if is_retracted {
+            warn!("The current version {} has been retracted by the publisher.", app_version);
+        }
+
+        // a retracted version must not stop users from moving to an older release, so updates below the highest
+        // version ever applied are still offered (as downgrades, so the updater allows them) if that version was retracted.
+        let highest_applied = self.inner.locator.get_highest_applied_version();
+        let is_highest_applied_retracted = highest_applied.as_ref().is_some_and(|v| retracted_versions.contains(v));
+
+        // report a newer release which this machine can not run, unless there is also a compatible update.
+        let incompatible_update = latest_incompatible
+            .as_ref()
+            .filter(|(_, v)| *v > app_version && (latest.is_none() || *v > latest_version));
+        if let Some((asset, version)) = incompatible_update {
+            warn!("A newer release ({}) is available, but it can not run on this machine.", version);
+            if latest.is_none() || (latest_version <= app_version && !is_retracted) {
+                return Ok(UpdateCheck::UpdateIncompatible(Box::new((*asset).clone())));
+            }
+        }
+
         if latest.is_none() {
+            if latest_incompatible.is_some() || not_rolled_out || !retracted_versions.is_empty() {
+                return Ok(UpdateCheck::NoUpdateAvailable);
+            }
             return Ok(UpdateCheck::RemoteIsEmpty);
         }
 
@@ -312,35 +810,91 @@ impl UpdateManager {
 
         debug!("Latest remote release: {} ({}).", remote_asset.FileName, remote_version);
 
+        // the local manifest may have been rolled back, so never offer a version older than one which has already been applied.
+        if let Some(highest_applied) = highest_applied.as_ref().filter(|_| !is_highest_applied_retracted) {
+            if remote_version > app_version && remote_version < *highest_applied && !allow_downgrade {
+                warn!(
+                    "Ignoring remote release {} because it is older than the highest applied version {}.",
+                    remote_version, highest_applied
+                );
+                return Ok(UpdateCheck::NoUpdateAvailable);
+            }
+        }
+
+        let is_unsupported = minimum_supported_version.as_ref().is_some_and(|min| app_version < *min);
+        if is_unsupported && remote_version <= app_version {
+            warn!(
+                "The current version {} is no longer supported, but there is no newer release available.",
+                app_version
+            );
+        }
+
         if remote_version > app_version {
             info!("Found newer remote release available ({} -> {}).", app_version, remote_version);
-            Ok(UpdateCheck::UpdateAvailable(Box::new(
-                self.create_delta_update_strategy(&assets, (remote_asset, remote_version)),
-            )))
+            let mut update = self.create_delta_update_strategy(&assets, (remote_asset, remote_version.clone()));
+            if highest_applied.is_some_and(|v| remote_version < v) {
+                info!("Release {} is older than a version which has already been applied.", remote_version);
+                update.IsDowngrade = true;
+            }
+            if let Some(mandatory) = latest_mandatory {
+                info!("Release {} is mandatory, so this update is required.", mandatory.Version);
+                update.IsRequired = true;
+            } else if is_unsupported {
+                info!("The current version {} is no longer supported, so this update is required.", app_version);
+                update.IsRequired = true;
+            }
+            Ok(UpdateCheck::UpdateAvailable(Box::new(update)))
+        } else if remote_version < app_version && is_retracted {
+            info!(
+                "Found older remote release available to replace the retracted version ({} -> {}).",
+                app_version, remote_version
+            );
+            Ok(UpdateCheck::UpdateAvailable(Box::new(UpdateInfo::new_full(
+                remote_asset.clone(),
+                true,
+                DOWNGRADE_STRATEGY_REASON.to_owned(),
+            ))))
         } else if remote_version < app_version && allow_downgrade {
             info!(
                 "Found older remote release available and downgrade is enabled ({} -> {}).",
                 app_version, remote_version
             );
-            Ok(UpdateCheck::UpdateAvailable(Box::new(UpdateInfo::new_full(remote_asset.clone(), true))))
+            Ok(UpdateCheck::UpdateAvailable(Box::new(UpdateInfo::new_full(
+                remote_asset.clone(),
+                true,
+                DOWNGRADE_STRATEGY_REASON.to_owned(),
+            ))))
         } else if remote_version == app_version && allow_downgrade && is_non_default_channel {
             info!(
                 "Latest remote release is the same version of a different channel, and downgrade is enabled ({} -> {}, {} -> {}).",
                 app_version, remote_version, app_channel, practical_channel
             );
-            Ok(UpdateCheck::UpdateAvailable(Box::new(UpdateInfo::new_full(remote_asset.clone(), true))))
+            Ok(UpdateCheck::UpdateAvailable(Box::new(UpdateInfo::new_full(
+                remote_asset.clone(),
+                true,
+                DOWNGRADE_STRATEGY_REASON.to_owned(),
+            ))))
         } else {
             Ok(UpdateCheck::NoUpdateAvailable)
         }
     }
 
     fn create_delta_update_strategy(&self, velopack_asset_feed: &[VelopackAsset], latest_remote: (&VelopackAsset, Version)) -> UpdateInfo {
+        let full_update = |reason: String| {
+            info!("{}", reason);
+            UpdateInfo::new_full(latest_remote.0.clone(), false, reason)
+        };
+
+        let max_deltas = self.inner.options.MaximumDeltasBeforeFallback;
+        if max_deltas < 0 {
+            return full_update("Delta updates are disabled, so a full update will be used.".to_owned());
+        }
+
         let packages_dir = self.inner.locator.get_packages_dir();
         let latest_local = locator::find_latest_full_package(&packages_dir);
 
         if latest_local.is_none() {
-            info!("There is no local/base package available for this update, so delta updates will be disabled.");
-            return UpdateInfo::new_full(latest_remote.0.clone(), false);
+            return full_update("There is no local/base package available for this update, so delta updates will be disabled.".to_owned());
         }
 
         let (latest_local_path, latest_local_manifest) = latest_local.unwrap();
@@ -356,29 +910,62 @@ impl UpdateManager {
             .find(|(asset, version)| asset.Type.eq_ignore_ascii_case("Delta") && version == &latest_remote.1);
 
         if matching_latest_delta.is_none() {
-            info!(
+            return full_update(format!(
                 "No matching delta update found for release {}, so deltas will be disabled.",
                 latest_remote.1
-            );
-            return UpdateInfo::new_full(latest_remote.0.clone(), false);
+            ));
         }
 
-        let mut remotes_greater_than_local = assets_and_versions
-            .iter()
-            .filter(|(asset, _version)| asset.Type.eq_ignore_ascii_case("Delta"))
-            .filter(|(_asset, version)| version > &latest_local_manifest.version && version <= &latest_remote.1)
-            .collect::<Vec<_>>();
-
-        remotes_greater_than_local.sort_by(|a, b| a.1.cmp(&b.1));
-        let remotes_greater_than_local = remotes_greater_than_local.iter().map(|obj| obj.0.clone()).collect::<Vec<VelopackAsset>>();
+        let patch_cost = (latest_remote.0.Size / 100).saturating_mul(DELTA_PATCH_COST_PERCENT);
+        let Some(delta_path) = find_delta_path(&assets_and_versions, &latest_local_manifest.version, &latest_remote.1, patch_cost) else {
+            return full_update(format!(
+                "No chain of delta updates was found from {} to {}, so deltas will be disabled.",
+                latest_local_manifest.version, latest_remote.1
+            ));
+        };
 
         info!(
             "Found {} delta updates between {} and {}.",
-            remotes_greater_than_local.len(),
+            delta_path.len(),
             latest_local_manifest.version,
             latest_remote.1
         );
-        UpdateInfo::new_delta(latest_remote.0.clone(), local_asset, remotes_greater_than_local)
+
+        if delta_path.len() > max_deltas as usize {
+            return full_update(format!(
+                "There are {} delta updates to apply, which is more than the maximum of {}, so a full update will be used.",
+                delta_path.len(),
+                max_deltas
+            ));
+        }
+
+        let full_size = latest_remote.0.Size;
+        let delta_size: u64 = delta_path.iter().map(|d| d.Size).sum();
+        let reason = if full_size == 0 || delta_path.iter().any(|d| d.Size == 0) {
+            "The package sizes are unknown, so delta updates will be used.".to_owned()
+        } else {
+            let patch_cost = patch_cost.saturating_mul(delta_path.len() as u64);
+            let delta_cost = delta_size.saturating_add(patch_cost);
+            if delta_cost >= full_size {
+                return full_update(format!(
+                    "The {} delta updates ({} bytes, plus an estimated {} bytes of patching) cost more than the full package ({} bytes), so a full update will be used.",
+                    delta_path.len(),
+                    delta_size,
+                    patch_cost,
+                    full_size
+                ));
+            }
+            format!(
+                "The {} delta updates ({} bytes, plus an estimated {} bytes of patching) cost less than the full package ({} bytes), so delta updates will be used.",
+                delta_path.len(),
+                delta_size,
+                patch_cost,
+                full_size
+            )
+        };
+
+        info!("{}", reason);
+        UpdateInfo::new_delta(latest_remote.0.clone(), local_asset, delta_path, reason)
     }
 
     /// Downloads the specified updates to the local app packages directory. Progress is reported back to the caller via an optional Sender.
@@ -387,8 +974,84 @@ impl UpdateManager {
     ///   this method will attempt to unpack and prepare them.
     /// - If there is no delta update available, or there is an error preparing delta
     ///   packages, this method will fall back to downloading the full version of the update.
+    ///
+    /// The download can be cancelled from another thread with `cancel_downloads`, in which case this returns `Error::Cancelled`.
     pub fn download_updates(&self, update: &UpdateInfo, progress: Option<Sender<i16>>) -> Result<(), Error> {
         let _mutex = &self.inner.locator.try_get_exclusive_lock()?;
+        self.consume_cancellation(self.download_updates_impl(update, progress))
+    }
+
+    fn download_updates_impl(&self, update: &UpdateInfo, progress: Option<Sender<i16>>) -> Result<(), Error> {
+        CancellationToken::check(Some(&self.inner.cancellation_token))?;
+        let Some(pending) = self.prepare_download(update)? else {
+            return Ok(());
+        };
+
+        if update.BaseRelease.is_some() && !update.DeltasToTarget.is_empty() {
+            info!("Beginning delta update process.");
+            match self.download_and_apply_delta_updates(update, &pending.partial_file, progress.clone()) {
+                Ok(()) => {}
+                Err(Error::Cancelled) => return Err(Error::Cancelled),
+                Err(e) => {
+                    info!("Falling back to full update... (delta error was: {})", e);
+                    self.download_and_verify_package(&update.TargetFullRelease, &pending.partial_file, progress)?;
+                    info!("Successfully downloaded file: '{:?}'", pending.partial_file);
+                }
+            }
+        } else {
+            self.download_and_verify_package(&update.TargetFullRelease, &pending.partial_file, progress)?;
+            info!("Successfully downloaded file: '{:?}'", pending.partial_file);
+        }
+
+        self.finish_download(pending)
+    }
+
+    /// Downloads the specified updates without blocking the current thread. See `download_updates` for details.
+    /// Checksum verification and applying delta packages are run on the tokio blocking thread pool.
+    #[cfg(feature = "async")]
+    pub async fn download_updates_async(&self, update: &UpdateInfo, progress: Option<Sender<i16>>) -> Result<(), Error> {
+        let _mutex = &self.inner.locator.try_get_exclusive_lock()?;
+        self.consume_cancellation(self.download_updates_impl_async(update, progress).await)
+    }
+
+    #[cfg(feature = "async")]
+    async fn download_updates_impl_async(&self, update: &UpdateInfo, progress: Option<Sender<i16>>) -> Result<(), Error> {
+        CancellationToken::check(Some(&self.inner.cancellation_token))?;
+        let Some(pending) = self.prepare_download(update)? else {
+            return Ok(());
+        };
+
+        if update.BaseRelease.is_some() && !update.DeltasToTarget.is_empty() {
+            info!("Beginning delta update process.");
+            match self
+                .download_and_apply_delta_updates_async(update, &pending.partial_file, progress.clone())
+                .await
+            {
+                Ok(()) => {}
+                Err(Error::Cancelled) => return Err(Error::Cancelled),
+                Err(e) => {
+                    info!("Falling back to full update... (delta error was: {})", e);
+                    self.download_and_verify_package_async(&update.TargetFullRelease, &pending.partial_file, progress)
+                        .await?;
+                    info!("Successfully downloaded file: '{:?}'", pending.partial_file);
+                }
+            }
+        } else {
+            self.download_and_verify_package_async(&update.TargetFullRelease, &pending.partial_file, progress)
+                .await?;
+            info!("Successfully downloaded file: '{:?}'", pending.partial_file);
+        }
+
+        let um = self.clone();
+        sources::spawn_blocking(move || um.finish_download(pending)).await
+    }
+
+    /// Finds the paths for a new download, and the old packages that should be cleaned up once it is complete.
+    /// Returns None if the package has already been downloaded.
+    fn prepare_download(&self, update: &UpdateInfo) -> Result<Option<PendingDownload>, Error> {
+        // fail before downloading anything if the feed does not provide a strong enough checksum.
+        self.get_package_checksum(&update.TargetFullRelease)?;
+
         let name = &update.TargetFullRelease.FileName;
         let packages_dir = &self.inner.locator.get_packages_dir();
 
@@ -398,47 +1061,33 @@ impl UpdateManager {
 
         if final_target_file.exists() {
             info!("Package already exists on disk, skipping download: '{:?}'", final_target_file);
-            return Ok(());
+            return Ok(None);
         }
 
         let old_nupkg_pattern = format!("{}/*.nupkg", packages_dir.to_string_lossy());
         let old_partial_pattern = format!("{}/*.partial", packages_dir.to_string_lossy());
-        let delta_pattern = format!("{}/*-delta.nupkg", packages_dir.to_string_lossy());
+        let old_resume_pattern = format!("{}/*.partial.resume", packages_dir.to_string_lossy());
         let mut to_delete = Vec::new();
 
-        fn find_files_to_delete(pattern: &str, to_delete: &mut Vec<PathBuf>) {
-            match glob::glob(pattern) {
-                Ok(paths) => {
-                    for path in paths.into_iter().flatten() {
-                        to_delete.push(path);
-                    }
-                }
-                Err(e) => {
-                    error!("Error while searching for packages to clean: {}", e);
-                }
-            }
-        }
-
         find_files_to_delete(&old_nupkg_pattern, &mut to_delete);
         find_files_to_delete(&old_partial_pattern, &mut to_delete);
+        find_files_to_delete(&old_resume_pattern, &mut to_delete);
 
-        if update.BaseRelease.is_some() && !update.DeltasToTarget.is_empty() {
-            info!("Beginning delta update process.");
-            if self.download_and_apply_delta_updates(update, &partial_file, progress.clone()).is_err() {
-                info!("Falling back to full update...");
-                self.inner
-                    .source
-                    .download_release_entry(&update.TargetFullRelease, &partial_file, progress)?;
-                self.verify_package_checksum(&partial_file, &update.TargetFullRelease)?;
-                info!("Successfully downloaded file: '{:?}'", partial_file);
-            }
-        } else {
-            self.inner
-                .source
-                .download_release_entry(&update.TargetFullRelease, &partial_file, progress)?;
-            self.verify_package_checksum(&partial_file, &update.TargetFullRelease)?;
-            info!("Successfully downloaded file: '{:?}'", partial_file);
-        }
+        Ok(Some(PendingDownload {
+            final_target_file,
+            partial_file,
+            to_delete,
+        }))
+    }
+
+    fn finish_download(&self, pending: PendingDownload) -> Result<(), Error> {
+        let PendingDownload {
+            final_target_file,
+            partial_file,
+            mut to_delete,
+        } = pending;
+        let packages_dir = &self.inner.locator.get_packages_dir();
+        let delta_pattern = format!("{}/*-delta.nupkg", packages_dir.to_string_lossy());
 
         info!("Renaming partial file to final target: '{:?}'", final_target_file);
         fs::rename(&partial_file, &final_target_file)?;
@@ -450,8 +1099,7 @@ impl UpdateManager {
         match crate::bundle::load_bundle_from_file(&final_target_file) {
             Ok(bundle) => {
                 info!("Bundle loaded successfully.");
-                let update_exe_path = self.inner.locator.get_update_path();
-                if let Err(e) = bundle.extract_zip_predicate_to_path(|f| f.ends_with("Squirrel.exe"), update_exe_path) {
+                if let Err(e) = extract_updater_from_bundle(&bundle, &self.inner.locator.get_update_path()) {
                     error!("Error extracting Update.exe from bundle: {}", e);
                 }
             }
@@ -468,48 +1116,100 @@ impl UpdateManager {
         Ok(())
     }
 
-    fn download_and_apply_delta_updates(&self, update: &UpdateInfo, output_file: &PathBuf, progress: Option<Sender<i16>>) -> Result<(), Error> {
+    fn download_and_apply_delta_updates(&self, update: &UpdateInfo, output_file: &Path, progress: Option<Sender<i16>>) -> Result<(), Error> {
         let packages_dir = self.inner.locator.get_packages_dir();
-        let base_release_path = packages_dir.join(&update.BaseRelease.as_ref().unwrap().FileName);
-
-        let mut args: Vec<OsString> = vec![
-            "patch".into(),
-            "--old".into(),
-            base_release_path.clone().into(),
-            "--output".into(),
-            output_file.clone().into(),
-        ];
+        let mut delta_files = Vec::new();
 
         for (i, delta) in update.DeltasToTarget.iter().enumerate() {
             let delta_file = packages_dir.join(&delta.FileName);
             let partial_file = delta_file.with_extension("partial");
 
+            CancellationToken::check(Some(&self.inner.cancellation_token))?;
             info!("Downloading delta package: '{}'", &delta.FileName);
-            self.inner.source.download_release_entry(delta, &partial_file, None)?;
-            self.verify_package_checksum(&partial_file, delta)?;
+            self.download_and_verify_package(delta, &partial_file, None)?;
 
             fs::rename(&partial_file, &delta_file)?;
             debug!("Successfully downloaded file: '{}'", &delta.FileName);
             if let Some(progress) = &progress {
                 let _ = progress.send(((i as f64 / update.DeltasToTarget.len() as f64) * 70.0) as i16);
             }
+            delta_files.push(delta_file);
+        }
+
+        self.apply_delta_updates(update, output_file, delta_files, progress)
+    }
+
+    #[cfg(feature = "async")]
+    async fn download_and_apply_delta_updates_async(
+        &self,
+        update: &UpdateInfo,
+        output_file: &Path,
+        progress: Option<Sender<i16>>,
+    ) -> Result<(), Error> {
+        let packages_dir = self.inner.locator.get_packages_dir();
+        let mut delta_files = Vec::new();
+
+        for (i, delta) in update.DeltasToTarget.iter().enumerate() {
+            let delta_file = packages_dir.join(&delta.FileName);
+            let partial_file = delta_file.with_extension("partial");
+
+            CancellationToken::check(Some(&self.inner.cancellation_token))?;
+            info!("Downloading delta package: '{}'", &delta.FileName);
+            self.download_and_verify_package_async(delta, &partial_file, None).await?;
+
+            tokio::fs::rename(&partial_file, &delta_file).await?;
+            debug!("Successfully downloaded file: '{}'", &delta.FileName);
+            if let Some(progress) = &progress {
+                let _ = progress.send(((i as f64 / update.DeltasToTarget.len() as f64) * 70.0) as i16);
+            }
+            delta_files.push(delta_file);
+        }
+
+        let um = self.clone();
+        let update = update.clone();
+        let output_file = output_file.to_path_buf();
+        sources::spawn_blocking(move || um.apply_delta_updates(&update, &output_file, delta_files, progress)).await
+    }
+
+    fn apply_delta_updates(
+        &self,
+        update: &UpdateInfo,
+        output_file: &Path,
+        delta_files: Vec<PathBuf>,
+        progress: Option<Sender<i16>>,
+    ) -> Result<(), Error> {
+        let packages_dir = self.inner.locator.get_packages_dir();
+        let base_release_path = packages_dir.join(&update.BaseRelease.as_ref().unwrap().FileName);
+
+        let mut args: Vec<OsString> = vec![
+            "patch".into(),
+            "--old".into(),
+            base_release_path.clone().into(),
+            "--output".into(),
+            output_file.into(),
+        ];
 
+        for delta_file in delta_files {
             args.push("--delta".into());
             args.push(delta_file.into());
         }
 
+        // the patched package is re-compressed, so it is never byte-identical to the published full package and
+        // can not be checked against its hash. instead, every patched file is verified against its delta shasum.
         info!("Applying {} patches to {:?}.", update.DeltasToTarget.len(), output_file);
 
+        // the patch output replaces any partially downloaded full package, so it can no longer be resumed.
+        download::clear_resume_state(output_file);
+
         if let Some(progress) = &progress {
             let _ = progress.send(70);
         }
 
-        let output = std::process::Command::new(self.inner.locator.get_update_path()).args(args).output()?;
-        if output.status.success() {
+        let (status, stderr) = self.run_patch_process(&args, output_file)?;
+        if status.success() {
             info!("Successfully applied delta updates.");
         } else {
-            let error_message = String::from_utf8_lossy(&output.stderr);
-            error!("Error applying delta updates: {}", error_message);
+            error!("Error applying delta updates: {}", stderr);
             return Err(Error::Io(std::io::Error::other("Process exited with non-zero status")));
         }
 
@@ -519,38 +1219,239 @@ impl UpdateManager {
         Ok(())
     }
 
-    fn verify_package_checksum(&self, file: &Path, asset: &VelopackAsset) -> Result<(), Error> {
+    /// Runs the patch process, killing it if the download is cancelled. Returns the exit status and stderr.
+    fn run_patch_process(&self, args: &[OsString], output_file: &Path) -> Result<(std::process::ExitStatus, String), Error> {
+        let mut child = std::process::Command::new(self.inner.locator.get_update_path())
+            .args(args)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::piped())
+            .spawn()?;
+
+        // read stderr on another thread, so the process can not block on a full pipe while we wait for it.
+        let mut stderr_pipe = child.stderr.take();
+        let stderr_reader = std::thread::spawn(move || {
+            let mut stderr = String::new();
+            if let Some(pipe) = stderr_pipe.as_mut() {
+                let _ = std::io::Read::read_to_string(pipe, &mut stderr);
+            }
+            stderr
+        });
+
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if self.inner.cancellation_token.is_cancelled() {
+                info!("Delta update was cancelled, stopping patch process.");
+                let _ = child.kill();
+                let _ = child.wait();
+                let _ = stderr_reader.join();
+                // the patch output is incomplete, and can not be resumed.
+                let _ = fs::remove_file(output_file);
+                return Err(Error::Cancelled);
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        };
+
+        let stderr = stderr_reader.join().unwrap_or_default();
+        Ok((status, stderr))
+    }
+
+    fn download_and_verify_package(&self, asset: &VelopackAsset, partial_file: &Path, progress: Option<Sender<i16>>) -> Result<(), Error> {
+        let hashes = self.inner.source.download_release_entry_with_hashes(asset, partial_file, progress)?;
+        self.verify_downloaded_package(asset, partial_file, hashes.as_ref())
+    }
+
+    #[cfg(feature = "async")]
+    async fn download_and_verify_package_async(
+        &self,
+        asset: &VelopackAsset,
+        partial_file: &Path,
+        progress: Option<Sender<i16>>,
+    ) -> Result<(), Error> {
+        let hashes = self
+            .inner
+            .async_source
+            .download_release_entry_with_hashes_async(asset, partial_file, progress)
+            .await?;
+        let um = self.clone();
+        let asset = asset.clone();
+        let partial_file = partial_file.to_path_buf();
+        sources::spawn_blocking(move || um.verify_downloaded_package(&asset, &partial_file, hashes.as_ref())).await
+    }
+
+    fn verify_downloaded_package(&self, asset: &VelopackAsset, partial_file: &Path, hashes: Option<&FileHashes>) -> Result<(), Error> {
+        match self.verify_package_checksum(partial_file, asset, hashes) {
+            Ok(()) => {}
+            Err(Error::Cancelled) => return Err(Error::Cancelled),
+            Err(e) => {
+                // a corrupt download must not be resumed next time, so start over from scratch.
+                download::clear_resume_state(partial_file);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the strongest checksum of the asset, or an error if it is weaker than `UpdateOptions.MinimumHashAlgorithm`.
+    fn get_package_checksum(&self, asset: &VelopackAsset) -> Result<(HashAlgorithm, String), Error> {
+        let strongest = asset.get_checksums().into_iter().next();
+        if let Some(minimum) = Self::get_minimum_hash_algorithm(&self.inner.options)? {
+            if strongest
+                .as_ref()
+                .map_or(true, |(algorithm, _)| algorithm.strength() < minimum.strength())
+            {
+                error!(
+                    "Package '{}' does not have a checksum at least as strong as {}.",
+                    asset.FileName,
+                    minimum.name()
+                );
+                return Err(Error::ChecksumTooWeak(asset.FileName.clone(), minimum.name().to_owned()));
+            }
+        }
+        // SHA1 is required and should always be present in the remote feed, so this
+        // can only fail if the feed is malformed.
+        strongest.ok_or_else(|| Error::ChecksumTooWeak(asset.FileName.clone(), HashAlgorithm::Sha1.name().to_owned()))
+    }
+
+    /// Verifies the size and checksum of a downloaded package. If the source hashed the package while downloading it,
+    /// those digests are used, otherwise (eg. the download was resumed) the package is read again to hash it.
+    fn verify_package_checksum(&self, file: &Path, asset: &VelopackAsset, hashes: Option<&FileHashes>) -> Result<(), Error> {
         let file_size = file.metadata()?.len();
         if file_size != asset.Size {
             error!("File size mismatch for file '{:?}': expected {}, got {}", file, asset.Size, file_size);
             return Err(Error::SizeInvalid(file.to_path_buf(), asset.Size, file_size));
         }
 
-        // SHA1 is required and should always be present in the remote feed.
-        // SHA256 is optional and only supported by newer clients, so if it's
-        // missing from the remote asset we fall back to verifying SHA1 only.
-        let (sha1, sha256) = misc::calculate_sha1_sha256(file)?;
-        if !asset.SHA256.is_empty() {
-            if !sha256.eq_ignore_ascii_case(&asset.SHA256) {
-                error!(
-                    "SHA256 checksum mismatch for file '{:?}': expected '{}', got '{}'",
-                    file, asset.SHA256, sha256
-                );
-                return Err(Error::ChecksumInvalid(file.to_path_buf(), asset.SHA256.clone(), sha256));
+        // Older clients only understand SHA1 / SHA256, so newer feeds may also provide stronger checksums
+        // in Hashes. We always verify the strongest checksum which is available.
+        let (algorithm, expected) = self.get_package_checksum(asset)?;
+        let actual = match hashes.and_then(|h| h.get(algorithm)) {
+            Some(digest) => digest.to_owned(),
+            None => {
+                info!("Reading '{:?}' again to calculate its {} checksum.", file, algorithm.name());
+                let hashes = hashes::calculate_file_hashes(file, &[algorithm], Some(&self.inner.cancellation_token))?;
+                hashes.get(algorithm).unwrap_or_default().to_owned()
             }
-        } else if !sha1.eq_ignore_ascii_case(&asset.SHA1) {
-            error!("SHA1 checksum mismatch for file '{:?}': expected '{}', got '{}'", file, asset.SHA1, sha1);
-            return Err(Error::ChecksumInvalid(file.to_path_buf(), asset.SHA1.clone(), sha1));
+        };
+        if !actual.eq_ignore_ascii_case(&expected) {
+            error!(
+                "{} checksum mismatch for file '{:?}': expected '{}', got '{}'",
+                algorithm.name(),
+                file,
+                expected,
+                actual
+            );
+            return Err(Error::ChecksumInvalid(file.to_path_buf(), expected, actual));
         }
         Ok(())
     }
 
+    /// Compares the installed files of the current version against the file manifest of the package it was installed
+    /// from, and reports any files which are missing, modified, or were not installed from the package.
+    /// Returns `Error::NotInstalled` if the package for the current version is not in the packages directory.
+    pub fn verify_installation(&self) -> Result<IntegrityReport, Error> {
+        let package = integrity::find_installed_package(&self.inner.locator)
+            .ok_or_else(|| Error::NotInstalled(format!("The package for version {} was not found", self.get_current_version_as_string())))?;
+        integrity::verify_installation(&self.inner.locator, &package)
+    }
+
+    /// Verifies the installed files (see `verify_installation`), and restores any which are missing or modified from the
+    /// package the current version was installed from. If that package is no longer in the packages directory, it is
+    /// downloaded again from the update source. Returns the report from before the repair.
+    /// This function will acquire a global update lock so may fail if there is already another update operation in progress.
+    /// Files which are in use by a running process (eg. the current executable on Windows) may not be able to be restored.
+    pub fn repair_installation(&self) -> Result<IntegrityReport, Error> {
+        let _mutex = &self.inner.locator.try_get_exclusive_lock()?;
+        self.consume_cancellation(self.repair_installation_impl())
+    }
+
+    fn repair_installation_impl(&self) -> Result<IntegrityReport, Error> {
+        CancellationToken::check(Some(&self.inner.cancellation_token))?;
+        let package = match integrity::find_installed_package(&self.inner.locator) {
+            Some(package) => package,
+            None => {
+                let feed = self.get_release_feed()?;
+                let asset = self.find_installed_asset(&feed)?;
+                let package = self.inner.locator.get_packages_dir().join(&asset.FileName);
+                let partial_file = package.with_extension("partial");
+                fs::create_dir_all(self.inner.locator.get_packages_dir())?;
+                self.download_and_verify_package(&asset, &partial_file, None)?;
+                fs::rename(&partial_file, &package)?;
+                package
+            }
+        };
+        self.repair_installation_from_package(&package)
+    }
+
+    /// Verifies and repairs the installed files without blocking the current thread. See `repair_installation` for details.
+    #[cfg(feature = "async")]
+    pub async fn repair_installation_async(&self) -> Result<IntegrityReport, Error> {
+        let _mutex = &self.inner.locator.try_get_exclusive_lock()?;
+        self.consume_cancellation(self.repair_installation_impl_async().await)
+    }
+
+    #[cfg(feature = "async")]
+    async fn repair_installation_impl_async(&self) -> Result<IntegrityReport, Error> {
+        CancellationToken::check(Some(&self.inner.cancellation_token))?;
+        let package = match integrity::find_installed_package(&self.inner.locator) {
+            Some(package) => package,
+            None => {
+                let feed = self.get_release_feed_async().await?;
+                let asset = self.find_installed_asset(&feed)?;
+                let package = self.inner.locator.get_packages_dir().join(&asset.FileName);
+                let partial_file = package.with_extension("partial");
+                tokio::fs::create_dir_all(self.inner.locator.get_packages_dir()).await?;
+                self.download_and_verify_package_async(&asset, &partial_file, None).await?;
+                tokio::fs::rename(&partial_file, &package).await?;
+                package
+            }
+        };
+        let um = self.clone();
+        sources::spawn_blocking(move || um.repair_installation_from_package(&package)).await
+    }
+
+    /// Finds the full package for the currently installed version in the release feed.
+    fn find_installed_asset(&self, feed: &VelopackAssetFeed) -> Result<VelopackAsset, Error> {
+        let id = self.get_app_id();
+        let version = self.get_current_version();
+        let asset = feed.Assets.iter().find(|a| {
+            a.Type.eq_ignore_ascii_case("Full") && a.PackageId.eq_ignore_ascii_case(&id) && Version::parse(&a.Version).is_ok_and(|v| v == version)
+        });
+        let asset = asset.ok_or_else(|| {
+            Error::NotInstalled(format!(
+                "The package for version {} was not found locally or in the release feed",
+                version
+            ))
+        })?;
+        // fail before downloading anything if the feed does not provide a strong enough checksum.
+        self.get_package_checksum(asset)?;
+        Ok(asset.clone())
+    }
+
+    fn repair_installation_from_package(&self, package: &Path) -> Result<IntegrityReport, Error> {
+        let report = integrity::verify_installation(&self.inner.locator, package)?;
+        if report.is_intact() {
+            info!("All installed files are intact, nothing to repair.");
+            return Ok(report);
+        }
+        info!(
+            "Repairing {} missing and {} modified files from '{:?}'",
+            report.missing_files.len(),
+            report.modified_files.len(),
+            package
+        );
+        let bundle = crate::bundle::load_bundle_from_file(package)?;
+        integrity::repair_installed_files(&self.inner.locator, &bundle, &report)?;
+        Ok(report)
+    }
+
     /// This will exit your app immediately, apply updates, and then relaunch the app.
     /// If you need to save state or clean up, you should do that before calling this method.
     /// The user may be prompted during the update, if the update requires additional frameworks to be installed etc.
     pub fn apply_updates_and_restart<A>(&self, to_apply: A) -> Result<(), Error>
     where
-        A: AsRef<VelopackAsset>,
+        A: UpdateToApply,
     {
         self.wait_exit_then_apply_updates(to_apply, false, true, Vec::<String>::new())?;
         exit(0);
@@ -561,7 +1462,7 @@ impl UpdateManager {
     /// The user may be prompted during the update, if the update requires additional frameworks to be installed etc.
     pub fn apply_updates_and_restart_with_args<A, C, S>(&self, to_apply: A, restart_args: C) -> Result<(), Error>
     where
-        A: AsRef<VelopackAsset>,
+        A: UpdateToApply,
         S: AsRef<OsStr>,
         C: IntoIterator<Item = S>,
     {
@@ -574,7 +1475,7 @@ impl UpdateManager {
     /// The user may be prompted during the update, if the update requires additional frameworks to be installed etc.
     pub fn apply_updates_and_exit<A>(&self, to_apply: A) -> Result<(), Error>
     where
-        A: AsRef<VelopackAsset>,
+        A: UpdateToApply,
     {
         self.wait_exit_then_apply_updates(to_apply, false, false, Vec::<String>::new())?;
         exit(0);
@@ -586,7 +1487,7 @@ impl UpdateManager {
     /// The updater will only wait for 60 seconds before giving up.
     pub fn wait_exit_then_apply_updates<A, C, S>(&self, to_apply: A, silent: bool, restart: bool, restart_args: C) -> Result<(), Error>
     where
-        A: AsRef<VelopackAsset>,
+        A: UpdateToApply,
         S: AsRef<OsStr>,
         C: IntoIterator<Item = S>,
     {
@@ -606,10 +1507,11 @@ impl UpdateManager {
         restart_args: C,
     ) -> Result<(), Error>
     where
-        A: AsRef<VelopackAsset>,
+        A: UpdateToApply,
         S: AsRef<OsStr>,
         C: IntoIterator<Item = S>,
     {
+        let is_downgrade = to_apply.is_downgrade();
         let to_apply = to_apply.as_ref();
         let pkg_path = self.inner.locator.get_packages_dir().join(&to_apply.FileName);
 
@@ -644,6 +1546,19 @@ impl UpdateManager {
             args.push("--norestart".into());
         }
 
+        let update_path = self.inner.locator.get_update_path();
+        if (self.inner.options.AllowVersionDowngrade || is_downgrade) && self.is_below_applied_version(to_apply) {
+            if updater_supports_allow_downgrade(&update_path) {
+                args.push(ALLOW_DOWNGRADE_ARG.into());
+            } else {
+                // such an updater does not refuse downgrades, and would exit with an error if it was passed the argument.
+                info!(
+                    "The updater predates {}, so it is not needed to apply this downgrade.",
+                    ALLOW_DOWNGRADE_ARG
+                );
+            }
+        }
+
         args.push("--root".into());
         #[cfg(target_os = "linux")]
         args.push(self.inner.locator.get_appimage_path().into());
@@ -660,10 +1575,143 @@ impl UpdateManager {
             }
         }
 
-        let update_path = self.inner.locator.get_update_path();
         crate::process::run_process(&update_path, args, update_path.parent(), false, None)?;
         Ok(())
     }
+
+    /// Returns true if the asset is older than the current version, or the highest version which has ever been applied,
+    /// so the updater will refuse to apply it unless it is passed `--allowDowngrade`.
+    fn is_below_applied_version(&self, asset: &VelopackAsset) -> bool {
+        let Ok(version) = Version::parse(&asset.Version) else {
+            return false;
+        };
+        let current_version = self.inner.locator.get_manifest_version();
+        let minimum_version = match self.inner.locator.get_highest_applied_version() {
+            Some(highest) if highest > current_version => highest,
+            _ => current_version,
+        };
+        version < minimum_version
+    }
+}
+
+/// A package download which is in progress, see `UpdateManager::prepare_download`.
+struct PendingDownload {
+    final_target_file: PathBuf,
+    partial_file: PathBuf,
+    to_delete: Vec<PathBuf>,
+}
+
+/// The updater argument which bypasses its downgrade protection.
+const ALLOW_DOWNGRADE_ARG: &str = "--allowDowngrade";
+
+/// Returns true if the updater at the specified path accepts `--allowDowngrade`. Updaters built before it was added
+/// exit with an error when they are passed an unknown argument. The argument name is embedded in the updater by its
+/// command line parser, so it can be checked for without running it.
+fn updater_supports_allow_downgrade(update_exe_path: &Path) -> bool {
+    let needle = ALLOW_DOWNGRADE_ARG.trim_start_matches('-').as_bytes();
+    fs::read(update_exe_path).is_ok_and(|bytes| bytes.windows(needle.len()).any(|w| w == needle))
+}
+
+/// Replaces the updater with the one in the specified package. If the package was built before `--allowDowngrade` was
+/// added (eg. when downgrading to an old release) the current updater is kept instead, so it can still be passed the
+/// argument, and keeps refusing unexpected downgrades.
+#[cfg(any(target_os = "windows", test))]
+fn extract_updater_from_bundle(bundle: &crate::bundle::BundleZip, update_exe_path: &Path) -> Result<(), Error> {
+    let mut temp_name = update_exe_path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".new");
+    let temp_path = update_exe_path.with_file_name(temp_name);
+    bundle.extract_zip_predicate_to_path(|f| f.ends_with("Squirrel.exe"), &temp_path)?;
+
+    if updater_supports_allow_downgrade(update_exe_path) && !updater_supports_allow_downgrade(&temp_path) {
+        info!(
+            "Keeping the current Update.exe, because the one in the package does not support {}.",
+            ALLOW_DOWNGRADE_ARG
+        );
+        let _ = fs::remove_file(&temp_path);
+        return Ok(());
+    }
+
+    if let Err(e) = crate::misc::retry_io(|| fs::rename(&temp_path, update_exe_path)) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+fn find_files_to_delete(pattern: &str, to_delete: &mut Vec<PathBuf>) {
+    match glob::glob(pattern) {
+        Ok(paths) => {
+            for path in paths.into_iter().flatten() {
+                to_delete.push(path);
+            }
+        }
+        Err(e) => {
+            error!("Error while searching for packages to clean: {}", e);
+        }
+    }
+}
+
+/// Finds the cheapest chain of deltas from one version to another. Each delta is an edge from its base version (either
+/// `BaseVersion`, or the release immediately before it) to its own version, costing its size plus the estimated cost of
+/// applying it. If several chains cost the same, the one with the fewest deltas is chosen.
+fn find_delta_path(assets: &[(&VelopackAsset, Version)], from: &Version, to: &Version, patch_cost: u64) -> Option<Vec<VelopackAsset>> {
+    let mut known_versions: Vec<&Version> = assets.iter().map(|(_, version)| version).collect();
+    known_versions.push(from);
+
+    let mut edges: Vec<(Version, &Version, &VelopackAsset)> = Vec::new();
+    for (asset, version) in assets.iter().filter(|(asset, _)| asset.Type.eq_ignore_ascii_case("Delta")) {
+        let base = match asset.BaseVersion.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+            Some(base) => match Version::parse(base) {
+                Ok(base) => base,
+                Err(e) => {
+                    warn!("Ignoring delta {} because its base version '{}' is invalid: {}", asset.FileName, base, e);
+                    continue;
+                }
+            },
+            None => match known_versions.iter().filter(|v| **v < version).max() {
+                Some(previous) => (*previous).clone(),
+                None => continue,
+            },
+        };
+        if base < *version && base >= *from && version <= to {
+            edges.push((base, version, asset));
+        }
+    }
+
+    // every delta moves to a higher version, so visiting them in order of their version finds the cheapest chain to each version.
+    edges.sort_by(|a, b| a.1.cmp(b.1));
+    let mut best: HashMap<&Version, (u64, usize, Option<usize>)> = HashMap::new();
+    best.insert(from, (0, 0, None));
+    for (i, (base, version, asset)) in edges.iter().enumerate() {
+        let Some(&(cost, steps, _)) = best.get(base) else {
+            continue;
+        };
+        let candidate = (cost.saturating_add(asset.Size).saturating_add(patch_cost), steps + 1, Some(i));
+        if best.get(version).map_or(true, |&(c, s, _)| (candidate.0, candidate.1) < (c, s)) {
+            best.insert(version, candidate);
+        }
+    }
+
+    let mut path = Vec::new();
+    let mut current = best.get(to)?.2;
+    while let Some(i) = current {
+        let (base, _, asset) = &edges[i];
+        path.push((*asset).clone());
+        current = best.get(base).and_then(|b| b.2);
+    }
+    path.reverse();
+    if path.is_empty() {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+/// Returns true if the channel is a valid NuGet id (which vpk requires), so it can safely be used in a file name.
+fn is_valid_channel_name(channel: &str) -> bool {
+    channel
+        .split(['.', '-'])
+        .all(|part| !part.is_empty() && part.chars().all(|c| c.is_alphanumeric() || c == '_'))
 }
 
 /// Converts a local manifest and path into a VelopackAsset.
@@ -675,8 +1723,830 @@ pub(crate) fn local_path_to_asset(manifest: &Manifest, path: &Path) -> VelopackA
         FileName: path.file_name().unwrap().to_string_lossy().to_string(),
         SHA1: String::new(),
         SHA256: String::new(),
+        Hashes: HashMap::new(),
         Size: path.metadata().map(|m| m.len()).unwrap_or(0),
         NotesMarkdown: manifest.release_notes.clone(),
         NotesHtml: manifest.release_notes_html.clone(),
+        Arch: Some(manifest.machine_architecture.clone()).filter(|s| !s.is_empty()),
+        OS: Some(manifest.os.clone()).filter(|s| !s.is_empty()),
+        MinOSVersion: Some(manifest.os_min_version.clone()).filter(|s| !s.is_empty()),
+        RolloutPercentage: 0,
+        IsMandatory: false,
+        Retracted: false,
+        BaseVersion: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::FileSource;
+
+    fn create_test_manager(dir: &Path) -> UpdateManager {
+        create_test_manager_with_options(dir, None).unwrap()
+    }
+
+    fn create_test_manager_with_options(dir: &Path, options: Option<UpdateOptions>) -> Result<UpdateManager, Error> {
+        let manifest_path = dir.join("TestApp.nuspec");
+        fs::write(
+            &manifest_path,
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://schemas.microsoft.com/packaging/2010/07/nuspec.xsd">
+  <metadata>
+    <id>TestApp</id>
+    <version>1.0.0</version>
+    <mainExe>test.exe</mainExe>
+    <channel>stable</channel>
+  </metadata>
+</package>"#,
+        )
+        .unwrap();
+        fs::create_dir_all(dir.join("packages")).unwrap();
+        fs::create_dir_all(dir.join("feed")).unwrap();
+        fs::write(dir.join("Update"), "").unwrap();
+
+        let locator = VelopackLocatorConfig {
+            RootAppDir: dir.to_path_buf(),
+            UpdateExePath: dir.join("Update"),
+            PackagesDir: dir.join("packages"),
+            ManifestPath: manifest_path,
+            CurrentBinaryDir: dir.join("current"),
+            IsPortable: false,
+        };
+        UpdateManager::new(FileSource::new(dir.join("feed")), options, Some(locator))
+    }
+
+    fn write_feed(dir: &Path, sequence: u64, expires: Option<&str>) {
+        let feed = VelopackAssetFeed {
+            Assets: Vec::new(),
+            Sequence: sequence,
+            Expires: expires.map(|e| e.to_owned()),
+            MinimumSupportedVersion: None,
+        };
+        fs::write(dir.join("feed").join("releases.stable.json"), serde_json::to_string(&feed).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_feed_sequence_can_not_go_backwards() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let manager = create_test_manager(tmp_dir.path());
+
+        write_feed(tmp_dir.path(), 5, None);
+        assert_eq!(manager.get_release_feed().unwrap().Sequence, 5);
+
+        write_feed(tmp_dir.path(), 6, None);
+        assert_eq!(manager.get_release_feed().unwrap().Sequence, 6);
+
+        write_feed(tmp_dir.path(), 5, None);
+        assert!(matches!(manager.get_release_feed(), Err(Error::FeedRollback(6, 5))));
+        assert!(matches!(manager.check_for_updates(), Err(Error::FeedRollback(6, 5))));
+
+        // a feed without a sequence is also a rollback, once we have seen one with a sequence
+        write_feed(tmp_dir.path(), 0, None);
+        assert!(matches!(manager.get_release_feed(), Err(Error::FeedRollback(6, 0))));
+
+        // the highest sequence is persisted, so a new manager will reject the old feed too
+        let manager = create_test_manager(tmp_dir.path());
+        write_feed(tmp_dir.path(), 5, None);
+        assert!(matches!(manager.get_release_feed(), Err(Error::FeedRollback(6, 5))));
+    }
+
+    #[test]
+    fn test_feed_sequence_is_not_saved_for_invalid_channel() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let options = UpdateOptions {
+            ExplicitChannel: Some("x/../../escaped".to_owned()),
+            ..Default::default()
+        };
+        let manager = create_test_manager_with_options(tmp_dir.path(), Some(options)).unwrap();
+
+        // lay out the feed so the file source finds it through the path in the channel name
+        fs::create_dir_all(tmp_dir.path().join("feed").join("releases.x")).unwrap();
+        write_feed(tmp_dir.path(), 5, None);
+        fs::rename(
+            tmp_dir.path().join("feed").join("releases.stable.json"),
+            tmp_dir.path().join("escaped.json"),
+        )
+        .unwrap();
+        fs::create_dir_all(tmp_dir.path().join("packages").join(".feedSequence.x")).unwrap();
+
+        assert!(manager.get_release_feed().is_err());
+        assert!(!tmp_dir.path().join("escaped").exists());
+
+        assert!(is_valid_channel_name("stable"));
+        assert!(is_valid_channel_name("osx-arm64_beta.2"));
+        assert!(!is_valid_channel_name(""));
+        assert!(!is_valid_channel_name(".."));
+        assert!(!is_valid_channel_name("a/b"));
+        assert!(!is_valid_channel_name("a\\b"));
+        assert!(!is_valid_channel_name("a..b"));
+    }
+
+    #[test]
+    fn test_expired_feed_is_rejected() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let manager = create_test_manager(tmp_dir.path());
+
+        write_feed(tmp_dir.path(), 0, None);
+        manager.get_release_feed().unwrap();
+
+        write_feed(tmp_dir.path(), 0, Some("2999-01-01T00:00:00Z"));
+        manager.get_release_feed().unwrap();
+
+        write_feed(tmp_dir.path(), 0, Some("2000-01-01T00:00:00+02:00"));
+        assert!(matches!(manager.get_release_feed(), Err(Error::FeedExpired(_))));
+
+        write_feed(tmp_dir.path(), 0, Some("not a date"));
+        assert!(manager.get_release_feed().is_err());
+    }
+
+    #[test]
+    fn test_merged_feed_keeps_highest_sequence_and_latest_expiry() {
+        let mut feed = VelopackAssetFeed::default();
+        feed.merge(VelopackAssetFeed {
+            Assets: Vec::new(),
+            Sequence: 3,
+            Expires: Some("2030-01-01T00:00:00Z".to_owned()),
+            MinimumSupportedVersion: None,
+        });
+        feed.merge(VelopackAssetFeed {
+            Assets: Vec::new(),
+            Sequence: 2,
+            Expires: Some("2031-01-01T00:00:00Z".to_owned()),
+            MinimumSupportedVersion: Some("1.2.0".to_owned()),
+        });
+        feed.merge(VelopackAssetFeed {
+            Assets: Vec::new(),
+            Sequence: 1,
+            Expires: None,
+            MinimumSupportedVersion: Some("1.1.0".to_owned()),
+        });
+        assert_eq!(feed.Sequence, 3);
+        assert_eq!(feed.Expires.as_deref(), Some("2031-01-01T00:00:00Z"));
+        assert_eq!(feed.MinimumSupportedVersion.as_deref(), Some("1.2.0"));
+    }
+
+    fn create_hello_asset(dir: &Path) -> (PathBuf, VelopackAsset) {
+        let path = dir.join("TestApp-2.0.0-full.nupkg");
+        fs::write(&path, b"hello").unwrap();
+        let asset = VelopackAsset {
+            PackageId: "TestApp".to_owned(),
+            Version: "2.0.0".to_owned(),
+            Type: "Full".to_owned(),
+            FileName: "TestApp-2.0.0-full.nupkg".to_owned(),
+            SHA1: "AAF4C61DDCC5E8A2DABEDE0F3B482CD9AEA9434D".to_owned(),
+            Size: 5,
+            ..Default::default()
+        };
+        (path, asset)
+    }
+
+    #[test]
+    fn test_verify_package_checksum_uses_strongest_hash() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let manager = create_test_manager(tmp_dir.path());
+        let (path, mut asset) = create_hello_asset(tmp_dir.path());
+        manager.verify_package_checksum(&path, &asset, None).unwrap();
+
+        asset.SHA256 = "2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824".to_owned();
+        asset.Hashes = HashMap::from([
+            ("md5".to_owned(), "5D41402ABC4B2A76B9719D911017C592".to_owned()),
+            (
+                "blake3".to_owned(),
+                "ea8f163db38682925e4491c5e58d4bb3506ef8c14eb78a86e908c5624a67200f".to_owned(),
+            ),
+        ]);
+        assert_eq!(asset.get_checksums()[0].0, HashAlgorithm::Blake3);
+        assert_eq!(asset.get_download_hash_algorithms(), vec![HashAlgorithm::Blake3]);
+        manager.verify_package_checksum(&path, &asset, None).unwrap();
+
+        // the SHA1 and SHA256 checksums still match, but the stronger checksum does not
+        asset.Hashes = HashMap::from([("sha512".to_owned(), "0000".to_owned())]);
+        assert!(matches!(
+            manager.verify_package_checksum(&path, &asset, None),
+            Err(Error::ChecksumInvalid(..))
+        ));
+    }
+
+    #[test]
+    fn test_verify_package_checksum_uses_digests_from_download() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let manager = create_test_manager(tmp_dir.path());
+        let (path, asset) = create_hello_asset(tmp_dir.path());
+
+        // the digests computed while downloading are trusted, so the file is not read again
+        let mut hasher = hashes::MultiHasher::new(&HashAlgorithm::ALL);
+        hasher.update(b"world");
+        let digests = hasher.finalize();
+        assert!(matches!(
+            manager.verify_package_checksum(&path, &asset, Some(&digests)),
+            Err(Error::ChecksumInvalid(..))
+        ));
+
+        let mut hasher = hashes::MultiHasher::new(&HashAlgorithm::ALL);
+        hasher.update(b"hello");
+        manager.verify_package_checksum(&path, &asset, Some(&hasher.finalize())).unwrap();
+
+        // if the required digest was not computed, the file is hashed instead
+        let digests = hashes::MultiHasher::new(&[HashAlgorithm::Blake3]).finalize();
+        manager.verify_package_checksum(&path, &asset, Some(&digests)).unwrap();
+    }
+
+    #[test]
+    fn test_minimum_hash_algorithm_rejects_weak_checksums() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let options = UpdateOptions {
+            MinimumHashAlgorithm: Some("SHA-256".to_owned()),
+            ..Default::default()
+        };
+        let manager = create_test_manager_with_options(tmp_dir.path(), Some(options)).unwrap();
+        let (path, mut asset) = create_hello_asset(tmp_dir.path());
+
+        assert!(matches!(
+            manager.verify_package_checksum(&path, &asset, None),
+            Err(Error::ChecksumTooWeak(..))
+        ));
+        let update = UpdateInfo::new_full(asset.clone(), false, String::new());
+        assert!(matches!(manager.download_updates(&update, None), Err(Error::ChecksumTooWeak(..))));
+
+        asset.Hashes = HashMap::from([(
+            "sha512".to_owned(),
+            "9B71D224BD62F3785D96D46AD3EA3D73319BFBC2890CAADAE2DFF72519673CA72323C3D99BA5C11D7C7ACC6E14B8C5DA0C4663475C2E5C3ADEF46F73BCDEC043"
+                .to_owned(),
+        )]);
+        manager.verify_package_checksum(&path, &asset, None).unwrap();
+
+        let options = UpdateOptions {
+            MinimumHashAlgorithm: Some("md5".to_owned()),
+            ..Default::default()
+        };
+        assert!(create_test_manager_with_options(tmp_dir.path(), Some(options)).is_err());
+    }
+
+    #[test]
+    fn test_cancel_before_download_is_not_lost() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let manager = create_test_manager(tmp_dir.path());
+        let (_, asset) = create_hello_asset(&tmp_dir.path().join("feed"));
+        let update = UpdateInfo::new_full(asset, false, String::new());
+        let package = tmp_dir.path().join("packages").join("TestApp-2.0.0-full.nupkg");
+
+        manager.cancel_downloads();
+        assert!(matches!(manager.download_updates(&update, None), Err(Error::Cancelled)));
+        assert!(!package.exists());
+
+        // the cancellation only applies to one operation
+        manager.download_updates(&update, None).unwrap();
+        assert!(package.exists());
+    }
+
+    #[test]
+    fn test_repair_installation_downloads_missing_package() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let manager = create_test_manager(tmp_dir.path());
+        assert!(matches!(manager.verify_installation(), Err(Error::NotInstalled(..))));
+        write_feed(tmp_dir.path(), 0, None);
+        assert!(matches!(manager.repair_installation(), Err(Error::NotInstalled(..))));
+
+        let nuspec = b"<package><metadata><id>TestApp</id><version>1.0.0</version></metadata></package>";
+        let package = crate::bundle::create_test_bundle(&[("TestApp.nuspec", nuspec), ("lib/app/test.exe", b"exe")]);
+        let feed_dir = tmp_dir.path().join("feed");
+        fs::write(feed_dir.join("TestApp-1.0.0-full.nupkg"), &package).unwrap();
+        let hashes = hashes::calculate_file_hashes(feed_dir.join("TestApp-1.0.0-full.nupkg"), &[HashAlgorithm::Sha1], None).unwrap();
+        let feed = VelopackAssetFeed {
+            Assets: vec![VelopackAsset {
+                PackageId: "TestApp".to_owned(),
+                Version: "1.0.0".to_owned(),
+                Type: "Full".to_owned(),
+                FileName: "TestApp-1.0.0-full.nupkg".to_owned(),
+                SHA1: hashes.get(HashAlgorithm::Sha1).unwrap().to_owned(),
+                Size: package.len() as u64,
+                ..Default::default()
+            }],
+            Sequence: 1,
+            Expires: None,
+            MinimumSupportedVersion: None,
+        };
+        fs::write(feed_dir.join("releases.stable.json"), serde_json::to_string(&feed).unwrap()).unwrap();
+
+        let report = manager.repair_installation().unwrap();
+        assert!(tmp_dir.path().join("packages").join("TestApp-1.0.0-full.nupkg").exists());
+        #[cfg(not(target_os = "linux"))]
+        {
+            assert_eq!(report.missing_files, vec!["test.exe"]);
+            assert_eq!(fs::read(tmp_dir.path().join("current").join("test.exe")).unwrap(), b"exe");
+        }
+        #[cfg(target_os = "linux")]
+        assert!(report.is_intact());
+        assert!(manager.verify_installation().unwrap().is_intact());
+    }
+
+    fn create_platform_asset(version: &str, arch: &str, min_os_version: Option<&str>) -> VelopackAsset {
+        VelopackAsset {
+            PackageId: "TestApp".to_owned(),
+            Version: version.to_owned(),
+            Type: "Full".to_owned(),
+            FileName: format!("TestApp-{}-{}-full.nupkg", version, arch),
+            Arch: Some(arch.to_owned()),
+            OS: Some("win".to_owned()),
+            MinOSVersion: min_os_version.map(|v| v.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_check_for_updates_skips_incompatible_releases() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let manager = create_test_manager(tmp_dir.path());
+        let platform = PlatformInfo {
+            arch: Some(crate::platform::RuntimeArch::X64),
+            os: "win".to_owned(),
+            os_version: Some((10, 0, 19045)),
+        };
+        let check = |assets: Vec<VelopackAsset>| {
+            let feed = VelopackAssetFeed {
+                Assets: assets,
+                Sequence: 0,
+                Expires: None,
+                MinimumSupportedVersion: None,
+            };
+            manager.check_for_updates_in_feed_for_platform(feed, &platform).unwrap()
+        };
+
+        let assets = vec![
+            create_platform_asset("2.0.0", "x64", None),
+            create_platform_asset("2.0.0", "arm64", None),
+            create_platform_asset("3.0.0", "arm64", None),
+            create_platform_asset("3.0.0", "x64", Some("11")),
+        ];
+        match check(assets.clone()) {
+            UpdateCheck::UpdateAvailable(update) => assert_eq!(update.TargetFullRelease.FileName, "TestApp-2.0.0-x64-full.nupkg"),
+            _ => panic!("expected a compatible update"),
+        }
+
+        match check(assets[2..].to_vec()) {
+            UpdateCheck::UpdateIncompatible(asset) => assert_eq!(asset.Version, "3.0.0"),
+            _ => panic!("expected an incompatible update"),
+        }
+
+        let mut linux = create_platform_asset("2.0.0", "x64", None);
+        linux.OS = Some("linux".to_owned());
+        assert!(matches!(check(vec![linux]), UpdateCheck::UpdateIncompatible(_)));
+        assert!(matches!(
+            check(vec![create_platform_asset("0.9.0", "arm64", None)]),
+            UpdateCheck::NoUpdateAvailable
+        ));
+    }
+
+    #[test]
+    fn test_check_for_updates_ignores_releases_below_highest_applied_version() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let platform = PlatformInfo::current();
+        let feed = || VelopackAssetFeed {
+            Assets: vec![VelopackAsset {
+                PackageId: "TestApp".to_owned(),
+                Version: "1.5.0".to_owned(),
+                Type: "Full".to_owned(),
+                FileName: "TestApp-1.5.0-full.nupkg".to_owned(),
+                ..Default::default()
+            }],
+            Sequence: 0,
+            Expires: None,
+            MinimumSupportedVersion: None,
+        };
+
+        let manager = create_test_manager(tmp_dir.path());
+        assert!(matches!(
+            manager.check_for_updates_in_feed_for_platform(feed(), &platform).unwrap(),
+            UpdateCheck::UpdateAvailable(_)
+        ));
+
+        // the app manifest says 1.0.0, but 2.0.0 has already been applied, so 1.5.0 would be a rollback
+        fs::write(tmp_dir.path().join("packages").join(".appliedVersion"), "2.0.0").unwrap();
+        assert!(matches!(
+            manager.check_for_updates_in_feed_for_platform(feed(), &platform).unwrap(),
+            UpdateCheck::NoUpdateAvailable
+        ));
+
+        let options = UpdateOptions {
+            AllowVersionDowngrade: true,
+            ..Default::default()
+        };
+        let manager = create_test_manager_with_options(tmp_dir.path(), Some(options)).unwrap();
+        assert!(matches!(
+            manager.check_for_updates_in_feed_for_platform(feed(), &platform).unwrap(),
+            UpdateCheck::UpdateAvailable(_)
+        ));
+    }
+
+    /// Replaces the test updater with a script which records the arguments it was started with. An updater built
+    /// before `--allowDowngrade` was added does not contain the argument name, and exits with an error if passed it.
+    #[cfg(unix)]
+    fn write_test_updater(dir: &Path, supports_allow_downgrade: bool) {
+        use std::os::unix::fs::PermissionsExt;
+        let args_file = dir.join("updater-args.txt");
+        let _ = fs::remove_file(&args_file);
+        let args_file = args_file.display();
+        // the argument name is split up, so that the script does not appear to accept it.
+        let header = if supports_allow_downgrade {
+            "# accepts --allowDowngrade".to_owned()
+        } else {
+            format!(
+                "case \" $* \" in *\" --allow\"[D]\"owngrade \"*) echo rejected > '{}'; exit 2;; esac",
+                args_file
+            )
+        };
+        let script = format!(
+            "#!/bin/sh\n{}\nprintf '%s\\n' \"$@\" > \"$0.tmp\"\nmv \"$0.tmp\" '{}'\n",
+            header, args_file
+        );
+        let update_path = dir.join("Update");
+        fs::write(&update_path, script).unwrap();
+        fs::set_permissions(&update_path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[cfg(unix)]
+    fn read_test_updater_args(dir: &Path) -> Vec<String> {
+        let args_file = dir.join("updater-args.txt");
+        for _ in 0..100 {
+            if let Ok(args) = fs::read_to_string(&args_file) {
+                return args.lines().map(|l| l.to_owned()).collect();
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        panic!("the test updater was not started");
+    }
+
+    fn create_local_test_asset(dir: &Path, version: &str) -> VelopackAsset {
+        let asset = VelopackAsset {
+            PackageId: "TestApp".to_owned(),
+            Version: version.to_owned(),
+            Type: "Full".to_owned(),
+            FileName: format!("TestApp-{}-full.nupkg", version),
+            ..Default::default()
+        };
+        fs::write(dir.join("packages").join(&asset.FileName), "").unwrap();
+        asset
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_apply_downgrade_with_updater_which_predates_allow_downgrade() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let options = UpdateOptions {
+            AllowVersionDowngrade: true,
+            ..Default::default()
+        };
+        let manager = create_test_manager_with_options(tmp_dir.path(), Some(options)).unwrap();
+        let apply = |version: &str| {
+            let asset = create_local_test_asset(tmp_dir.path(), version);
+            manager
+                .unsafe_apply_updates(&asset, true, ApplyWaitMode::NoWait, false, Vec::<String>::new())
+                .unwrap();
+            read_test_updater_args(tmp_dir.path())
+        };
+
+        // an old updater does not refuse downgrades, so it is not passed an argument which it would reject
+        write_test_updater(tmp_dir.path(), false);
+        let args = apply("0.9.0");
+        assert_eq!(args[0], "apply");
+        assert!(!args.contains(&"--allowDowngrade".to_owned()));
+
+        // a current updater is passed it, but only if the package is older than the current or highest applied version
+        write_test_updater(tmp_dir.path(), true);
+        assert!(apply("0.9.0").contains(&"--allowDowngrade".to_owned()));
+        write_test_updater(tmp_dir.path(), true);
+        assert!(!apply("1.5.0").contains(&"--allowDowngrade".to_owned()));
+        fs::write(tmp_dir.path().join("packages").join(".appliedVersion"), "2.0.0").unwrap();
+        write_test_updater(tmp_dir.path(), true);
+        assert!(apply("1.5.0").contains(&"--allowDowngrade".to_owned()));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_apply_retraction_rollback() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let manager = create_test_manager(tmp_dir.path());
+        let mut retracted = create_local_test_asset(tmp_dir.path(), "1.0.0");
+        retracted.Retracted = true;
+        let feed = VelopackAssetFeed {
+            Assets: vec![create_local_test_asset(tmp_dir.path(), "0.9.0"), retracted],
+            Sequence: 0,
+            Expires: None,
+            MinimumSupportedVersion: None,
+        };
+        let update = match manager.check_for_updates_in_feed_for_platform(feed, &PlatformInfo::current()).unwrap() {
+            UpdateCheck::UpdateAvailable(update) => update,
+            _ => panic!("expected the retracted version to be rolled back"),
+        };
+        let apply_update = || {
+            manager
+                .unsafe_apply_updates(&*update, true, ApplyWaitMode::NoWait, false, Vec::<String>::new())
+                .unwrap();
+            read_test_updater_args(tmp_dir.path())
+        };
+        let apply_asset = || {
+            manager
+                .unsafe_apply_updates(&update.TargetFullRelease, true, ApplyWaitMode::NoWait, false, Vec::<String>::new())
+                .unwrap();
+            read_test_updater_args(tmp_dir.path())
+        };
+
+        // an updater which predates the argument does not refuse the rollback, so it must not be passed it
+        write_test_updater(tmp_dir.path(), false);
+        let args = apply_update();
+        assert_eq!(args[0], "apply");
+        assert!(!args.contains(&"--allowDowngrade".to_owned()));
+
+        // a current updater is passed it, but only when applying the UpdateInfo which allows the rollback
+        write_test_updater(tmp_dir.path(), true);
+        assert!(apply_update().contains(&"--allowDowngrade".to_owned()));
+        write_test_updater(tmp_dir.path(), true);
+        assert!(!apply_asset().contains(&"--allowDowngrade".to_owned()));
+    }
+
+    #[test]
+    fn test_updater_is_not_replaced_by_one_without_allow_downgrade() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let update_path = tmp_dir.path().join("Update.exe");
+        let extract = |updater: &[u8]| {
+            let package = crate::bundle::create_test_bundle(&[("TestApp.nuspec", b"<package />"), ("lib/app/Squirrel.exe", updater)]);
+            extract_updater_from_bundle(&crate::bundle::load_bundle_from_memory(&package).unwrap(), &update_path).unwrap();
+        };
+
+        // the package is older than the current updater, so the current updater is kept
+        fs::write(&update_path, "current updater, accepts --allowDowngrade").unwrap();
+        extract(b"old updater");
+        assert_eq!(fs::read_to_string(&update_path).unwrap(), "current updater, accepts --allowDowngrade");
+
+        extract(b"new updater, accepts --allowDowngrade");
+        assert_eq!(fs::read_to_string(&update_path).unwrap(), "new updater, accepts --allowDowngrade");
+
+        // an old updater is always replaced
+        fs::write(&update_path, "old updater").unwrap();
+        extract(b"other old updater");
+        assert_eq!(fs::read_to_string(&update_path).unwrap(), "other old updater");
+        assert_eq!(fs::read_dir(tmp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_rollout_bucket_is_stable_and_distributed() {
+        assert_eq!(get_rollout_bucket("user-a"), get_rollout_bucket("user-a"));
+        let mut buckets = [0u32; 100];
+        for i in 0..10000 {
+            buckets[get_rollout_bucket(&format!("user-{}", i)) as usize] += 1;
+        }
+        assert!(buckets.iter().all(|&count| count > 50 && count < 150));
+    }
+
+    #[test]
+    fn test_check_for_updates_respects_rollout_percentage() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let manager = create_test_manager(tmp_dir.path());
+        let platform = PlatformInfo::current();
+        let bucket = get_rollout_bucket(&manager.inner.locator.get_staged_user_id());
+        let check = |rollout_percentage: u32| {
+            let feed = VelopackAssetFeed {
+                Assets: vec![VelopackAsset {
+                    PackageId: "TestApp".to_owned(),
+                    Version: "2.0.0".to_owned(),
+                    Type: "Full".to_owned(),
+                    FileName: "TestApp-2.0.0-full.nupkg".to_owned(),
+                    RolloutPercentage: rollout_percentage,
+                    ..Default::default()
+                }],
+                Sequence: 0,
+                Expires: None,
+                MinimumSupportedVersion: None,
+            };
+            manager.check_for_updates_in_feed_for_platform(feed, &platform).unwrap()
+        };
+
+        assert!(matches!(check(0), UpdateCheck::UpdateAvailable(_)));
+        assert!(matches!(check(100), UpdateCheck::UpdateAvailable(_)));
+        assert!(matches!(check(bucket + 1), UpdateCheck::UpdateAvailable(_)));
+        if bucket > 0 {
+            assert!(matches!(check(bucket), UpdateCheck::NoUpdateAvailable));
+        }
+    }
+
+    #[test]
+    fn test_check_for_updates_reports_required_updates() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let manager = create_test_manager(tmp_dir.path());
+        let platform = PlatformInfo::current();
+        let asset = |version: &str, is_mandatory: bool| VelopackAsset {
+            PackageId: "TestApp".to_owned(),
+            Version: version.to_owned(),
+            Type: "Full".to_owned(),
+            FileName: format!("TestApp-{}-full.nupkg", version),
+            IsMandatory: is_mandatory,
+            ..Default::default()
+        };
+        let check = |assets: Vec<VelopackAsset>, minimum_supported_version: Option<&str>| {
+            let feed = VelopackAssetFeed {
+                Assets: assets,
+                Sequence: 0,
+                Expires: None,
+                MinimumSupportedVersion: minimum_supported_version.map(|v| v.to_owned()),
+            };
+            manager.check_for_updates_in_feed_for_platform(feed, &platform).unwrap()
+        };
+
+        let is_required = |check: UpdateCheck| match check {
+            UpdateCheck::UpdateAvailable(update) => update.IsRequired,
+            _ => panic!("expected an update"),
+        };
+        assert!(!is_required(check(vec![asset("2.0.0", false)], None)));
+        assert!(!is_required(check(vec![asset("2.0.0", false)], Some("0.9.0"))));
+        assert!(matches!(check(vec![asset("0.9.0", true)], None), UpdateCheck::NoUpdateAvailable));
+
+        match check(vec![asset("1.5.0", true), asset("2.0.0", false)], None) {
+            UpdateCheck::UpdateAvailable(update) => {
+                assert_eq!(update.TargetFullRelease.Version, "2.0.0");
+                assert!(update.IsRequired);
+            }
+            _ => panic!("expected a required update"),
+        }
+
+        assert!(is_required(check(vec![asset("2.0.0", false)], Some("1.1.0"))));
+
+        // there is nothing to update to, so the app can only be told there is no update
+        assert!(matches!(
+            check(vec![asset("1.0.0", false)], Some("2.0.0")),
+            UpdateCheck::NoUpdateAvailable
+        ));
+    }
+
+    #[test]
+    fn test_check_for_updates_replaces_retracted_version() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let manager = create_test_manager(tmp_dir.path());
+        let platform = PlatformInfo::current();
+        let asset = |version: &str, retracted: bool| VelopackAsset {
+            PackageId: "TestApp".to_owned(),
+            Version: version.to_owned(),
+            Type: "Full".to_owned(),
+            FileName: format!("TestApp-{}-full.nupkg", version),
+            Retracted: retracted,
+            ..Default::default()
+        };
+        let check = |assets: Vec<VelopackAsset>| {
+            let feed = VelopackAssetFeed {
+                Assets: assets,
+                Sequence: 0,
+                Expires: None,
+                MinimumSupportedVersion: None,
+            };
+            manager.check_for_updates_in_feed_for_platform(feed, &platform).unwrap()
+        };
+
+        // retracted releases are never offered
+        assert!(matches!(
+            check(vec![asset("0.9.0", false), asset("2.0.0", true)]),
+            UpdateCheck::NoUpdateAvailable
+        ));
+
+        // the current version (1.0.0) is retracted, so the newest release which is not is offered, even though downgrades are not allowed
+        match check(vec![
+            asset("0.8.0", false),
+            asset("0.9.0", false),
+            asset("1.0.0", true),
+            asset("2.0.0", true),
+        ]) {
+            UpdateCheck::UpdateAvailable(update) => {
+                assert_eq!(update.TargetFullRelease.Version, "0.9.0");
+                assert!(update.IsDowngrade);
+                // the flag is carried by the UpdateInfo, so only applying the UpdateInfo bypasses the downgrade protection
+                assert!(update.is_downgrade());
+                assert!(!update.TargetFullRelease.is_downgrade());
+            }
+            _ => panic!("expected the retracted version to be replaced"),
+        }
+
+        // a retracted version which has already been applied does not block newer releases below it
+        fs::write(tmp_dir.path().join("packages").join(".appliedVersion"), "2.0.0").unwrap();
+        match check(vec![asset("1.5.0", false), asset("2.0.0", true)]) {
+            UpdateCheck::UpdateAvailable(update) => {
+                assert_eq!(update.TargetFullRelease.Version, "1.5.0");
+                assert!(update.IsDowngrade);
+            }
+            _ => panic!("expected the release below the retracted version to be offered"),
+        }
+        match check(vec![asset("1.5.0", false), asset("2.0.0", false)]) {
+            UpdateCheck::UpdateAvailable(update) => {
+                assert_eq!(update.TargetFullRelease.Version, "2.0.0");
+                assert!(!update.IsDowngrade);
+            }
+            _ => panic!("expected the newest release to be offered"),
+        }
+    }
+
+    #[test]
+    fn test_delta_strategy_compares_delta_and_full_cost() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let nuspec = b"<package><metadata><id>TestApp</id><version>1.0.0</version></metadata></package>";
+        let package = crate::bundle::create_test_bundle(&[("TestApp.nuspec", nuspec)]);
+        let manager = create_test_manager(tmp_dir.path());
+        fs::write(tmp_dir.path().join("packages").join("TestApp-1.0.0-full.nupkg"), package).unwrap();
+
+        let asset = |version: &str, asset_type: &str, size: u64| VelopackAsset {
+            PackageId: "TestApp".to_owned(),
+            Version: version.to_owned(),
+            Type: asset_type.to_owned(),
+            FileName: format!("TestApp-{}-{}.nupkg", version, asset_type.to_lowercase()),
+            Size: size,
+            ..Default::default()
+        };
+        let strategy = |delta_sizes: &[u64], full_size: u64| {
+            let full = asset("2.0.0", "Full", full_size);
+            let mut feed = vec![full.clone()];
+            for (i, size) in delta_sizes.iter().enumerate() {
+                let version = if i + 1 == delta_sizes.len() {
+                    "2.0.0".to_owned()
+                } else {
+                    format!("1.{}.0", i + 1)
+                };
+                feed.push(asset(&version, "Delta", *size));
+            }
+            manager.create_delta_update_strategy(&feed, (&full, Version::parse("2.0.0").unwrap()))
+        };
+
+        // 2 small deltas are cheaper than the full package
+        let update = strategy(&[100_000, 100_000], 10_000_000);
+        assert_eq!(update.DeltasToTarget.len(), 2);
+        assert_eq!(update.DownloadSize, 200_000);
+        assert!(update.StrategyReason.contains("delta updates will be used"), "{}", update.StrategyReason);
+
+        // 9 large deltas are more expensive than the full package, once the cost of patching is included
+        let update = strategy(&[1_000_000; 9], 10_000_000);
+        assert!(update.DeltasToTarget.is_empty());
+        assert!(update.BaseRelease.is_none());
+        assert_eq!(update.DownloadSize, 10_000_000);
+        assert!(update.StrategyReason.contains("full update will be used"), "{}", update.StrategyReason);
+
+        // more deltas than MaximumDeltasBeforeFallback (10 by default) always use the full package
+        let update = strategy(&[1; 11], 10_000_000);
+        assert!(update.DeltasToTarget.is_empty());
+
+        // without sizes the deltas can not be compared, so they are used
+        let update = strategy(&[0, 0], 0);
+        assert_eq!(update.DeltasToTarget.len(), 2);
+    }
+
+    #[test]
+    fn test_find_delta_path_uses_cumulative_deltas() {
+        let delta = |version: &str, base: Option<&str>, size: u64| VelopackAsset {
+            PackageId: "TestApp".to_owned(),
+            Version: version.to_owned(),
+            Type: "Delta".to_owned(),
+            FileName: format!("TestApp-{}-from-{}-delta.nupkg", version, base.unwrap_or("previous")),
+            Size: size,
+            BaseVersion: base.map(|b| b.to_owned()),
+            ..Default::default()
+        };
+        let find = |assets: &[VelopackAsset], from: &str| {
+            let assets: Vec<(&VelopackAsset, Version)> = assets.iter().map(|a| (a, Version::parse(&a.Version).unwrap())).collect();
+            find_delta_path(&assets, &Version::parse(from).unwrap(), &Version::parse("2.0.0").unwrap(), 100)
+                .map(|path| path.iter().map(|d| d.FileName.clone()).collect::<Vec<_>>())
+        };
+
+        let assets = vec![
+            delta("1.1.0", None, 1000),
+            delta("1.5.0", None, 1000),
+            delta("1.5.0", Some("1.0.0"), 1500),
+            delta("2.0.0", None, 1000),
+        ];
+
+        // the cumulative delta skips 1.1.0, so it is cheaper than applying both 1.1.0 and 1.5.0
+        assert_eq!(
+            find(&assets, "1.0.0").unwrap(),
+            vec!["TestApp-1.5.0-from-1.0.0-delta.nupkg", "TestApp-2.0.0-from-previous-delta.nupkg"]
+        );
+        assert_eq!(
+            find(&assets, "1.1.0").unwrap(),
+            vec!["TestApp-1.5.0-from-previous-delta.nupkg", "TestApp-2.0.0-from-previous-delta.nupkg"]
+        );
+
+        // without sizes, the chain with the fewest deltas is chosen
+        let unsized_assets: Vec<VelopackAsset> = assets.iter().map(|a| VelopackAsset { Size: 0, ..a.clone() }).collect();
+        assert_eq!(find(&unsized_assets, "1.0.0").unwrap().len(), 2);
+
+        // a delta with an explicit base version can only be applied to that version
+        assert!(find(&[delta("2.0.0", Some("1.5.0"), 1000)], "1.0.0").is_none());
+        assert!(find(&assets[..3], "1.0.0").is_none());
+    }
+
+    #[test]
+    fn test_offline_feed_fallback_is_opt_in() {
+        let max_age = |seconds: i64| {
+            UpdateManager::get_max_offline_age(&UpdateOptions {
+                MaximumCachedFeedAgeSeconds: seconds,
+                ..Default::default()
+            })
+        };
+        assert_eq!(max_age(0), None);
+        assert_eq!(max_age(-1), None);
+        assert_eq!(max_age(604800), Some(Duration::from_secs(604800)));
     }
 }
@@ -1,8 +1,6 @@
-use crate::Error;
+use crate::hashes::{self, HashAlgorithm};
+use crate::{CancellationToken, Error};
 use rand::distr::{Alphanumeric, SampleString};
-use sha2::Digest;
-use std::fs::File;
-use std::io::{BufReader, Read};
 use std::path::Path;
 use std::thread;
 use std::time::Duration;
@@ -37,7 +35,7 @@ pub fn random_string(len: usize) -> String {
     Alphanumeric.sample_string(&mut rand::rng(), len)
 }
 
-fn to_hex(bytes: &[u8]) -> String {
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
     bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut s, b| {
         use std::fmt::Write;
         write!(s, "{:02X}", b).unwrap();
@@ -46,26 +44,14 @@ fn to_hex(bytes: &[u8]) -> String {
 }
 
 pub fn calculate_sha1_sha256<P: AsRef<Path>>(file: P) -> Result<(String, String), Error> {
-    let file = File::open(file)?;
-    let mut reader = BufReader::new(file);
-
-    let mut sha256 = sha2::Sha256::new();
-    let mut sha1 = sha1::Sha1::new();
-
-    let mut buffer = [0u8; 64 * 1024];
-    loop {
-        let bytes_read = reader.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
-        }
-
-        sha256.update(&buffer[..bytes_read]);
-        sha1.update(&buffer[..bytes_read]);
-    }
-
-    let sha256_hash = to_hex(&sha256.finalize());
-    let sha1_hash = to_hex(&sha1.finalize());
+    calculate_sha1_sha256_cancellable(file, None)
+}
 
+/// Calculates the SHA1 and SHA256 hashes of a file, stopping with `Error::Cancelled` if the token (if any) is cancelled.
+pub fn calculate_sha1_sha256_cancellable<P: AsRef<Path>>(file: P, cancel: Option<&CancellationToken>) -> Result<(String, String), Error> {
+    let hashes = hashes::calculate_file_hashes(file, &[HashAlgorithm::Sha1, HashAlgorithm::Sha256], cancel)?;
+    let sha1_hash = hashes.get(HashAlgorithm::Sha1).unwrap_or_default().to_owned();
+    let sha256_hash = hashes.get(HashAlgorithm::Sha256).unwrap_or_default().to_owned();
     Ok((sha1_hash, sha256_hash))
 }
 
@@ -116,6 +102,7 @@ pub fn is_sub_path<P1: AsRef<Path>, P2: AsRef<Path>>(path: P1, parent: P2) -> bo
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs::File;
     use std::io::Write;
 
     #[test]
@@ -144,4 +131,16 @@ mod tests {
         assert!(sha1.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_lowercase()));
         assert!(sha256.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_lowercase()));
     }
+
+    #[test]
+    fn test_calculate_sha1_sha256_can_be_cancelled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        std::fs::write(&path, b"hello world").unwrap();
+
+        let cancel = CancellationToken::new();
+        assert!(calculate_sha1_sha256_cancellable(&path, Some(&cancel)).is_ok());
+        cancel.cancel();
+        assert!(matches!(calculate_sha1_sha256_cancellable(&path, Some(&cancel)), Err(Error::Cancelled)));
+    }
 }
@@ -0,0 +1,169 @@
+use crate::constants;
+
+/// The CPU architectures which Velopack packages can target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeArch {
+    /// 32-bit x86
+    X86,
+    /// 64-bit x86
+    X64,
+    /// 64-bit ARM
+    Arm64,
+}
+
+impl RuntimeArch {
+    /// Parses an architecture name (eg. 'x64', 'x86_64' or 'aarch64'). Returns None if it is not recognised.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "x86" | "i386" | "i686" => Some(RuntimeArch::X86),
+            "x64" | "x86_64" | "amd64" => Some(RuntimeArch::X64),
+            "arm64" | "aarch64" => Some(RuntimeArch::Arm64),
+            _ => None,
+        }
+    }
+}
+
+/// Describes the machine that updates will be installed on, to decide which releases in a feed it can run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlatformInfo {
+    /// The native CPU architecture of the machine, if it could be detected.
+    pub arch: Option<RuntimeArch>,
+    /// The short name of the operating system, as used in package manifests ('win', 'osx' or 'linux').
+    pub os: String,
+    /// The version of the operating system, if it could be detected.
+    pub os_version: Option<(u64, u64, u64)>,
+}
+
+impl PlatformInfo {
+    /// Detects the architecture and operating system of the current machine.
+    pub fn current() -> Self {
+        let info = os_info::get();
+        let os_version = match info.version() {
+            os_info::Version::Semantic(major, minor, patch) => Some((*major, *minor, *patch)),
+            _ => None,
+        };
+        PlatformInfo {
+            arch: info.architecture().and_then(RuntimeArch::from_name),
+            os: constants::DEFAULT_CHANNEL_NAME.to_owned(),
+            os_version,
+        }
+    }
+
+    /// Returns the reason a release with the specified architecture, operating system and minimum operating system
+    /// version can not run on this machine, or None if it can. Anything which is unspecified, unrecognised or
+    /// could not be detected is assumed to be compatible.
+    pub fn get_incompatibility(&self, arch: Option<&str>, os: Option<&str>, min_os_version: Option<&str>) -> Option<String> {
+        if let Some(os) = os.map(normalize_os).filter(|os| !os.is_empty()) {
+            if os != self.os {
+                return Some(format!("it is for '{}' but this machine is '{}'", os, self.os));
+            }
+        }
+
+        if let Some(min_version) = min_os_version.and_then(|v| parse_os_version(v, &self.os)) {
+            if let Some(os_version) = self.os_version {
+                if os_version < min_version {
+                    return Some(format!(
+                        "it requires OS version {} but this machine is {}",
+                        format_os_version(min_version),
+                        format_os_version(os_version)
+                    ));
+                }
+            }
+        }
+
+        if let (Some(machine), Some(arch)) = (self.arch, arch.and_then(RuntimeArch::from_name)) {
+            if !self.is_arch_supported(machine, arch) {
+                return Some(format!("it is for {:?} but this machine is {:?}", arch, machine));
+            }
+        }
+
+        None
+    }
+
+    fn is_arch_supported(&self, machine: RuntimeArch, arch: RuntimeArch) -> bool {
+        if machine == arch {
+            return true;
+        }
+        match self.os.as_str() {
+            // windows x64 also supports x86, and windows arm64 supports x86, and only on Windows 11 does it support x64.
+            "win" => match machine {
+                RuntimeArch::X86 => false,
+                RuntimeArch::X64 => arch == RuntimeArch::X86,
+                RuntimeArch::Arm64 => arch == RuntimeArch::X86 || self.os_version.map_or(true, |v| v >= (10, 0, 22000)),
+            },
+            // macos arm64 can run x64 with rosetta.
+            "osx" => machine == RuntimeArch::Arm64 && arch == RuntimeArch::X64,
+            _ => false,
+        }
+    }
+}
+
+fn normalize_os(os: &str) -> String {
+    match os.trim().to_ascii_lowercase().as_str() {
+        "windows" | "win" => "win".to_owned(),
+        "osx" | "macos" | "darwin" => "osx".to_owned(),
+        other => other.to_owned(),
+    }
+}
+
+/// Parses an OS version such as '10.0.19041' or '11'. Windows 11 is reported as 10.0.22000 or later, so it is mapped to that.
+fn parse_os_version(version: &str, os: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = version.trim().split('.').map(|p| p.parse::<u64>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    if major == 11 && os == "win" {
+        return Some((10, 0, patch.max(22000)));
+    }
+    Some((major, minor, patch))
+}
+
+fn format_os_version(version: (u64, u64, u64)) -> String {
+    format!("{}.{}.{}", version.0, version.1, version.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn platform(arch: RuntimeArch, os: &str, os_version: (u64, u64, u64)) -> PlatformInfo {
+        PlatformInfo { arch: Some(arch), os: os.to_owned(), os_version: Some(os_version) }
+    }
+
+    #[test]
+    fn test_architecture_compatibility() {
+        let win_x64 = platform(RuntimeArch::X64, "win", (10, 0, 19045));
+        assert!(win_x64.get_incompatibility(Some("x64"), Some("win"), None).is_none());
+        assert!(win_x64.get_incompatibility(Some("x86"), None, None).is_none());
+        assert!(win_x64.get_incompatibility(Some("arm64"), None, None).is_some());
+        assert!(win_x64.get_incompatibility(Some("unknown"), None, None).is_none());
+        assert!(win_x64.get_incompatibility(None, None, None).is_none());
+
+        let win10_arm64 = platform(RuntimeArch::Arm64, "win", (10, 0, 19045));
+        assert!(win10_arm64.get_incompatibility(Some("x64"), None, None).is_some());
+        let win11_arm64 = platform(RuntimeArch::Arm64, "win", (10, 0, 22631));
+        assert!(win11_arm64.get_incompatibility(Some("x64"), None, None).is_none());
+        assert!(win11_arm64.get_incompatibility(None, None, Some("11")).is_none());
+        assert!(win10_arm64.get_incompatibility(None, None, Some("11")).is_some());
+
+        let osx_arm64 = platform(RuntimeArch::Arm64, "osx", (14, 5, 0));
+        assert!(osx_arm64.get_incompatibility(Some("x64"), None, None).is_none());
+        let linux_x64 = platform(RuntimeArch::X64, "linux", (6, 1, 0));
+        assert!(linux_x64.get_incompatibility(Some("arm64"), None, None).is_some());
+
+        let unknown = PlatformInfo { arch: None, os: "linux".to_owned(), os_version: None };
+        assert!(unknown.get_incompatibility(Some("arm64"), None, Some("99")).is_none());
+    }
+
+    #[test]
+    fn test_os_compatibility() {
+        let osx = platform(RuntimeArch::X64, "osx", (13, 2, 1));
+        assert!(osx.get_incompatibility(None, Some("macos"), None).is_none());
+        assert!(osx.get_incompatibility(None, Some("win"), None).is_some());
+        assert!(osx.get_incompatibility(None, Some(""), None).is_none());
+        assert!(osx.get_incompatibility(None, None, Some("13")).is_none());
+        assert!(osx.get_incompatibility(None, None, Some("13.2.1")).is_none());
+        assert!(osx.get_incompatibility(None, None, Some("13.3")).is_some());
+        assert!(osx.get_incompatibility(None, None, Some("not a version")).is_none());
+    }
+}
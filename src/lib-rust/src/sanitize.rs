@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::Error;
+
+fn unsafe_entry(name: &str, reason: &str) -> Error {
+    Error::InvalidPackage(format!("Unsafe archive entry '{}' ({})", name, reason))
+}
+
+/// Validates an archive entry name and returns the relative path it should be extracted to. Both '/' and '\'
+/// are treated as separators. Absolute paths, drive letters, '..' components and null characters are rejected.
+pub fn sanitize_entry_name(name: &str) -> Result<PathBuf, Error> {
+    if name.contains('\0') {
+        return Err(unsafe_entry(name, "contains a null character"));
+    }
+
+    let normalized = name.replace('\\', "/");
+    if normalized.starts_with('/') {
+        return Err(unsafe_entry(name, "is an absolute path"));
+    }
+
+    let mut path = PathBuf::new();
+    for part in normalized.split('/') {
+        match part {
+            "" | "." => continue,
+            ".." => return Err(unsafe_entry(name, "contains a parent directory component")),
+            p if p.contains(':') => return Err(unsafe_entry(name, "contains a drive or stream separator")),
+            p => path.push(p),
+        }
+    }
+
+    if path.as_os_str().is_empty() {
+        return Err(unsafe_entry(name, "is empty"));
+    }
+    Ok(path)
+}
+
+/// Validates every entry name in an archive, and checks that no two entries would be extracted to the same path.
+/// On Windows and macOS names are also compared case-insensitively, because the file systems there usually are.
+pub fn validate_entry_names<S: AsRef<str>>(names: &[S]) -> Result<(), Error> {
+    let mut seen: HashMap<String, (String, bool)> = HashMap::new();
+    for name in names {
+        let name = name.as_ref();
+        let is_dir = name.ends_with('/') || name.ends_with('\\');
+        let key = sanitize_entry_name(name)?.to_string_lossy().replace('\\', "/");
+        #[cfg(any(target_os = "windows", target_os = "macos"))]
+        let key = key.to_lowercase();
+        if let Some((other_name, other_is_dir)) = seen.get(&key) {
+            if !is_dir || !other_is_dir {
+                return Err(unsafe_entry(name, &format!("collides with '{}'", other_name)));
+            }
+        }
+        seen.insert(key, (name.to_owned(), is_dir));
+    }
+    Ok(())
+}
+
+/// Joins a sanitized relative path to the target directory. Fails if any part of the path which already exists
+/// is a symlink that resolves outside of the target directory, since writing through it would escape the target.
+pub fn resolve_entry_path(target_dir: &Path, relative_path: &Path) -> Result<PathBuf, Error> {
+    let display = relative_path.to_string_lossy();
+    let mut current = target_dir.to_path_buf();
+    let mut canonical_target: Option<PathBuf> = None;
+    let mut exists = true;
+
+    for component in relative_path.components() {
+        let Component::Normal(part) = component else {
+            return Err(unsafe_entry(&display, "is not a plain relative path"));
+        };
+        current.push(part);
+
+        if !exists {
+            continue;
+        }
+
+        match fs::symlink_metadata(&current) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                if canonical_target.is_none() {
+                    canonical_target = Some(fs::canonicalize(target_dir)?);
+                }
+                let resolved = fs::canonicalize(&current).map_err(|_| unsafe_entry(&display, "resolves through a broken symlink"))?;
+                if !resolved.starts_with(canonical_target.as_ref().unwrap()) {
+                    return Err(unsafe_entry(&display, "resolves through a symlink outside of the target directory"));
+                }
+            }
+            Ok(_) => {}
+            Err(_) => exists = false,
+        }
+    }
+
+    Ok(current)
+}
+
+/// Validates that a symlink which will be created at `link_path` (inside `target_dir`) with the specified
+/// target does not point outside of the target directory.
+pub fn validate_symlink_target(target_dir: &Path, link_path: &Path, link_target: &str) -> Result<(), Error> {
+    let display = link_path.to_string_lossy();
+    let link_dir = link_path
+        .parent()
+        .and_then(|p| p.strip_prefix(target_dir).ok())
+        .ok_or_else(|| unsafe_entry(&display, "is not inside the target directory"))?;
+
+    let normalized = link_target.replace('\\', "/");
+    if normalized.starts_with('/') || normalized.contains(':') {
+        return Err(unsafe_entry(&display, "is a symlink with an absolute target"));
+    }
+
+    let mut depth = link_dir.components().count();
+    for part in normalized.split('/') {
+        match part {
+            "" | "." => continue,
+            ".." if depth == 0 => return Err(unsafe_entry(&display, "is a symlink to a target outside of the target directory")),
+            ".." => depth -= 1,
+            _ => depth += 1,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_entry_name() {
+        assert_eq!(sanitize_entry_name("lib/app/app.exe").unwrap(), Path::new("lib").join("app").join("app.exe"));
+        assert_eq!(sanitize_entry_name("lib\\app\\.\\app.exe").unwrap(), Path::new("lib").join("app").join("app.exe"));
+        assert_eq!(sanitize_entry_name("lib//app/").unwrap(), Path::new("lib").join("app"));
+
+        for name in ["", "/", "/etc/passwd", "\\evil", "../evil", "lib/../../evil", "lib\\..\\evil", "C:/evil", "C:evil", "a.txt:stream", "a\0b"] {
+            assert!(sanitize_entry_name(name).is_err(), "{:?} should be rejected", name);
+        }
+    }
+
+    #[test]
+    fn test_validate_entry_names() {
+        validate_entry_names(&["lib/", "lib/app/", "lib/app/a.txt", "lib/app/b.txt", "./lib/"]).unwrap();
+        let case_collision = validate_entry_names(&["lib/a.txt", "lib/A.TXT"]);
+        assert_eq!(case_collision.is_err(), cfg!(any(target_os = "windows", target_os = "macos")));
+        assert!(validate_entry_names(&["lib/a.txt", "lib/./a.txt"]).is_err());
+        assert!(validate_entry_names(&["lib/a.txt", "lib\\a.txt"]).is_err());
+        assert!(validate_entry_names(&["lib/a", "lib/a/"]).is_err());
+        assert!(validate_entry_names(&["lib/../a"]).is_err());
+    }
+
+    #[test]
+    fn test_validate_symlink_target() {
+        let root = Path::new("root");
+        validate_symlink_target(root, &root.join("a").join("link"), "b/c").unwrap();
+        validate_symlink_target(root, &root.join("a").join("link"), "../b").unwrap();
+        validate_symlink_target(root, &root.join("link"), "a/../b").unwrap();
+        assert!(validate_symlink_target(root, &root.join("a").join("link"), "../../b").is_err());
+        assert!(validate_symlink_target(root, &root.join("link"), "a/../../b").is_err());
+        assert!(validate_symlink_target(root, &root.join("link"), "/etc/passwd").is_err());
+        assert!(validate_symlink_target(root, &root.join("link"), "C:\\Windows").is_err());
+        assert!(validate_symlink_target(root, Path::new("elsewhere").join("link").as_path(), "a").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_entry_path_rejects_escaping_symlinks() {
+        let outside = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        fs::create_dir_all(target.path().join("inside")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), target.path().join("escape")).unwrap();
+        std::os::unix::fs::symlink(target.path().join("inside"), target.path().join("alias")).unwrap();
+
+        let path = resolve_entry_path(target.path(), Path::new("new/dir/file.txt")).unwrap();
+        assert_eq!(path, target.path().join("new/dir/file.txt"));
+        resolve_entry_path(target.path(), Path::new("alias/file.txt")).unwrap();
+        assert!(resolve_entry_path(target.path(), Path::new("escape/file.txt")).is_err());
+        assert!(resolve_entry_path(target.path(), Path::new("escape")).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use base64::Engine;
+use ring::signature::{UnparsedPublicKey, ED25519};
+
+use crate::Error;
+
+const PUBLIC_KEY_LENGTH: usize = 32;
+const SIGNATURE_LENGTH: usize = 64;
+
+/// Returns the name of the detached signature file for the specified file, eg. 'releases.stable.json.sig'.
+pub fn get_signature_file_name(file_name: &str) -> String {
+    format!("{}.sig", file_name)
+}
+
+/// Returns the data which is signed for a release feed: the feed file name (eg. 'releases.stable.json') and a
+/// newline, followed by the contents of the feed. Signing the name means that a feed signed for one channel
+/// can not be served in place of the feed for another channel.
+pub fn get_feed_signature_payload(file_name: &str, json: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(file_name.len() + 1 + json.len());
+    payload.extend_from_slice(file_name.as_bytes());
+    payload.push(b'\n');
+    payload.extend_from_slice(json);
+    payload
+}
+
+/// Verifies detached Ed25519 signatures against one or more trusted public keys. A signature is accepted
+/// if it was made by any of the trusted keys, so a new key can be trusted before the old one is retired.
+#[derive(Debug, Clone, Default)]
+pub struct SignatureVerifier {
+    public_keys: Vec<[u8; PUBLIC_KEY_LENGTH]>,
+}
+
+impl SignatureVerifier {
+    /// Create a new SignatureVerifier from a list of base64 encoded Ed25519 public keys.
+    pub fn new<S: AsRef<str>>(public_keys: &[S]) -> Result<SignatureVerifier, Error> {
+        let mut keys = Vec::new();
+        for key in public_keys {
+            let key = key.as_ref().trim();
+            let bytes = decode_base64(key).map_err(|e| Error::Other(format!("Invalid trusted public key '{}': {}", key, e)))?;
+            let bytes: [u8; PUBLIC_KEY_LENGTH] = bytes.try_into().map_err(|b: Vec<u8>| {
+                Error::Other(format!(
+                    "Invalid trusted public key '{}': expected {} bytes, found {}",
+                    key,
+                    PUBLIC_KEY_LENGTH,
+                    b.len()
+                ))
+            })?;
+            keys.push(bytes);
+        }
+        Ok(SignatureVerifier { public_keys: keys })
+    }
+
+    /// Returns true if there are no trusted keys, in which case every signature will be rejected.
+    pub fn is_empty(&self) -> bool {
+        self.public_keys.is_empty()
+    }
+
+    /// Verifies that the base64 encoded detached signature was made over `data` by one of the trusted keys.
+    /// The name is only used to describe the file in the returned error.
+    pub fn verify(&self, name: &str, data: &[u8], signature: &str) -> Result<(), Error> {
+        let signature = decode_base64(signature.trim()).map_err(|e| Error::SignatureInvalid(name.to_owned(), e))?;
+        if signature.len() != SIGNATURE_LENGTH {
+            return Err(Error::SignatureInvalid(
+                name.to_owned(),
+                format!("expected a {} byte signature, found {}", SIGNATURE_LENGTH, signature.len()),
+            ));
+        }
+
+        for (i, key) in self.public_keys.iter().enumerate() {
+            if UnparsedPublicKey::new(&ED25519, key).verify(data, &signature).is_ok() {
+                debug!("Signature of '{}' was verified with trusted key {}.", name, i);
+                return Ok(());
+            }
+        }
+
+        Err(Error::SignatureInvalid(name.to_owned(), "it was not signed by any of the trusted keys".to_owned()))
+    }
+
+    /// Verifies the base64 encoded detached signature of a release feed, see `get_feed_signature_payload`.
+    pub fn verify_feed(&self, file_name: &str, json: &[u8], signature: &str) -> Result<(), Error> {
+        self.verify(file_name, &get_feed_signature_payload(file_name, json), signature)
+    }
+}
+
+fn decode_base64(value: &str) -> Result<Vec<u8>, String> {
+    base64::engine::general_purpose::STANDARD.decode(value).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    fn get_key_pair(seed: u8) -> Ed25519KeyPair {
+        Ed25519KeyPair::from_seed_unchecked(&[seed; 32]).unwrap()
+    }
+
+    fn get_public_key(key_pair: &Ed25519KeyPair) -> String {
+        base64::engine::general_purpose::STANDARD.encode(key_pair.public_key().as_ref())
+    }
+
+    fn sign(key_pair: &Ed25519KeyPair, data: &[u8]) -> String {
+        base64::engine::general_purpose::STANDARD.encode(key_pair.sign(data).as_ref())
+    }
+
+    #[test]
+    fn test_verify_accepts_any_trusted_key() {
+        let old_key = get_key_pair(1);
+        let new_key = get_key_pair(2);
+        let verifier = SignatureVerifier::new(&[get_public_key(&old_key), get_public_key(&new_key)]).unwrap();
+
+        verifier.verify("feed", b"hello", &sign(&old_key, b"hello")).unwrap();
+        verifier.verify("feed", b"hello", &sign(&new_key, b"hello")).unwrap();
+    }
+
+    #[test]
+    fn test_verify_rejects_bad_signatures() {
+        let trusted = get_key_pair(1);
+        let untrusted = get_key_pair(3);
+        let verifier = SignatureVerifier::new(&[get_public_key(&trusted)]).unwrap();
+
+        assert!(matches!(verifier.verify("feed", b"hello", &sign(&untrusted, b"hello")), Err(Error::SignatureInvalid(..))));
+        assert!(matches!(verifier.verify("feed", b"tampered", &sign(&trusted, b"hello")), Err(Error::SignatureInvalid(..))));
+        assert!(matches!(verifier.verify("feed", b"hello", "not base64!"), Err(Error::SignatureInvalid(..))));
+        assert!(matches!(verifier.verify("feed", b"hello", "AAAA"), Err(Error::SignatureInvalid(..))));
+    }
+
+    #[test]
+    fn test_verify_feed_is_bound_to_the_feed_name() {
+        let key = get_key_pair(1);
+        let verifier = SignatureVerifier::new(&[get_public_key(&key)]).unwrap();
+        let signature = sign(&key, &get_feed_signature_payload("releases.beta.json", b"{}"));
+
+        verifier.verify_feed("releases.beta.json", b"{}", &signature).unwrap();
+        assert!(matches!(verifier.verify_feed("releases.stable.json", b"{}", &signature), Err(Error::SignatureInvalid(..))));
+        assert!(matches!(verifier.verify_feed("releases.beta.json", b"{}", &sign(&key, b"{}")), Err(Error::SignatureInvalid(..))));
+    }
+
+    #[test]
+    fn test_invalid_public_keys_are_an_error() {
+        assert!(SignatureVerifier::new(&["not base64!"]).is_err());
+        assert!(SignatureVerifier::new(&["AAAA"]).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    future::Future,
+    path::Path,
+    sync::{mpsc::Sender, Arc},
+};
+
+use crate::bundle::Manifest;
+use crate::*;
+
+use super::{AsyncUpdateSource, UpdateSource};
+
+/// Allows a blocking `UpdateSource` to be used where an `AsyncUpdateSource` is required. Each request
+/// is run on the tokio blocking thread pool, so it will not stall the async runtime.
+pub struct AsyncSourceAdapter {
+    source: Arc<dyn UpdateSource>,
+}
+
+impl Clone for AsyncSourceAdapter {
+    fn clone(&self) -> Self {
+        AsyncSourceAdapter {
+            source: Arc::clone(&self.source),
+        }
+    }
+}
+
+impl AsyncSourceAdapter {
+    /// Create a new AsyncSourceAdapter which wraps the specified blocking source.
+    pub fn new<T: UpdateSource + 'static>(source: T) -> AsyncSourceAdapter {
+        AsyncSourceAdapter { source: Arc::new(source) }
+    }
+
+    /// Create a new AsyncSourceAdapter which wraps a shared blocking source.
+    pub fn new_shared(source: Arc<dyn UpdateSource>) -> AsyncSourceAdapter {
+        AsyncSourceAdapter { source }
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncUpdateSource for AsyncSourceAdapter {
+    async fn get_release_feed_async(&self, channel: &str, app: &Manifest, staged_user_id: &str) -> Result<VelopackAssetFeed, Error> {
+        let source = Arc::clone(&self.source);
+        let channel = channel.to_owned();
+        let app = app.clone();
+        let staged_user_id = staged_user_id.to_owned();
+        spawn_blocking(move || source.get_release_feed(&channel, &app, &staged_user_id)).await
+    }
+
+    async fn download_release_entry_async(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<(), Error> {
+        let source = Arc::clone(&self.source);
+        let asset = asset.clone();
+        let local_file = local_file.to_path_buf();
+        spawn_blocking(move || source.download_release_entry(&asset, &local_file, progress_sender)).await
+    }
+
+    async fn download_release_entry_with_hashes_async(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<Option<FileHashes>, Error> {
+        let source = Arc::clone(&self.source);
+        let asset = asset.clone();
+        let local_file = local_file.to_path_buf();
+        spawn_blocking(move || source.download_release_entry_with_hashes(&asset, &local_file, progress_sender)).await
+    }
+
+    fn set_default_http_config(&mut self, config: &HttpClientConfig) {
+        match Arc::get_mut(&mut self.source) {
+            Some(source) => source.set_default_http_config(config),
+            None => warn!("AsyncSourceAdapter has been cloned, so the HttpClientConfig from UpdateOptions can not be applied."),
+        }
+    }
+
+    fn set_feed_cache(&mut self, cache: &FeedCache) {
+        match Arc::get_mut(&mut self.source) {
+            Some(source) => source.set_feed_cache(cache),
+            None => warn!("AsyncSourceAdapter has been cloned, so the feed cache can not be applied."),
+        }
+    }
+
+    fn set_download_throttle(&mut self, throttle: &DownloadThrottle) {
+        match Arc::get_mut(&mut self.source) {
+            Some(source) => source.set_download_throttle(throttle),
+            None => warn!("AsyncSourceAdapter has been cloned, so the download throttle can not be applied."),
+        }
+    }
+
+    fn set_cancellation_token(&mut self, token: &CancellationToken) {
+        match Arc::get_mut(&mut self.source) {
+            Some(source) => source.set_cancellation_token(token),
+            None => warn!("AsyncSourceAdapter has been cloned, so the cancellation token can not be applied."),
+        }
+    }
+
+    fn set_feed_verifier(&mut self, verifier: &SignatureVerifier) -> bool {
+        match Arc::get_mut(&mut self.source) {
+            Some(source) => source.set_feed_verifier(verifier),
+            None => {
+                warn!("AsyncSourceAdapter has been cloned, so the feed verifier can not be applied.");
+                false
+            }
+        }
+    }
+}
+
+/// Allows an `AsyncUpdateSource` to be used where a blocking `UpdateSource` is required. Each request is
+/// run to completion on a short-lived runtime on its own thread, so it is safe to call from any context,
+/// but it will block the calling thread until the request is complete.
+pub struct BlockingSourceAdapter {
+    source: Arc<dyn AsyncUpdateSource>,
+}
+
+impl Clone for BlockingSourceAdapter {
+    fn clone(&self) -> Self {
+        BlockingSourceAdapter {
+            source: Arc::clone(&self.source),
+        }
+    }
+}
+
+impl BlockingSourceAdapter {
+    /// Create a new BlockingSourceAdapter which wraps the specified async source.
+    pub fn new<T: AsyncUpdateSource + 'static>(source: T) -> BlockingSourceAdapter {
+        BlockingSourceAdapter { source: Arc::new(source) }
+    }
+
+    /// Create a new BlockingSourceAdapter which wraps a shared async source.
+    pub fn new_shared(source: Arc<dyn AsyncUpdateSource>) -> BlockingSourceAdapter {
+        BlockingSourceAdapter { source }
+    }
+}
+
+impl UpdateSource for BlockingSourceAdapter {
+    fn get_release_feed(&self, channel: &str, app: &Manifest, staged_user_id: &str) -> Result<VelopackAssetFeed, Error> {
+        block_on(self.source.get_release_feed_async(channel, app, staged_user_id))
+    }
+
+    fn download_release_entry(&self, asset: &VelopackAsset, local_file: &Path, progress_sender: Option<Sender<i16>>) -> Result<(), Error> {
+        block_on(self.source.download_release_entry_async(asset, local_file, progress_sender))
+    }
+
+    fn download_release_entry_with_hashes(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<Option<FileHashes>, Error> {
+        block_on(self.source.download_release_entry_with_hashes_async(asset, local_file, progress_sender))
+    }
+
+    fn set_default_http_config(&mut self, config: &HttpClientConfig) {
+        match Arc::get_mut(&mut self.source) {
+            Some(source) => source.set_default_http_config(config),
+            None => warn!("BlockingSourceAdapter has been cloned, so the HttpClientConfig from UpdateOptions can not be applied."),
+        }
+    }
+
+    fn set_feed_cache(&mut self, cache: &FeedCache) {
+        match Arc::get_mut(&mut self.source) {
+            Some(source) => source.set_feed_cache(cache),
+            None => warn!("BlockingSourceAdapter has been cloned, so the feed cache can not be applied."),
+        }
+    }
+
+    fn set_download_throttle(&mut self, throttle: &DownloadThrottle) {
+        match Arc::get_mut(&mut self.source) {
+            Some(source) => source.set_download_throttle(throttle),
+            None => warn!("BlockingSourceAdapter has been cloned, so the download throttle can not be applied."),
+        }
+    }
+
+    fn set_cancellation_token(&mut self, token: &CancellationToken) {
+        match Arc::get_mut(&mut self.source) {
+            Some(source) => source.set_cancellation_token(token),
+            None => warn!("BlockingSourceAdapter has been cloned, so the cancellation token can not be applied."),
+        }
+    }
+
+    fn set_feed_verifier(&mut self, verifier: &SignatureVerifier) -> bool {
+        match Arc::get_mut(&mut self.source) {
+            Some(source) => source.set_feed_verifier(verifier),
+            None => {
+                warn!("BlockingSourceAdapter has been cloned, so the feed verifier can not be applied.");
+                false
+            }
+        }
+    }
+}
+
+/// Runs a blocking operation on the tokio blocking thread pool, and waits for it to complete.
+pub(crate) async fn spawn_blocking<T, F>(operation: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, Error> + Send + 'static,
+{
+    tokio::task::spawn_blocking(operation)
+        .await
+        .map_err(|e| Error::Other(format!("Blocking update operation did not complete: {}", e)))?
+}
+
+/// Runs a future to completion on a new runtime. The runtime is created on a separate thread, because
+/// tokio does not allow a runtime to be started from a thread which is already running one.
+fn block_on<T, F>(future: F) -> Result<T, Error>
+where
+    T: Send,
+    F: Future<Output = Result<T, Error>> + Send,
+{
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+                runtime.block_on(future)
+            })
+            .join()
+            .map_err(|_| Error::Other("Async update operation panicked".to_owned()))?
+    })
+}
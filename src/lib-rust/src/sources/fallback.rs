@@ -0,0 +1,163 @@
+use std::{
+    path::Path,
+    sync::{mpsc::Sender, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::bundle::Manifest;
+use crate::*;
+
+use super::UpdateSource;
+
+struct FallbackEntry {
+    name: String,
+    source: Box<dyn UpdateSource>,
+    unhealthy_until: Mutex<Option<Instant>>,
+}
+
+/// Wraps an ordered list of sources (eg. a primary CDN and one or more mirrors), and tries each
+/// of them in turn until one succeeds. A source which fails is skipped for a cooldown period,
+/// unless every source is currently cooling down, in which case they are all tried again.
+pub struct FallbackSource {
+    entries: Vec<FallbackEntry>,
+    cooldown: Duration,
+}
+
+impl Default for FallbackSource {
+    fn default() -> Self {
+        FallbackSource::new()
+    }
+}
+
+impl FallbackSource {
+    /// Create a new FallbackSource with no sources. Add sources in priority order with `with_source`.
+    pub fn new() -> FallbackSource {
+        FallbackSource {
+            entries: Vec::new(),
+            cooldown: Duration::from_secs(300),
+        }
+    }
+
+    /// Add a source to the end of the list. The name is used to log which source served each request.
+    pub fn with_source<T: UpdateSource + 'static>(self, name: &str, source: T) -> FallbackSource {
+        self.with_source_boxed(name, Box::new(source))
+    }
+
+    /// Add a boxed source to the end of the list. The name is used to log which source served each request.
+    pub fn with_source_boxed(mut self, name: &str, source: Box<dyn UpdateSource>) -> FallbackSource {
+        self.entries.push(FallbackEntry {
+            name: name.to_owned(),
+            source,
+            unhealthy_until: Mutex::new(None),
+        });
+        self
+    }
+
+    /// How long a source is skipped for after it fails. The default is 5 minutes.
+    pub fn with_cooldown(mut self, cooldown: Duration) -> FallbackSource {
+        self.cooldown = cooldown;
+        self
+    }
+
+    fn try_each<T, F>(&self, operation: &str, mut action: F) -> Result<(T, &str), Error>
+    where
+        F: FnMut(&dyn UpdateSource) -> Result<T, Error>,
+    {
+        if self.entries.is_empty() {
+            return Err(Error::NotSupported("FallbackSource does not contain any sources".to_owned()));
+        }
+
+        let now = Instant::now();
+        let mut healthy: Vec<&FallbackEntry> = self
+            .entries
+            .iter()
+            .filter(|e| e.unhealthy_until.lock().unwrap().map(|until| until <= now).unwrap_or(true))
+            .collect();
+        if healthy.is_empty() {
+            warn!("All sources are cooling down after recent failures, trying them all again.");
+            healthy = self.entries.iter().collect();
+        }
+
+        let mut last_error = None;
+        for entry in healthy {
+            match action(entry.source.as_ref()) {
+                Ok(result) => {
+                    *entry.unhealthy_until.lock().unwrap() = None;
+                    return Ok((result, &entry.name));
+                }
+                Err(Error::Cancelled) => return Err(Error::Cancelled),
+                Err(e) => {
+                    warn!(
+                        "Source '{}' failed to {}, skipping it for {:?}: {}",
+                        entry.name, operation, self.cooldown, e
+                    );
+                    *entry.unhealthy_until.lock().unwrap() = Some(Instant::now() + self.cooldown);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap())
+    }
+}
+
+impl UpdateSource for FallbackSource {
+    fn get_release_feed(&self, channel: &str, app: &Manifest, staged_user_id: &str) -> Result<VelopackAssetFeed, Error> {
+        let (feed, name) = self.try_each("get the release feed", |source| source.get_release_feed(channel, app, staged_user_id))?;
+        info!("Release feed for channel {} was served by source '{}'.", channel, name);
+        Ok(feed)
+    }
+
+    fn download_release_entry(&self, asset: &VelopackAsset, local_file: &Path, progress_sender: Option<Sender<i16>>) -> Result<(), Error> {
+        self.download_release_entry_with_hashes(asset, local_file, progress_sender)?;
+        Ok(())
+    }
+
+    fn download_release_entry_with_hashes(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<Option<FileHashes>, Error> {
+        let (hashes, name) = self.try_each("download an asset", |source| {
+            source.download_release_entry_with_hashes(asset, local_file, progress_sender.clone())
+        })?;
+        info!("Asset '{}' was served by source '{}'.", asset.FileName, name);
+        Ok(hashes)
+    }
+
+    fn set_default_http_config(&mut self, config: &HttpClientConfig) {
+        for entry in &mut self.entries {
+            entry.source.set_default_http_config(config);
+        }
+    }
+
+    fn set_feed_cache(&mut self, cache: &FeedCache) {
+        for entry in &mut self.entries {
+            entry.source.set_feed_cache(cache);
+        }
+    }
+
+    fn set_download_throttle(&mut self, throttle: &DownloadThrottle) {
+        for entry in &mut self.entries {
+            entry.source.set_download_throttle(throttle);
+        }
+    }
+
+    fn set_cancellation_token(&mut self, token: &CancellationToken) {
+        for entry in &mut self.entries {
+            entry.source.set_cancellation_token(token);
+        }
+    }
+
+    fn set_feed_verifier(&mut self, verifier: &SignatureVerifier) -> bool {
+        let mut supported = !self.entries.is_empty();
+        for entry in &mut self.entries {
+            if !entry.source.set_feed_verifier(verifier) {
+                warn!("Source '{}' does not support release feed signatures.", entry.name);
+                supported = false;
+            }
+        }
+        supported
+    }
+}
@@ -1,11 +1,16 @@
 use std::{
+    fs::File,
+    io::{Read, Write},
     path::{Path, PathBuf},
     sync::mpsc::Sender,
 };
 
 use crate::bundle::Manifest;
+use crate::hashes::MultiHasher;
 use crate::*;
 
+#[cfg(feature = "async")]
+use super::AsyncUpdateSource;
 use super::UpdateSource;
 
 #[derive(Clone)]
@@ -13,13 +18,17 @@ use super::UpdateSource;
 /// must contain one or more valid packages, as well as a 'releases.{channel}.json' index file.
 pub struct FileSource {
     path: PathBuf,
+    feed_verifier: Option<SignatureVerifier>,
 }
 
 impl FileSource {
     /// Create a new FileSource with the specified base directory.
     pub fn new<P: AsRef<Path>>(path: P) -> FileSource {
         let path = path.as_ref();
-        FileSource { path: PathBuf::from(path) }
+        FileSource {
+            path: PathBuf::from(path),
+            feed_verifier: None,
+        }
     }
 }
 
@@ -29,21 +38,111 @@ impl UpdateSource for FileSource {
         let releases_path = self.path.join(&releases_name);
 
         info!("Reading releases from file: {:?}", releases_path);
-        let json = std::fs::read_to_string(releases_path)?;
+        let json = std::fs::read_to_string(&releases_path)?;
+        if let Some(verifier) = &self.feed_verifier {
+            let signature = std::fs::read_to_string(self.path.join(signing::get_signature_file_name(&releases_name)))?;
+            verifier.verify_feed(&releases_name, json.as_bytes(), &signature)?;
+        }
         let feed: VelopackAssetFeed = serde_json::from_str(&json)?;
         Ok(feed)
     }
 
     fn download_release_entry(&self, asset: &VelopackAsset, local_file: &Path, progress_sender: Option<Sender<i16>>) -> Result<(), Error> {
-        let asset_path = self.path.join(&asset.FileName);
-        info!("About to copy from file '{:?}' to file '{:?}'", asset_path, local_file);
-        if let Some(progress_sender) = &progress_sender {
-            let _ = progress_sender.send(50);
-        }
-        std::fs::copy(asset_path, local_file)?;
-        if let Some(progress_sender) = &progress_sender {
-            let _ = progress_sender.send(100);
+        self.download_release_entry_with_hashes(asset, local_file, progress_sender)?;
+        Ok(())
+    }
+
+    fn download_release_entry_with_hashes(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<Option<FileHashes>, Error> {
+        copy_release_entry(
+            &self.path.join(&asset.FileName),
+            local_file,
+            &asset.get_download_hash_algorithms(),
+            progress_sender,
+        )
+    }
+    fn set_feed_verifier(&mut self, verifier: &SignatureVerifier) -> bool {
+        self.feed_verifier = Some(verifier.clone());
+        true
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl AsyncUpdateSource for FileSource {
+    async fn get_release_feed_async(&self, channel: &str, _: &Manifest, _staged_user_id: &str) -> Result<VelopackAssetFeed, Error> {
+        let releases_name = format!("releases.{}.json", channel);
+        let releases_path = self.path.join(&releases_name);
+
+        info!("Reading releases from file: {:?}", releases_path);
+        let json = tokio::fs::read_to_string(&releases_path).await?;
+        if let Some(verifier) = &self.feed_verifier {
+            let signature = tokio::fs::read_to_string(self.path.join(signing::get_signature_file_name(&releases_name))).await?;
+            verifier.verify_feed(&releases_name, json.as_bytes(), &signature)?;
         }
+        let feed: VelopackAssetFeed = serde_json::from_str(&json)?;
+        Ok(feed)
+    }
+
+    async fn download_release_entry_async(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<(), Error> {
+        self.download_release_entry_with_hashes_async(asset, local_file, progress_sender).await?;
         Ok(())
     }
+
+    async fn download_release_entry_with_hashes_async(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<Option<FileHashes>, Error> {
+        let asset_path = self.path.join(&asset.FileName);
+        let local_file = local_file.to_path_buf();
+        let algorithms = asset.get_download_hash_algorithms();
+        super::spawn_blocking(move || copy_release_entry(&asset_path, &local_file, &algorithms, progress_sender)).await
+    }
+    fn set_feed_verifier(&mut self, verifier: &SignatureVerifier) -> bool {
+        UpdateSource::set_feed_verifier(self, verifier)
+    }
+}
+
+/// Copies a package to the local file, hashing it with the specified algorithms as it is written so that it does not
+/// need to be read again.
+fn copy_release_entry(
+    asset_path: &Path,
+    local_file: &Path,
+    algorithms: &[HashAlgorithm],
+    progress_sender: Option<Sender<i16>>,
+) -> Result<Option<FileHashes>, Error> {
+    info!("About to copy from file '{:?}' to file '{:?}'", asset_path, local_file);
+    if let Some(progress_sender) = &progress_sender {
+        let _ = progress_sender.send(50);
+    }
+
+    let mut source = File::open(asset_path)?;
+    let mut target = File::create(local_file)?;
+    let mut hasher = MultiHasher::new(algorithms);
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        let size = source.read(&mut buffer)?;
+        if size == 0 {
+            break;
+        }
+        target.write_all(&buffer[..size])?;
+        hasher.update(&buffer[..size]);
+    }
+    target.flush()?;
+
+    if let Some(progress_sender) = &progress_sender {
+        let _ = progress_sender.send(100);
+    }
+    Ok(Some(hasher.finalize()))
 }
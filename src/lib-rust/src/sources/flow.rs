@@ -9,6 +9,8 @@ use serde::Deserialize;
 use crate::bundle::Manifest;
 use crate::*;
 
+#[cfg(feature = "async")]
+use super::AsyncUpdateSource;
 use super::UpdateSource;
 
 #[derive(Deserialize)]
@@ -23,6 +25,10 @@ struct FlowReleaseAsset {
 pub struct VelopackFlowSource {
     base_uri: String,
     asset_ids: Mutex<HashMap<String, String>>,
+    http_config: Option<HttpClientConfig>,
+    feed_cache: Option<FeedCache>,
+    download_throttle: Option<DownloadThrottle>,
+    cancellation_token: Option<CancellationToken>,
 }
 
 impl VelopackFlowSource {
@@ -38,22 +44,19 @@ impl VelopackFlowSource {
         VelopackFlowSource {
             base_uri: uri,
             asset_ids: Mutex::new(HashMap::new()),
+            http_config: None,
+            feed_cache: None,
+            download_throttle: None,
+            cancellation_token: None,
         }
     }
-}
 
-impl Clone for VelopackFlowSource {
-    fn clone(&self) -> Self {
-        let ids = self.asset_ids.lock().unwrap().clone();
-        VelopackFlowSource {
-            base_uri: self.base_uri.clone(),
-            asset_ids: Mutex::new(ids),
-        }
+    /// Use the specified HTTP client configuration (proxy, timeouts, etc.) for all requests made by this source.
+    pub fn with_http_config(mut self, config: HttpClientConfig) -> VelopackFlowSource {
+        self.http_config = Some(config);
+        self
     }
-}
-
-impl UpdateSource for VelopackFlowSource {
-    fn get_release_feed(&self, channel: &str, app: &Manifest, staged_user_id: &str) -> Result<VelopackAssetFeed, Error> {
+    fn get_manifest_url(&self, channel: &str, app: &Manifest, staged_user_id: &str) -> Result<url::Url, Error> {
         let manifest_url = format!("{}v1.0/manifest/{}/{}", self.base_uri, app.id, channel);
         let mut url = url::Url::parse(&manifest_url)?;
 
@@ -72,10 +75,11 @@ impl UpdateSource for VelopackFlowSource {
                 query.append_pair("stagingId", staged_user_id);
             }
         }
+        Ok(url)
+    }
 
-        info!("Downloading releases from '{}'.", url);
-        let json = download::download_url_as_string(url.as_str())?;
-        let flow_assets: Vec<FlowReleaseAsset> = serde_json::from_str(&json)?;
+    fn parse_release_feed(&self, json: &str) -> Result<VelopackAssetFeed, Error> {
+        let flow_assets: Vec<FlowReleaseAsset> = serde_json::from_str(json)?;
 
         let mut ids = self.asset_ids.lock().unwrap();
         let mut assets = Vec::new();
@@ -86,10 +90,13 @@ impl UpdateSource for VelopackFlowSource {
             assets.push(fa.asset);
         }
 
-        Ok(VelopackAssetFeed { Assets: assets })
+        Ok(VelopackAssetFeed {
+            Assets: assets,
+            ..Default::default()
+        })
     }
 
-    fn download_release_entry(&self, asset: &VelopackAsset, local_file: &Path, progress_sender: Option<Sender<i16>>) -> Result<(), Error> {
+    fn get_download_url(&self, asset: &VelopackAsset) -> Result<String, Error> {
         let ids = self.asset_ids.lock().unwrap();
         let release_id = ids.get(&asset.FileName).ok_or_else(|| {
             Error::Other(format!(
@@ -97,14 +104,142 @@ impl UpdateSource for VelopackFlowSource {
                 asset.FileName
             ))
         })?;
+        Ok(format!("{}v1.0/download/{}", self.base_uri, release_id))
+    }
+}
+
+impl Clone for VelopackFlowSource {
+    fn clone(&self) -> Self {
+        let ids = self.asset_ids.lock().unwrap().clone();
+        VelopackFlowSource {
+            base_uri: self.base_uri.clone(),
+            asset_ids: Mutex::new(ids),
+            http_config: self.http_config.clone(),
+            feed_cache: self.feed_cache.clone(),
+            download_throttle: self.download_throttle.clone(),
+            cancellation_token: self.cancellation_token.clone(),
+        }
+    }
+}
+
+impl UpdateSource for VelopackFlowSource {
+    fn get_release_feed(&self, channel: &str, app: &Manifest, staged_user_id: &str) -> Result<VelopackAssetFeed, Error> {
+        let url = self.get_manifest_url(channel, app, staged_user_id)?;
+        info!("Downloading releases from '{}'.", url);
+        let config = self.http_config.clone().unwrap_or_default();
+        let json = download::download_url_as_string_cached(url.as_str(), &[], &config, self.feed_cache.as_ref())?;
+        self.parse_release_feed(&json)
+    }
 
-        let download_url = format!("{}v1.0/download/{}", self.base_uri, release_id);
+    fn download_release_entry(&self, asset: &VelopackAsset, local_file: &Path, progress_sender: Option<Sender<i16>>) -> Result<(), Error> {
+        self.download_release_entry_with_hashes(asset, local_file, progress_sender)?;
+        Ok(())
+    }
+
+    fn download_release_entry_with_hashes(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<Option<FileHashes>, Error> {
+        let download_url = self.get_download_url(asset)?;
         info!("Downloading '{}' from '{}'.", asset.FileName, download_url);
-        download::download_url_to_file(&download_url, local_file, move |p| {
-            if let Some(progress_sender) = &progress_sender {
-                let _ = progress_sender.send(p);
-            }
-        })?;
+        let config = self.http_config.clone().unwrap_or_default();
+        download::download_url_to_file_with_hashes(
+            &download_url,
+            local_file,
+            &[],
+            &config,
+            self.download_throttle.as_ref(),
+            self.cancellation_token.as_ref(),
+            &asset.get_download_hash_algorithms(),
+            move |p| {
+                if let Some(progress_sender) = &progress_sender {
+                    let _ = progress_sender.send(p);
+                }
+            },
+        )
+    }
+
+    fn set_default_http_config(&mut self, config: &HttpClientConfig) {
+        if self.http_config.is_none() {
+            self.http_config = Some(config.clone());
+        }
+    }
+
+    fn set_feed_cache(&mut self, cache: &FeedCache) {
+        self.feed_cache = Some(cache.clone());
+    }
+
+    fn set_download_throttle(&mut self, throttle: &DownloadThrottle) {
+        self.download_throttle = Some(throttle.clone());
+    }
+
+    fn set_cancellation_token(&mut self, token: &CancellationToken) {
+        self.cancellation_token = Some(token.clone());
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl AsyncUpdateSource for VelopackFlowSource {
+    async fn get_release_feed_async(&self, channel: &str, app: &Manifest, staged_user_id: &str) -> Result<VelopackAssetFeed, Error> {
+        let url = self.get_manifest_url(channel, app, staged_user_id)?;
+        info!("Downloading releases from '{}'.", url);
+        let config = self.http_config.clone().unwrap_or_default();
+        let json = download_async::download_url_as_string_cached(url.as_str(), &[], &config, self.feed_cache.as_ref()).await?;
+        self.parse_release_feed(&json)
+    }
+
+    async fn download_release_entry_async(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<(), Error> {
+        self.download_release_entry_with_hashes_async(asset, local_file, progress_sender).await?;
         Ok(())
     }
+
+    async fn download_release_entry_with_hashes_async(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<Option<FileHashes>, Error> {
+        let download_url = self.get_download_url(asset)?;
+        info!("Downloading '{}' from '{}'.", asset.FileName, download_url);
+        let config = self.http_config.clone().unwrap_or_default();
+        download_async::download_url_to_file_with_hashes(
+            &download_url,
+            local_file,
+            &[],
+            &config,
+            self.download_throttle.as_ref(),
+            self.cancellation_token.as_ref(),
+            &asset.get_download_hash_algorithms(),
+            move |p| {
+                if let Some(progress_sender) = &progress_sender {
+                    let _ = progress_sender.send(p);
+                }
+            },
+        )
+        .await
+    }
+
+    fn set_default_http_config(&mut self, config: &HttpClientConfig) {
+        UpdateSource::set_default_http_config(self, config);
+    }
+
+    fn set_feed_cache(&mut self, cache: &FeedCache) {
+        UpdateSource::set_feed_cache(self, cache);
+    }
+
+    fn set_download_throttle(&mut self, throttle: &DownloadThrottle) {
+        UpdateSource::set_download_throttle(self, throttle);
+    }
+
+    fn set_cancellation_token(&mut self, token: &CancellationToken) {
+        UpdateSource::set_cancellation_token(self, token);
+    }
 }
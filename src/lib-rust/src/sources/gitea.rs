@@ -1,11 +1,20 @@
-use std::{path::Path, sync::mpsc::Sender};
+use std::{
+    path::Path,
+    sync::{mpsc::Sender, Arc},
+    time::Duration,
+};
 
 use serde::Deserialize;
 
 use crate::bundle::Manifest;
 use crate::*;
 
-use super::{download_git_release_entry, get_git_release_feed, UpdateSource};
+use super::{
+    download_git_release_entry, get_git_release_feed, get_git_releases, GitReleaseCache, UpdateSource, DEFAULT_MAX_RATE_LIMIT_WAIT,
+    DEFAULT_MAX_RELEASE_PAGES,
+};
+#[cfg(feature = "async")]
+use super::{download_git_release_entry_async, get_git_release_feed_async, get_git_releases_async, AsyncUpdateSource};
 
 #[derive(Deserialize)]
 struct GiteaRelease {
@@ -27,6 +36,14 @@ pub struct GiteaSource {
     repo_url: url::Url,
     access_token: Option<String>,
     prerelease: bool,
+    http_config: Option<HttpClientConfig>,
+    feed_cache: Option<FeedCache>,
+    download_throttle: Option<DownloadThrottle>,
+    cancellation_token: Option<CancellationToken>,
+    feed_verifier: Option<SignatureVerifier>,
+    max_pages: usize,
+    max_rate_limit_wait: Duration,
+    release_cache: GitReleaseCache<GiteaRelease>,
 }
 
 impl GiteaSource {
@@ -40,9 +57,37 @@ impl GiteaSource {
             repo_url: url,
             access_token,
             prerelease,
+            http_config: None,
+            feed_cache: None,
+            download_throttle: None,
+            cancellation_token: None,
+            feed_verifier: None,
+            max_pages: DEFAULT_MAX_RELEASE_PAGES,
+            max_rate_limit_wait: DEFAULT_MAX_RATE_LIMIT_WAIT,
+            release_cache: GitReleaseCache::default(),
         }
     }
 
+    /// Use the specified HTTP client configuration (proxy, timeouts, etc.) for all requests made by this source.
+    pub fn with_http_config(mut self, config: HttpClientConfig) -> GiteaSource {
+        self.http_config = Some(config);
+        self
+    }
+
+    /// The maximum number of pages of releases to request, newest first. Pages are only requested until one
+    /// contains a release with the release feed (or the asset being downloaded). The default is 1.
+    pub fn with_max_pages(mut self, max_pages: usize) -> GiteaSource {
+        self.max_pages = max_pages.max(1);
+        self
+    }
+
+    /// The longest time to wait for the server's rate limit to reset before giving up with `Error::RateLimited`.
+    /// By default, rate limited requests are not retried.
+    pub fn with_max_rate_limit_wait(mut self, max_wait: Duration) -> GiteaSource {
+        self.max_rate_limit_wait = max_wait;
+        self
+    }
+
     fn get_api_base_url(&self) -> String {
         let scheme = self.repo_url.scheme();
         let host = self.repo_url.host_str().unwrap_or("gitea.com");
@@ -60,19 +105,56 @@ impl GiteaSource {
         headers
     }
 
-    fn get_releases(&self) -> Result<Vec<GiteaRelease>, Error> {
+    fn get_releases_url(&self) -> String {
         let base = self.get_api_base_url();
         let path = self.repo_url.path();
-        let url = format!("{}repos{}/releases?limit=10&page=1&draft=false", base, path);
+        format!("{}repos{}/releases?limit=10&draft=false", base, path)
+    }
+
+    fn get_releases(&self, is_wanted: impl Fn(&GiteaRelease) -> bool) -> Result<Vec<GiteaRelease>, Error> {
         let headers = self.get_headers("application/json");
         let header_refs: Vec<(&str, &str)> = headers.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
-        let json = download::download_url_as_string_with_headers(&url, &header_refs)?;
-        let mut releases: Vec<GiteaRelease> = serde_json::from_str(&json)?;
+        let config = self.http_config.clone().unwrap_or_default();
+        let releases = get_git_releases(
+            &self.get_releases_url(),
+            &header_refs,
+            &config,
+            self.feed_cache.as_ref(),
+            self.max_pages,
+            self.max_rate_limit_wait,
+            is_wanted,
+        )?;
+        Ok(self.sort_and_filter_releases(releases))
+    }
+
+    #[cfg(feature = "async")]
+    async fn get_releases_async(&self, is_wanted: impl Fn(&GiteaRelease) -> bool + Send) -> Result<Vec<GiteaRelease>, Error> {
+        let headers = self.get_headers("application/json");
+        let header_refs: Vec<(&str, &str)> = headers.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        let config = self.http_config.clone().unwrap_or_default();
+        let releases = get_git_releases_async(
+            &self.get_releases_url(),
+            &header_refs,
+            &config,
+            self.feed_cache.as_ref(),
+            self.max_pages,
+            self.max_rate_limit_wait,
+            is_wanted,
+        )
+        .await?;
+        Ok(self.sort_and_filter_releases(releases))
+    }
+
+    fn sort_and_filter_releases(&self, mut releases: Vec<GiteaRelease>) -> Vec<GiteaRelease> {
         releases.sort_by(|a, b| b.published_at.cmp(&a.published_at));
         if !self.prerelease {
             releases.retain(|r| !r.prerelease);
         }
-        Ok(releases)
+        releases
+    }
+
+    fn is_release_with_asset(&self, release: &GiteaRelease, asset_name: &str) -> bool {
+        (self.prerelease || !release.prerelease) && self.get_asset_url_from_name(release, asset_name).is_ok()
     }
 
     fn get_asset_url_from_name(&self, release: &GiteaRelease, asset_name: &str) -> Result<String, Error> {
@@ -102,23 +184,164 @@ impl GiteaSource {
 
 impl UpdateSource for GiteaSource {
     fn get_release_feed(&self, channel: &str, _app: &Manifest, _staged_user_id: &str) -> Result<VelopackAssetFeed, Error> {
-        let releases = self.get_releases()?;
+        let releases_file_name = format!("releases.{}.json", channel);
+        let releases = self
+            .release_cache
+            .set(self.get_releases(|r| self.is_release_with_asset(r, &releases_file_name))?);
         let headers = self.get_headers("application/octet-stream");
         let header_refs: Vec<(&str, &str)> = headers.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
-        get_git_release_feed(channel, &header_refs, releases.len(), |i, name| {
-            self.get_asset_url_from_name(&releases[i], name)
-        })
+        let config = self.http_config.clone().unwrap_or_default();
+        get_git_release_feed(
+            channel,
+            &header_refs,
+            &config,
+            self.feed_cache.as_ref(),
+            self.feed_verifier.as_ref(),
+            releases.len(),
+            |i, name| self.get_asset_url_from_name(&releases[i], name),
+        )
     }
 
     fn download_release_entry(&self, asset: &VelopackAsset, local_file: &Path, progress_sender: Option<Sender<i16>>) -> Result<(), Error> {
-        let releases = self.get_releases()?;
+        self.download_release_entry_with_hashes(asset, local_file, progress_sender)?;
+        Ok(())
+    }
+
+    fn download_release_entry_with_hashes(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<Option<FileHashes>, Error> {
+        let mut releases = self.release_cache.get();
+        if !releases.iter().any(|r| self.is_release_with_asset(r, &asset.FileName)) {
+            releases = Arc::new(self.get_releases(|r| self.is_release_with_asset(r, &asset.FileName))?);
+        }
+        let headers = self.get_headers("application/octet-stream");
+        let header_refs: Vec<(&str, &str)> = headers.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        let config = self.http_config.clone().unwrap_or_default();
+        for release in releases.iter() {
+            if let Ok(url) = self.get_asset_url_from_name(release, &asset.FileName) {
+                return download_git_release_entry(
+                    &url,
+                    &header_refs,
+                    &config,
+                    self.download_throttle.as_ref(),
+                    self.cancellation_token.as_ref(),
+                    local_file,
+                    &asset.get_download_hash_algorithms(),
+                    progress_sender,
+                );
+            }
+        }
+        Err(Error::Other(format!("Could not find asset '{}' in any Gitea release.", asset.FileName)))
+    }
+
+    fn set_default_http_config(&mut self, config: &HttpClientConfig) {
+        if self.http_config.is_none() {
+            self.http_config = Some(config.clone());
+        }
+    }
+
+    fn set_feed_cache(&mut self, cache: &FeedCache) {
+        self.feed_cache = Some(cache.clone());
+    }
+
+    fn set_download_throttle(&mut self, throttle: &DownloadThrottle) {
+        self.download_throttle = Some(throttle.clone());
+    }
+
+    fn set_cancellation_token(&mut self, token: &CancellationToken) {
+        self.cancellation_token = Some(token.clone());
+    }
+
+    fn set_feed_verifier(&mut self, verifier: &SignatureVerifier) -> bool {
+        self.feed_verifier = Some(verifier.clone());
+        true
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl AsyncUpdateSource for GiteaSource {
+    async fn get_release_feed_async(&self, channel: &str, _app: &Manifest, _staged_user_id: &str) -> Result<VelopackAssetFeed, Error> {
+        let releases_file_name = format!("releases.{}.json", channel);
+        let releases = self
+            .release_cache
+            .set(self.get_releases_async(|r| self.is_release_with_asset(r, &releases_file_name)).await?);
+        let headers = self.get_headers("application/octet-stream");
+        let header_refs: Vec<(&str, &str)> = headers.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        let config = self.http_config.clone().unwrap_or_default();
+        get_git_release_feed_async(
+            channel,
+            &header_refs,
+            &config,
+            self.feed_cache.as_ref(),
+            self.feed_verifier.as_ref(),
+            releases.len(),
+            |i, name| self.get_asset_url_from_name(&releases[i], name),
+        )
+        .await
+    }
+
+    async fn download_release_entry_async(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<(), Error> {
+        self.download_release_entry_with_hashes_async(asset, local_file, progress_sender).await?;
+        Ok(())
+    }
+
+    async fn download_release_entry_with_hashes_async(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<Option<FileHashes>, Error> {
+        let mut releases = self.release_cache.get();
+        if !releases.iter().any(|r| self.is_release_with_asset(r, &asset.FileName)) {
+            releases = Arc::new(self.get_releases_async(|r| self.is_release_with_asset(r, &asset.FileName)).await?);
+        }
         let headers = self.get_headers("application/octet-stream");
         let header_refs: Vec<(&str, &str)> = headers.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
-        for release in &releases {
+        let config = self.http_config.clone().unwrap_or_default();
+        for release in releases.iter() {
             if let Ok(url) = self.get_asset_url_from_name(release, &asset.FileName) {
-                return download_git_release_entry(&url, &header_refs, local_file, progress_sender);
+                return download_git_release_entry_async(
+                    &url,
+                    &header_refs,
+                    &config,
+                    self.download_throttle.as_ref(),
+                    self.cancellation_token.as_ref(),
+                    local_file,
+                    &asset.get_download_hash_algorithms(),
+                    progress_sender,
+                )
+                .await;
             }
         }
         Err(Error::Other(format!("Could not find asset '{}' in any Gitea release.", asset.FileName)))
     }
+
+    fn set_default_http_config(&mut self, config: &HttpClientConfig) {
+        UpdateSource::set_default_http_config(self, config);
+    }
+
+    fn set_feed_cache(&mut self, cache: &FeedCache) {
+        UpdateSource::set_feed_cache(self, cache);
+    }
+
+    fn set_download_throttle(&mut self, throttle: &DownloadThrottle) {
+        UpdateSource::set_download_throttle(self, throttle);
+    }
+
+    fn set_cancellation_token(&mut self, token: &CancellationToken) {
+        UpdateSource::set_cancellation_token(self, token);
+    }
+
+    fn set_feed_verifier(&mut self, verifier: &SignatureVerifier) -> bool {
+        UpdateSource::set_feed_verifier(self, verifier)
+    }
 }
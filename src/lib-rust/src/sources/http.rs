@@ -1,16 +1,39 @@
-use std::{path::Path, sync::mpsc::Sender};
+use std::{
+    path::Path,
+    sync::{mpsc::Sender, Arc},
+};
+
+use base64::Engine;
 
 use crate::bundle::Manifest;
 use crate::*;
 
+#[cfg(feature = "async")]
+use super::AsyncUpdateSource;
 use super::UpdateSource;
 
+type TokenProvider = Arc<dyn Fn() -> Result<String, Error> + Send + Sync>;
+
+#[derive(Clone)]
+enum HttpAuthorization {
+    Basic(String),
+    Bearer(String),
+    TokenProvider(TokenProvider),
+}
+
 #[derive(Clone)]
 /// Retrieves updates from a static file host or other web server.
 /// Will perform a request for '{baseUri}/RELEASES' to locate the available packages,
 /// and provides query parameters to specify the name of the requested package.
 pub struct HttpSource {
     url: String,
+    headers: Vec<(String, String)>,
+    authorization: Option<HttpAuthorization>,
+    http_config: Option<HttpClientConfig>,
+    feed_cache: Option<FeedCache>,
+    download_throttle: Option<DownloadThrottle>,
+    cancellation_token: Option<CancellationToken>,
+    feed_verifier: Option<SignatureVerifier>,
 }
 
 impl HttpSource {
@@ -18,12 +41,69 @@ impl HttpSource {
     pub fn new<S: AsRef<str>>(url: S) -> HttpSource {
         HttpSource {
             url: url.as_ref().to_owned(),
+            headers: Vec::new(),
+            authorization: None,
+            http_config: None,
+            feed_cache: None,
+            download_throttle: None,
+            cancellation_token: None,
+            feed_verifier: None,
         }
     }
-}
 
-impl UpdateSource for HttpSource {
-    fn get_release_feed(&self, channel: &str, app: &Manifest, staged_user_id: &str) -> Result<VelopackAssetFeed, Error> {
+    /// Use the specified HTTP client configuration (proxy, timeouts, etc.) for all requests made by this source.
+    pub fn with_http_config(mut self, config: HttpClientConfig) -> HttpSource {
+        self.http_config = Some(config);
+        self
+    }
+
+    /// Send an extra header with every request made by this source (e.g. an API key).
+    pub fn with_header<N: AsRef<str>, V: AsRef<str>>(mut self, name: N, value: V) -> HttpSource {
+        self.headers.push((name.as_ref().to_owned(), value.as_ref().to_owned()));
+        self
+    }
+
+    /// Authenticate every request with HTTP basic authentication. This replaces any other authentication method.
+    pub fn with_basic_auth<U: AsRef<str>, P: AsRef<str>>(mut self, username: U, password: P) -> HttpSource {
+        let credentials = format!("{}:{}", username.as_ref(), password.as_ref());
+        let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+        self.authorization = Some(HttpAuthorization::Basic(encoded));
+        self
+    }
+
+    /// Authenticate every request with a static bearer token. This replaces any other authentication method.
+    pub fn with_bearer_token<S: AsRef<str>>(mut self, token: S) -> HttpSource {
+        self.authorization = Some(HttpAuthorization::Bearer(token.as_ref().to_owned()));
+        self
+    }
+
+    /// Authenticate every request with a bearer token returned by the provided callback. The callback is invoked
+    /// before every request, so short-lived tokens can be refreshed between downloading the release feed and
+    /// downloading packages. This replaces any other authentication method.
+    pub fn with_token_provider<F>(mut self, provider: F) -> HttpSource
+    where
+        F: Fn() -> Result<String, Error> + Send + Sync + 'static,
+    {
+        self.authorization = Some(HttpAuthorization::TokenProvider(Arc::new(provider)));
+        self
+    }
+
+    fn get_request_headers(&self) -> Result<Vec<(String, String)>, Error> {
+        let mut headers = self.headers.clone();
+        let authorization = match &self.authorization {
+            Some(HttpAuthorization::Basic(credentials)) => Some(format!("Basic {}", credentials)),
+            Some(HttpAuthorization::Bearer(token)) => Some(format!("Bearer {}", token)),
+            Some(HttpAuthorization::TokenProvider(provider)) => Some(format!("Bearer {}", provider()?)),
+            None => None,
+        };
+        if let Some(authorization) = authorization {
+            headers.retain(|(name, _)| !name.eq_ignore_ascii_case("Authorization"));
+            headers.push(("Authorization".to_owned(), authorization));
+        }
+        Ok(headers)
+    }
+
+    fn get_releases_url(&self, channel: &str, app: &Manifest, staged_user_id: &str) -> Result<url::Url, Error> {
         let releases_name = format!("releases.{}.json", channel);
 
         let path = self.url.trim_end_matches('/').to_owned() + "/";
@@ -32,24 +112,171 @@ impl UpdateSource for HttpSource {
         releases_url.set_query(Some(
             format!("localVersion={}&id={}&stagingId={}", app.version, app.id, staged_user_id).as_str(),
         ));
+        Ok(releases_url)
+    }
+
+    fn get_signature_url(&self, channel: &str) -> Result<url::Url, Error> {
+        let signature_name = signing::get_signature_file_name(&format!("releases.{}.json", channel));
+        let path = self.url.trim_end_matches('/').to_owned() + "/";
+        let url = url::Url::parse(&path)?;
+        Ok(url.join(&signature_name)?)
+    }
+
+    fn get_asset_url(&self, asset: &VelopackAsset) -> Result<url::Url, Error> {
+        let path = self.url.trim_end_matches('/').to_owned() + "/";
+        let url = url::Url::parse(&path)?;
+        Ok(url.join(&asset.FileName)?)
+    }
+}
 
+impl UpdateSource for HttpSource {
+    fn get_release_feed(&self, channel: &str, app: &Manifest, staged_user_id: &str) -> Result<VelopackAssetFeed, Error> {
+        let releases_url = self.get_releases_url(channel, app, staged_user_id)?;
         info!("Downloading releases for channel {} from: {}", channel, releases_url);
-        let json = download::download_url_as_string(releases_url.as_str())?;
+        let headers = self.get_request_headers()?;
+        let header_refs: Vec<(&str, &str)> = headers.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        let config = self.http_config.clone().unwrap_or_default();
+        let json = download::download_url_as_string_cached(releases_url.as_str(), &header_refs, &config, self.feed_cache.as_ref())?;
+        if let Some(verifier) = &self.feed_verifier {
+            let signature_url = self.get_signature_url(channel)?;
+            let signature = download::download_url_as_string_cached(signature_url.as_str(), &header_refs, &config, self.feed_cache.as_ref())?;
+            verifier.verify_feed(&format!("releases.{}.json", channel), json.as_bytes(), &signature)?;
+        }
         let feed: VelopackAssetFeed = serde_json::from_str(&json)?;
         Ok(feed)
     }
 
     fn download_release_entry(&self, asset: &VelopackAsset, local_file: &Path, progress_sender: Option<Sender<i16>>) -> Result<(), Error> {
-        let path = self.url.trim_end_matches('/').to_owned() + "/";
-        let url = url::Url::parse(&path)?;
-        let asset_url = url.join(&asset.FileName)?;
+        self.download_release_entry_with_hashes(asset, local_file, progress_sender)?;
+        Ok(())
+    }
 
+    fn download_release_entry_with_hashes(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<Option<FileHashes>, Error> {
+        let asset_url = self.get_asset_url(asset)?;
         info!("About to download from URL '{}' to file '{:?}'", asset_url, local_file);
-        download::download_url_to_file(asset_url.as_str(), local_file, move |p| {
-            if let Some(progress_sender) = &progress_sender {
-                let _ = progress_sender.send(p);
-            }
-        })?;
+        let headers = self.get_request_headers()?;
+        let header_refs: Vec<(&str, &str)> = headers.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        let config = self.http_config.clone().unwrap_or_default();
+        download::download_url_to_file_with_hashes(
+            asset_url.as_str(),
+            local_file,
+            &header_refs,
+            &config,
+            self.download_throttle.as_ref(),
+            self.cancellation_token.as_ref(),
+            &asset.get_download_hash_algorithms(),
+            move |p| {
+                if let Some(progress_sender) = &progress_sender {
+                    let _ = progress_sender.send(p);
+                }
+            },
+        )
+    }
+
+    fn set_default_http_config(&mut self, config: &HttpClientConfig) {
+        if self.http_config.is_none() {
+            self.http_config = Some(config.clone());
+        }
+    }
+
+    fn set_feed_cache(&mut self, cache: &FeedCache) {
+        self.feed_cache = Some(cache.clone());
+    }
+
+    fn set_download_throttle(&mut self, throttle: &DownloadThrottle) {
+        self.download_throttle = Some(throttle.clone());
+    }
+
+    fn set_cancellation_token(&mut self, token: &CancellationToken) {
+        self.cancellation_token = Some(token.clone());
+    }
+
+    fn set_feed_verifier(&mut self, verifier: &SignatureVerifier) -> bool {
+        self.feed_verifier = Some(verifier.clone());
+        true
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl AsyncUpdateSource for HttpSource {
+    async fn get_release_feed_async(&self, channel: &str, app: &Manifest, staged_user_id: &str) -> Result<VelopackAssetFeed, Error> {
+        let releases_url = self.get_releases_url(channel, app, staged_user_id)?;
+        info!("Downloading releases for channel {} from: {}", channel, releases_url);
+        let headers = self.get_request_headers()?;
+        let header_refs: Vec<(&str, &str)> = headers.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        let config = self.http_config.clone().unwrap_or_default();
+        let json = download_async::download_url_as_string_cached(releases_url.as_str(), &header_refs, &config, self.feed_cache.as_ref()).await?;
+        if let Some(verifier) = &self.feed_verifier {
+            let signature_url = self.get_signature_url(channel)?;
+            let signature =
+                download_async::download_url_as_string_cached(signature_url.as_str(), &header_refs, &config, self.feed_cache.as_ref()).await?;
+            verifier.verify_feed(&format!("releases.{}.json", channel), json.as_bytes(), &signature)?;
+        }
+        let feed: VelopackAssetFeed = serde_json::from_str(&json)?;
+        Ok(feed)
+    }
+
+    async fn download_release_entry_async(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<(), Error> {
+        self.download_release_entry_with_hashes_async(asset, local_file, progress_sender).await?;
         Ok(())
     }
+
+    async fn download_release_entry_with_hashes_async(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<Option<FileHashes>, Error> {
+        let asset_url = self.get_asset_url(asset)?;
+        info!("About to download from URL '{}' to file '{:?}'", asset_url, local_file);
+        let headers = self.get_request_headers()?;
+        let header_refs: Vec<(&str, &str)> = headers.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        let config = self.http_config.clone().unwrap_or_default();
+        download_async::download_url_to_file_with_hashes(
+            asset_url.as_str(),
+            local_file,
+            &header_refs,
+            &config,
+            self.download_throttle.as_ref(),
+            self.cancellation_token.as_ref(),
+            &asset.get_download_hash_algorithms(),
+            move |p| {
+                if let Some(progress_sender) = &progress_sender {
+                    let _ = progress_sender.send(p);
+                }
+            },
+        )
+        .await
+    }
+
+    fn set_default_http_config(&mut self, config: &HttpClientConfig) {
+        UpdateSource::set_default_http_config(self, config);
+    }
+
+    fn set_feed_cache(&mut self, cache: &FeedCache) {
+        UpdateSource::set_feed_cache(self, cache);
+    }
+
+    fn set_download_throttle(&mut self, throttle: &DownloadThrottle) {
+        UpdateSource::set_download_throttle(self, throttle);
+    }
+
+    fn set_cancellation_token(&mut self, token: &CancellationToken) {
+        UpdateSource::set_cancellation_token(self, token);
+    }
+
+    fn set_feed_verifier(&mut self, verifier: &SignatureVerifier) -> bool {
+        UpdateSource::set_feed_verifier(self, verifier)
+    }
 }
@@ -1,24 +1,37 @@
 use std::{
     path::Path,
-    sync::{mpsc::Sender, Arc},
+    sync::{mpsc::Sender, Arc, Mutex},
+    time::Duration,
 };
 
+use serde::de::DeserializeOwned;
+
 use crate::bundle::Manifest;
 use crate::*;
 
+#[cfg(feature = "async")]
+mod adapters;
+mod fallback;
 mod file;
 mod flow;
 mod gitea;
 mod github;
 mod gitlab;
 mod http;
+mod s3;
 
+#[cfg(feature = "async")]
+pub(crate) use adapters::spawn_blocking;
+#[cfg(feature = "async")]
+pub use adapters::{AsyncSourceAdapter, BlockingSourceAdapter};
+pub use fallback::FallbackSource;
 pub use file::FileSource;
 pub use flow::VelopackFlowSource;
 pub use gitea::GiteaSource;
 pub use github::GithubSource;
 pub use gitlab::GitlabSource;
 pub use http::HttpSource;
+pub use s3::S3Source;
 
 /// Abstraction for finding and downloading updates from a package source / repository.
 /// An implementation may copy a file from a local repository, download from a web address,
@@ -29,6 +42,74 @@ pub trait UpdateSource: Send + Sync {
     fn get_release_feed(&self, channel: &str, app: &bundle::Manifest, staged_user_id: &str) -> Result<VelopackAssetFeed, Error>;
     /// Download the specified VelopackAsset to the provided local file path.
     fn download_release_entry(&self, asset: &VelopackAsset, local_file: &Path, progress_sender: Option<Sender<i16>>) -> Result<(), Error>;
+    /// Download the specified VelopackAsset to the provided local file path, hashing it as it is written with the
+    /// algorithms in `asset.Hashes`. Returns the digests, or None if the file was not hashed, in which case
+    /// UpdateManager reads it again to verify it. The default implementation calls `download_release_entry`.
+    fn download_release_entry_with_hashes(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<Option<FileHashes>, Error> {
+        self.download_release_entry(asset, local_file, progress_sender)?;
+        Ok(None)
+    }
+    /// Called by UpdateManager when UpdateOptions contains a HttpClientConfig. Sources which make network
+    /// requests should use this configuration, unless they have already been given one explicitly.
+    fn set_default_http_config(&mut self, _config: &HttpClientConfig) {}
+    /// Called by UpdateManager to provide a cache for release feeds in the app's packages directory. Sources
+    /// which download release feeds over HTTP should use it to avoid downloading feeds which have not changed.
+    fn set_feed_cache(&mut self, _cache: &FeedCache) {}
+    /// Called by UpdateManager to provide a throttle which limits the download rate of packages. Sources which
+    /// download packages over the network should pass it to the download functions.
+    fn set_download_throttle(&mut self, _throttle: &DownloadThrottle) {}
+    /// Called by UpdateManager to provide a token which is cancelled when the user cancels a download. Sources
+    /// which download packages should stop and return `Error::Cancelled` as soon as possible once it is cancelled.
+    fn set_cancellation_token(&mut self, _token: &CancellationToken) {}
+    /// Called by UpdateManager when UpdateOptions contains trusted public keys. Sources must then verify the
+    /// detached signature (eg. 'releases.{channel}.json.sig') of every release feed before it is parsed, and
+    /// refuse feeds which are unsigned or badly signed. Returns false if the source can not verify release feeds,
+    /// in which case UpdateManager will refuse to use it.
+    fn set_feed_verifier(&mut self, _verifier: &SignatureVerifier) -> bool {
+        false
+    }
+}
+
+/// The non-blocking equivalent of `UpdateSource`, for applications which use an async runtime such as tokio.
+/// A blocking `UpdateSource` can be used where an `AsyncUpdateSource` is required by wrapping it in an
+/// `AsyncSourceAdapter`, and vice versa with `BlockingSourceAdapter`.
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+pub trait AsyncUpdateSource: Send + Sync {
+    /// Retrieve the list of available remote releases from the package source. These releases
+    /// can subsequently be downloaded with download_release_entry_async.
+    async fn get_release_feed_async(&self, channel: &str, app: &bundle::Manifest, staged_user_id: &str) -> Result<VelopackAssetFeed, Error>;
+    /// Download the specified VelopackAsset to the provided local file path.
+    async fn download_release_entry_async(&self, asset: &VelopackAsset, local_file: &Path, progress_sender: Option<Sender<i16>>)
+        -> Result<(), Error>;
+    /// Download the specified VelopackAsset and return its digests, see `UpdateSource::download_release_entry_with_hashes`.
+    /// The default implementation calls `download_release_entry_async`.
+    async fn download_release_entry_with_hashes_async(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<Option<FileHashes>, Error> {
+        self.download_release_entry_async(asset, local_file, progress_sender).await?;
+        Ok(None)
+    }
+    /// Called by UpdateManager when UpdateOptions contains a HttpClientConfig, see `UpdateSource::set_default_http_config`.
+    fn set_default_http_config(&mut self, _config: &HttpClientConfig) {}
+    /// Called by UpdateManager to provide a cache for release feeds, see `UpdateSource::set_feed_cache`.
+    fn set_feed_cache(&mut self, _cache: &FeedCache) {}
+    /// Called by UpdateManager to provide a download throttle, see `UpdateSource::set_download_throttle`.
+    fn set_download_throttle(&mut self, _throttle: &DownloadThrottle) {}
+    /// Called by UpdateManager to provide a cancellation token, see `UpdateSource::set_cancellation_token`.
+    fn set_cancellation_token(&mut self, _token: &CancellationToken) {}
+    /// Called by UpdateManager to require signed release feeds, see `UpdateSource::set_feed_verifier`.
+    fn set_feed_verifier(&mut self, _verifier: &SignatureVerifier) -> bool {
+        false
+    }
 }
 
 /// A source that does not provide any update capability.
@@ -102,6 +183,53 @@ impl UpdateSource for AutoSource {
     fn download_release_entry(&self, asset: &VelopackAsset, local_file: &Path, progress_sender: Option<Sender<i16>>) -> Result<(), Error> {
         self.source.download_release_entry(asset, local_file, progress_sender)
     }
+
+    fn download_release_entry_with_hashes(
+        &self,
+        asset: &VelopackAsset,
+        local_file: &Path,
+        progress_sender: Option<Sender<i16>>,
+    ) -> Result<Option<FileHashes>, Error> {
+        self.source.download_release_entry_with_hashes(asset, local_file, progress_sender)
+    }
+
+    fn set_default_http_config(&mut self, config: &HttpClientConfig) {
+        match Arc::get_mut(&mut self.source) {
+            Some(source) => source.set_default_http_config(config),
+            None => warn!("AutoSource has been cloned, so the HttpClientConfig from UpdateOptions can not be applied."),
+        }
+    }
+
+    fn set_feed_cache(&mut self, cache: &FeedCache) {
+        match Arc::get_mut(&mut self.source) {
+            Some(source) => source.set_feed_cache(cache),
+            None => warn!("AutoSource has been cloned, so the feed cache can not be applied."),
+        }
+    }
+
+    fn set_download_throttle(&mut self, throttle: &DownloadThrottle) {
+        match Arc::get_mut(&mut self.source) {
+            Some(source) => source.set_download_throttle(throttle),
+            None => warn!("AutoSource has been cloned, so the download throttle can not be applied."),
+        }
+    }
+
+    fn set_cancellation_token(&mut self, token: &CancellationToken) {
+        match Arc::get_mut(&mut self.source) {
+            Some(source) => source.set_cancellation_token(token),
+            None => warn!("AutoSource has been cloned, so the cancellation token can not be applied."),
+        }
+    }
+
+    fn set_feed_verifier(&mut self, verifier: &SignatureVerifier) -> bool {
+        match Arc::get_mut(&mut self.source) {
+            Some(source) => source.set_feed_verifier(verifier),
+            None => {
+                warn!("AutoSource has been cloned, so the feed verifier can not be applied.");
+                false
+            }
+        }
+    }
 }
 
 // --- Shared helpers for git-based sources ---
@@ -109,12 +237,21 @@ impl UpdateSource for AutoSource {
 /// Given a list of releases (already fetched and filtered by the specific source),
 /// downloads the releases.{channel}.json asset from each release, parses it,
 /// and merges all assets into a single VelopackAssetFeed.
-fn get_git_release_feed<F>(channel: &str, headers: &[(&str, &str)], release_count: usize, get_asset_url: F) -> Result<VelopackAssetFeed, Error>
+fn get_git_release_feed<F>(
+    channel: &str,
+    headers: &[(&str, &str)],
+    config: &HttpClientConfig,
+    cache: Option<&FeedCache>,
+    verifier: Option<&SignatureVerifier>,
+    release_count: usize,
+    get_asset_url: F,
+) -> Result<VelopackAssetFeed, Error>
 where
     F: Fn(usize, &str) -> Result<String, Error>,
 {
     let releases_file_name = format!("releases.{}.json", channel);
-    let mut all_assets: Vec<VelopackAsset> = Vec::new();
+    let signature_file_name = signing::get_signature_file_name(&releases_file_name);
+    let mut merged_feed = VelopackAssetFeed::default();
 
     for i in 0..release_count {
         let asset_url = match get_asset_url(i, &releases_file_name) {
@@ -125,36 +262,332 @@ where
             }
         };
 
-        match download::download_url_as_string_with_headers(&asset_url, headers) {
-            Ok(json) => match serde_json::from_str::<VelopackAssetFeed>(&json) {
-                Ok(feed) => {
-                    all_assets.extend(feed.Assets);
-                }
-                Err(e) => {
-                    trace!("Failed to parse release feed from release {}: {}", i, e);
-                }
-            },
+        let json = download::download_url_as_string_cached(&asset_url, headers, config, cache);
+        if let (Ok(json), Some(verifier)) = (&json, verifier) {
+            let signature_url = get_asset_url(i, &signature_file_name);
+            let signature = signature_url.and_then(|url| download::download_url_as_string_cached(&url, headers, config, cache));
+            verify_git_release_feed(verifier, &releases_file_name, i, json, signature)?;
+        }
+        extend_git_release_feed(&mut merged_feed, i, json);
+    }
+
+    Ok(merged_feed)
+}
+
+/// The async equivalent of `get_git_release_feed`.
+#[cfg(feature = "async")]
+async fn get_git_release_feed_async<F>(
+    channel: &str,
+    headers: &[(&str, &str)],
+    config: &HttpClientConfig,
+    cache: Option<&FeedCache>,
+    verifier: Option<&SignatureVerifier>,
+    release_count: usize,
+    get_asset_url: F,
+) -> Result<VelopackAssetFeed, Error>
+where
+    F: Fn(usize, &str) -> Result<String, Error> + Send,
+{
+    let releases_file_name = format!("releases.{}.json", channel);
+    let signature_file_name = signing::get_signature_file_name(&releases_file_name);
+    let mut merged_feed = VelopackAssetFeed::default();
+
+    for i in 0..release_count {
+        let asset_url = match get_asset_url(i, &releases_file_name) {
+            Ok(url) => url,
             Err(e) => {
-                trace!("Failed to download release feed from release {}: {}", i, e);
+                trace!("Skipping release {}: {}", i, e);
+                continue;
             }
+        };
+
+        let json = download_async::download_url_as_string_cached(&asset_url, headers, config, cache).await;
+        if let (Ok(json), Some(verifier)) = (&json, verifier) {
+            let signature = match get_asset_url(i, &signature_file_name) {
+                Ok(url) => download_async::download_url_as_string_cached(&url, headers, config, cache).await,
+                Err(e) => Err(e),
+            };
+            verify_git_release_feed(verifier, &releases_file_name, i, json, signature)?;
         }
+        extend_git_release_feed(&mut merged_feed, i, json);
     }
 
-    Ok(VelopackAssetFeed { Assets: all_assets })
+    Ok(merged_feed)
+}
+
+/// Verifies the signature of the release feed from a single git release. A release with a feed which is not signed,
+/// or which is not signed by a trusted key, fails the whole release feed rather than being skipped.
+fn verify_git_release_feed(
+    verifier: &SignatureVerifier,
+    releases_file_name: &str,
+    index: usize,
+    json: &str,
+    signature: Result<String, Error>,
+) -> Result<(), Error> {
+    let signature = signature.map_err(|e| {
+        Error::SignatureInvalid(
+            format!("{} from release {}", releases_file_name, index),
+            format!("the signature could not be downloaded ({})", e),
+        )
+    })?;
+    verifier.verify_feed(releases_file_name, json.as_bytes(), &signature)
+}
+
+/// Adds the assets from the release feed of a single git release to the merged feed, if it could be downloaded and parsed.
+fn extend_git_release_feed(merged_feed: &mut VelopackAssetFeed, index: usize, json: Result<String, Error>) {
+    match json {
+        Ok(json) => match serde_json::from_str::<VelopackAssetFeed>(&json) {
+            Ok(feed) => {
+                merged_feed.merge(feed);
+            }
+            Err(e) => {
+                trace!("Failed to parse release feed from release {}: {}", index, e);
+            }
+        },
+        Err(e) => {
+            trace!("Failed to download release feed from release {}: {}", index, e);
+        }
+    }
+}
+
+/// The default maximum number of pages of releases to request from a git hosting API.
+const DEFAULT_MAX_RELEASE_PAGES: usize = 1;
+
+/// The default maximum time to wait for a rate limit to reset before giving up. Rate limited requests are not
+/// retried unless a source is configured to wait for them.
+const DEFAULT_MAX_RATE_LIMIT_WAIT: Duration = Duration::ZERO;
+
+/// The releases most recently listed by a git source while fetching the release feed, so that downloading one of
+/// their assets afterwards does not need to list the releases again.
+struct GitReleaseCache<T> {
+    releases: Arc<Mutex<Arc<Vec<T>>>>,
+}
+
+impl<T> Clone for GitReleaseCache<T> {
+    fn clone(&self) -> Self {
+        GitReleaseCache {
+            releases: Arc::clone(&self.releases),
+        }
+    }
+}
+
+impl<T> Default for GitReleaseCache<T> {
+    fn default() -> Self {
+        GitReleaseCache {
+            releases: Arc::new(Mutex::new(Arc::new(Vec::new()))),
+        }
+    }
+}
+
+impl<T> GitReleaseCache<T> {
+    fn get(&self) -> Arc<Vec<T>> {
+        Arc::clone(&self.releases.lock().unwrap())
+    }
+
+    fn set(&self, releases: Vec<T>) -> Arc<Vec<T>> {
+        let releases = Arc::new(releases);
+        *self.releases.lock().unwrap() = Arc::clone(&releases);
+        releases
+    }
+}
+
+/// Downloads a paginated list of releases from a git hosting API, following the `Link` response header for up to
+/// `max_pages` pages, but no further than the first page which contains a release matching `is_wanted`. Requests
+/// which are rate limited are retried with backoff, or after the delay requested by the server, unless that delay
+/// is longer than `max_rate_limit_wait`. If a page after the first one can not be downloaded, the releases from the
+/// previous pages are returned.
+fn get_git_releases<T: DeserializeOwned>(
+    first_page_url: &str,
+    headers: &[(&str, &str)],
+    config: &HttpClientConfig,
+    cache: Option<&FeedCache>,
+    max_pages: usize,
+    max_rate_limit_wait: Duration,
+    is_wanted: impl Fn(&T) -> bool,
+) -> Result<Vec<T>, Error> {
+    let mut releases = Vec::new();
+    let mut next_url = Some(first_page_url.to_string());
+    let mut page = 0;
+
+    while let Some(url) = next_url.take() {
+        if page >= max_pages {
+            info!("Stopped requesting releases after {} pages.", max_pages);
+            break;
+        }
+
+        let result = with_rate_limit_retry(max_rate_limit_wait, || {
+            download::download_page_as_string_cached(&url, headers, config, cache)
+        });
+        match result.and_then(|(json, next)| Ok((serde_json::from_str::<Vec<T>>(&json)?, next))) {
+            Ok((page_releases, next)) => {
+                // the newest matching release has been found, so older pages are not needed.
+                next_url = if page_releases.iter().any(&is_wanted) {
+                    None
+                } else {
+                    next
+                };
+                releases.extend(page_releases);
+            }
+            Err(e) if page > 0 => {
+                warn!(
+                    "Failed to download page {} of releases, continuing with {} releases: {}",
+                    page + 1,
+                    releases.len(),
+                    e
+                );
+                break;
+            }
+            Err(e) => return Err(e),
+        }
+        page += 1;
+    }
+
+    Ok(releases)
+}
+
+/// The async equivalent of `get_git_releases`.
+#[cfg(feature = "async")]
+async fn get_git_releases_async<T: DeserializeOwned>(
+    first_page_url: &str,
+    headers: &[(&str, &str)],
+    config: &HttpClientConfig,
+    cache: Option<&FeedCache>,
+    max_pages: usize,
+    max_rate_limit_wait: Duration,
+    is_wanted: impl Fn(&T) -> bool + Send,
+) -> Result<Vec<T>, Error> {
+    let mut releases = Vec::new();
+    let mut next_url = Some(first_page_url.to_string());
+    let mut page = 0;
+
+    while let Some(url) = next_url.take() {
+        if page >= max_pages {
+            info!("Stopped requesting releases after {} pages.", max_pages);
+            break;
+        }
+
+        let result = with_rate_limit_retry_async(max_rate_limit_wait, || {
+            download_async::download_page_as_string_cached(&url, headers, config, cache)
+        })
+        .await;
+        match result.and_then(|(json, next)| Ok((serde_json::from_str::<Vec<T>>(&json)?, next))) {
+            Ok((page_releases, next)) => {
+                // the newest matching release has been found, so older pages are not needed.
+                next_url = if page_releases.iter().any(&is_wanted) {
+                    None
+                } else {
+                    next
+                };
+                releases.extend(page_releases);
+            }
+            Err(e) if page > 0 => {
+                warn!(
+                    "Failed to download page {} of releases, continuing with {} releases: {}",
+                    page + 1,
+                    releases.len(),
+                    e
+                );
+                break;
+            }
+            Err(e) => return Err(e),
+        }
+        page += 1;
+    }
+
+    Ok(releases)
+}
+
+/// Runs the provided request, and if it fails because of a rate limit, waits and tries again a few times.
+fn with_rate_limit_retry<T, F>(max_wait: Duration, mut request: F) -> Result<T, Error>
+where
+    F: FnMut() -> Result<T, Error>,
+{
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        match request() {
+            Err(Error::RateLimited(url, delay)) => match get_rate_limit_retry_wait(&url, delay, attempt, max_wait) {
+                Some(wait) => std::thread::sleep(wait),
+                None => return Err(Error::RateLimited(url, delay)),
+            },
+            result => return result,
+        }
+    }
+}
+
+/// The async equivalent of `with_rate_limit_retry`.
+#[cfg(feature = "async")]
+async fn with_rate_limit_retry_async<T, F, Fut>(max_wait: Duration, mut request: F) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, Error>>,
+{
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        match request().await {
+            Err(Error::RateLimited(url, delay)) => match get_rate_limit_retry_wait(&url, delay, attempt, max_wait) {
+                Some(wait) => tokio::time::sleep(wait).await,
+                None => return Err(Error::RateLimited(url, delay)),
+            },
+            result => return result,
+        }
+    }
+}
+
+/// Returns how long to wait before retrying a rate limited request, or None if it should not be retried.
+fn get_rate_limit_retry_wait(url: &str, delay: Option<Duration>, attempt: u32, max_wait: Duration) -> Option<Duration> {
+    const MAX_ATTEMPTS: u32 = 3;
+    if attempt >= MAX_ATTEMPTS || max_wait.is_zero() {
+        return None;
+    }
+    let wait = delay.unwrap_or_else(|| Duration::from_secs(1 << attempt));
+    if wait > max_wait {
+        warn!("Rate limited while requesting '{}', and the limit resets in {:?}, giving up.", url, wait);
+        return None;
+    }
+    warn!("Rate limited while requesting '{}', trying again in {:?}.", url, wait);
+    Some(wait)
 }
 
 /// Downloads an asset file from a git release.
+#[allow(clippy::too_many_arguments)]
 fn download_git_release_entry(
     asset_url: &str,
     headers: &[(&str, &str)],
+    config: &HttpClientConfig,
+    throttle: Option<&DownloadThrottle>,
+    cancel: Option<&CancellationToken>,
     local_file: &Path,
+    algorithms: &[HashAlgorithm],
     progress_sender: Option<Sender<i16>>,
-) -> Result<(), Error> {
+) -> Result<Option<FileHashes>, Error> {
     info!("About to download from URL '{}' to file '{:?}'", asset_url, local_file);
-    download::download_url_to_file_with_headers(asset_url, local_file, headers, move |p| {
+    download::download_url_to_file_with_hashes(asset_url, local_file, headers, config, throttle, cancel, algorithms, move |p| {
         if let Some(progress_sender) = &progress_sender {
             let _ = progress_sender.send(p);
         }
-    })?;
-    Ok(())
+    })
+}
+
+/// The async equivalent of `download_git_release_entry`.
+#[cfg(feature = "async")]
+#[allow(clippy::too_many_arguments)]
+async fn download_git_release_entry_async(
+    asset_url: &str,
+    headers: &[(&str, &str)],
+    config: &HttpClientConfig,
+    throttle: Option<&DownloadThrottle>,
+    cancel: Option<&CancellationToken>,
+    local_file: &Path,
+    algorithms: &[HashAlgorithm],
+    progress_sender: Option<Sender<i16>>,
+) -> Result<Option<FileHashes>, Error> {
+    info!("About to download from URL '{}' to file '{:?}'", asset_url, local_file);
+    download_async::download_url_to_file_with_hashes(asset_url, local_file, headers, config, throttle, cancel, algorithms, move |p| {
+        if let Some(progress_sender) = &progress_sender {
+            let _ = progress_sender.send(p);
+        }
+    })
+    .await
 }
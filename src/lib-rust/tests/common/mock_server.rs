@@ -11,9 +11,16 @@ pub struct MockRoute {
     pub expected_headers: Vec<(String, String)>,
 }
 
+#[derive(Clone)]
+struct RegisteredRoute {
+    route: MockRoute,
+    response_headers: Vec<(String, String)>,
+    once: bool,
+}
+
 pub struct MockHttpServer {
     pub addr: SocketAddr,
-    routes: Arc<Mutex<Vec<MockRoute>>>,
+    routes: Arc<Mutex<Vec<RegisteredRoute>>>,
     _handle: thread::JoinHandle<()>,
 }
 
@@ -22,14 +29,13 @@ impl MockHttpServer {
     pub fn empty() -> Self {
         let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind test server");
         let addr = listener.local_addr().unwrap();
-        let routes: Arc<Mutex<Vec<MockRoute>>> = Arc::new(Mutex::new(Vec::new()));
+        let routes: Arc<Mutex<Vec<RegisteredRoute>>> = Arc::new(Mutex::new(Vec::new()));
         let routes_clone = Arc::clone(&routes);
 
         let handle = thread::spawn(move || {
             while let Ok((stream, _)) = listener.accept() {
                 let routes = Arc::clone(&routes_clone);
                 thread::spawn(move || {
-                    let routes = routes.lock().unwrap().clone();
                     handle_connection(stream, &routes);
                 });
             }
@@ -43,7 +49,27 @@ impl MockHttpServer {
     }
 
     pub fn add_route(&self, route: MockRoute) {
-        self.routes.lock().unwrap().push(route);
+        self.add_route_with_headers(route, &[]);
+    }
+
+    /// Add a route which also sends the specified headers with its response.
+    pub fn add_route_with_headers(&self, route: MockRoute, response_headers: &[(&str, &str)]) {
+        self.register(route, response_headers, false);
+    }
+
+    /// Add a route which responds to a single matching request, and is then removed. Routes are matched
+    /// in the order they were added, so this can be used to return a different response the first time.
+    pub fn add_route_once(&self, route: MockRoute, response_headers: &[(&str, &str)]) {
+        self.register(route, response_headers, true);
+    }
+
+    fn register(&self, route: MockRoute, response_headers: &[(&str, &str)], once: bool) {
+        let response_headers = response_headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        self.routes.lock().unwrap().push(RegisteredRoute {
+            route,
+            response_headers,
+            once,
+        });
     }
 
     pub fn url(&self) -> String {
@@ -51,7 +77,7 @@ impl MockHttpServer {
     }
 }
 
-fn handle_connection(mut stream: TcpStream, routes: &[MockRoute]) {
+fn handle_connection(mut stream: TcpStream, routes: &Mutex<Vec<RegisteredRoute>>) {
     let mut buf = [0u8; 4096];
     let n = match stream.read(&mut buf) {
         Ok(n) => n,
@@ -60,37 +86,48 @@ fn handle_connection(mut stream: TcpStream, routes: &[MockRoute]) {
     let request = String::from_utf8_lossy(&buf[..n]).to_string();
     let request_lower = request.to_lowercase();
 
-    for route in routes {
-        if request.contains(&route.path_contains) {
-            // Verify expected headers (case-insensitive)
-            for (name, value) in &route.expected_headers {
-                let expected = format!("{}: {}", name, value).to_lowercase();
-                assert!(
-                    request_lower.contains(&expected),
-                    "Expected header '{}:{}' not found in request:\n{}",
-                    name,
-                    value,
-                    request
-                );
-            }
+    let matched = {
+        let mut routes = routes.lock().unwrap();
+        let index = routes.iter().position(|r| request.contains(&r.route.path_contains));
+        match index {
+            Some(i) if routes[i].once => Some(routes.remove(i)),
+            Some(i) => Some(routes[i].clone()),
+            None => None,
+        }
+    };
 
-            let status_text = match route.response_code {
-                200 => "OK",
-                404 => "Not Found",
-                500 => "Internal Server Error",
-                _ => "Unknown",
-            };
-            let response = format!(
-                "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nContent-Type: application/octet-stream\r\nConnection: close\r\n\r\n",
-                route.response_code,
-                status_text,
-                route.response_body.len()
+    if let Some(RegisteredRoute { route, response_headers, .. }) = matched {
+        // Verify expected headers (case-insensitive)
+        for (name, value) in &route.expected_headers {
+            let expected = format!("{}: {}", name, value).to_lowercase();
+            assert!(
+                request_lower.contains(&expected),
+                "Expected header '{}:{}' not found in request:\n{}",
+                name,
+                value,
+                request
             );
-            let _ = stream.write_all(response.as_bytes());
-            let _ = stream.write_all(&route.response_body);
-            let _ = stream.flush();
-            return;
         }
+
+        let status_text = match route.response_code {
+            200 => "OK",
+            404 => "Not Found",
+            429 => "Too Many Requests",
+            500 => "Internal Server Error",
+            _ => "Unknown",
+        };
+        let extra_headers: String = response_headers.iter().map(|(k, v)| format!("{}: {}\r\n", k, v)).collect();
+        let response = format!(
+            "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nContent-Type: application/octet-stream\r\n{}Connection: close\r\n\r\n",
+            route.response_code,
+            status_text,
+            route.response_body.len(),
+            extra_headers
+        );
+        let _ = stream.write_all(response.as_bytes());
+        let _ = stream.write_all(&route.response_body);
+        let _ = stream.flush();
+        return;
     }
 
     // 404 fallback
@@ -5,6 +5,7 @@ pub mod mock_server;
 pub use mock_server::{MockHttpServer, MockRoute};
 
 use semver::Version;
+use std::collections::HashMap;
 use velopack::bundle::Manifest;
 use velopack::VelopackAsset;
 
@@ -44,8 +45,35 @@ pub fn sample_asset() -> VelopackAsset {
         FileName: "TestApp-2.0.0-full.nupkg".to_string(),
         SHA1: "abc123".to_string(),
         SHA256: "def456".to_string(),
+        Hashes: HashMap::from([("sha512".to_string(), "789abc".to_string())]),
         Size: 1048576,
         NotesMarkdown: "# v2".to_string(),
         NotesHtml: "<h1>v2</h1>".to_string(),
+        Arch: Some("x64".to_string()),
+        OS: Some("win".to_string()),
+        MinOSVersion: None,
+        RolloutPercentage: 0,
+        IsMandatory: false,
+        Retracted: false,
+        BaseVersion: None,
     }
 }
+
+#[allow(dead_code)]
+pub fn feed_key_pair(seed: u8) -> ring::signature::Ed25519KeyPair {
+    ring::signature::Ed25519KeyPair::from_seed_unchecked(&[seed; 32]).unwrap()
+}
+
+#[allow(dead_code)]
+pub fn feed_public_key(key_pair: &ring::signature::Ed25519KeyPair) -> String {
+    use base64::Engine;
+    use ring::signature::KeyPair;
+    base64::engine::general_purpose::STANDARD.encode(key_pair.public_key().as_ref())
+}
+
+#[allow(dead_code)]
+pub fn sign_feed(key_pair: &ring::signature::Ed25519KeyPair, file_name: &str, data: &[u8]) -> String {
+    use base64::Engine;
+    let payload = velopack::signing::get_feed_signature_payload(file_name, data);
+    base64::engine::general_purpose::STANDARD.encode(key_pair.sign(&payload).as_ref())
+}
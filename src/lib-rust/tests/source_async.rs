@@ -0,0 +1,129 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use common::*;
+use std::sync::mpsc;
+use velopack::sources::{AsyncSourceAdapter, AsyncUpdateSource, BlockingSourceAdapter, FileSource, GithubSource, HttpSource, UpdateSource};
+
+fn feed_server() -> MockHttpServer {
+    let server = MockHttpServer::empty();
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![],
+    });
+    server
+}
+
+#[tokio::test]
+async fn http_feed_success() {
+    let server = feed_server();
+    let source = HttpSource::new(server.url()).with_header("X-Api-Key", "secret");
+    let feed = source.get_release_feed_async("stable", &test_manifest(), "").await.unwrap();
+    assert_eq!(feed.Assets.len(), 1);
+    assert_eq!(feed.Assets[0].PackageId, "TestApp");
+}
+
+#[tokio::test]
+async fn http_feed_server_error() {
+    let server = MockHttpServer::empty();
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json".into(),
+        response_code: 500,
+        response_body: b"Internal Server Error".to_vec(),
+        expected_headers: vec![],
+    });
+
+    let source = HttpSource::new(server.url());
+    assert!(source.get_release_feed_async("stable", &test_manifest(), "").await.is_err());
+}
+
+#[tokio::test]
+async fn http_download_reports_progress() {
+    let body = vec![0xAB; 64 * 1024];
+    let server = MockHttpServer::empty();
+    server.add_route(MockRoute {
+        path_contains: "TestApp-2.0.0-full.nupkg".into(),
+        response_code: 200,
+        response_body: body.clone(),
+        expected_headers: vec![("Authorization".into(), "Bearer token".into())],
+    });
+
+    let source = HttpSource::new(server.url()).with_bearer_token("token");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("downloaded.nupkg");
+    let (tx, rx) = mpsc::channel();
+    source.download_release_entry_async(&sample_asset(), &dest, Some(tx)).await.unwrap();
+
+    assert_eq!(std::fs::read(&dest).unwrap(), body);
+    let progress: Vec<i16> = rx.try_iter().collect();
+    assert_eq!(progress.last(), Some(&100));
+}
+
+#[tokio::test]
+async fn file_feed_and_download() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("releases.stable.json"), sample_feed_json()).unwrap();
+    std::fs::write(dir.path().join("TestApp-2.0.0-full.nupkg"), b"package").unwrap();
+
+    let source = FileSource::new(dir.path());
+    let feed = source.get_release_feed_async("stable", &test_manifest(), "").await.unwrap();
+    assert_eq!(feed.Assets.len(), 1);
+
+    let dest = dir.path().join("downloaded.nupkg");
+    source.download_release_entry_async(&sample_asset(), &dest, None).await.unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"package");
+}
+
+#[tokio::test]
+async fn github_feed_success() {
+    let server = MockHttpServer::empty();
+    let releases_json = format!(
+        r#"[{{"name":"v2.0.0","prerelease":false,"published_at":"2024-01-02T00:00:00Z","assets":[
+            {{"url":null,"browser_download_url":"{}/download/v2.0.0/releases.stable.json","name":"releases.stable.json"}}]}}]"#,
+        server.url()
+    );
+    server.add_route(MockRoute {
+        path_contains: "/api/v3/repos/testuser/testrepo/releases?".into(),
+        response_code: 200,
+        response_body: releases_json.into_bytes(),
+        expected_headers: vec![],
+    });
+    server.add_route(MockRoute {
+        path_contains: "/download/v2.0.0/releases.stable.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![],
+    });
+
+    let source = GithubSource::new(&format!("{}/testuser/testrepo", server.url()), None, false);
+    let feed = source.get_release_feed_async("stable", &test_manifest(), "").await.unwrap();
+    assert_eq!(feed.Assets.len(), 1);
+    assert_eq!(feed.Assets[0].Version, "2.0.0");
+}
+
+#[tokio::test]
+async fn blocking_source_can_be_used_as_async() {
+    let server = feed_server();
+    let source = AsyncSourceAdapter::new(HttpSource::new(server.url()));
+    let feed = source.get_release_feed_async("stable", &test_manifest(), "").await.unwrap();
+    assert_eq!(feed.Assets.len(), 1);
+}
+
+#[test]
+fn async_source_can_be_used_as_blocking() {
+    let server = feed_server();
+    let source = BlockingSourceAdapter::new(HttpSource::new(server.url()));
+    let feed = source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    assert_eq!(feed.Assets.len(), 1);
+}
+
+#[tokio::test]
+async fn async_source_can_be_used_as_blocking_inside_runtime() {
+    let server = feed_server();
+    let source = BlockingSourceAdapter::new(HttpSource::new(server.url()));
+    let feed = source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    assert_eq!(feed.Assets.len(), 1);
+}
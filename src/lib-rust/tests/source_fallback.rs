@@ -0,0 +1,189 @@
+mod common;
+
+use common::*;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc::Sender, Arc};
+use std::time::Duration;
+use velopack::bundle::Manifest;
+use velopack::sources::{FallbackSource, HttpSource, UpdateSource};
+use velopack::{Error, VelopackAsset, VelopackAssetFeed};
+
+/// A source that always fails, and counts how many times it was called.
+struct FailingSource {
+    calls: Arc<AtomicUsize>,
+}
+
+impl UpdateSource for FailingSource {
+    fn get_release_feed(&self, _channel: &str, _app: &Manifest, _staged_user_id: &str) -> Result<VelopackAssetFeed, Error> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Err(Error::Other("mirror is down".into()))
+    }
+    fn download_release_entry(&self, _asset: &VelopackAsset, _local_file: &Path, _progress: Option<Sender<i16>>) -> Result<(), Error> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Err(Error::Other("mirror is down".into()))
+    }
+}
+
+fn feed_server() -> MockHttpServer {
+    let server = MockHttpServer::empty();
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![],
+    });
+    server
+}
+
+#[test]
+fn feed_falls_back_to_mirror() {
+    let primary = MockHttpServer::empty();
+    primary.add_route(MockRoute {
+        path_contains: "releases.stable.json".into(),
+        response_code: 500,
+        response_body: b"Internal Server Error".to_vec(),
+        expected_headers: vec![],
+    });
+    let mirror = feed_server();
+
+    let source = FallbackSource::new()
+        .with_source("primary", HttpSource::new(primary.url()))
+        .with_source("mirror", HttpSource::new(mirror.url()));
+    let feed = source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    assert_eq!(feed.Assets.len(), 1);
+    assert_eq!(feed.Assets[0].PackageId, "TestApp");
+}
+
+#[test]
+fn download_falls_back_to_mirror() {
+    let body = vec![0xDE, 0xAD, 0xBE, 0xEF];
+    let primary = MockHttpServer::empty();
+    let mirror = MockHttpServer::empty();
+    mirror.add_route(MockRoute {
+        path_contains: "TestApp-2.0.0-full.nupkg".into(),
+        response_code: 200,
+        response_body: body.clone(),
+        expected_headers: vec![],
+    });
+
+    let source = FallbackSource::new()
+        .with_source("primary", HttpSource::new(primary.url()))
+        .with_source("mirror", HttpSource::new(mirror.url()));
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("downloaded.nupkg");
+    source.download_release_entry(&sample_asset(), &dest, None).unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), body);
+}
+
+/// A source which writes the package itself, and does not hash it.
+struct WritingSource {
+    body: Vec<u8>,
+}
+
+impl UpdateSource for WritingSource {
+    fn get_release_feed(&self, _channel: &str, _app: &Manifest, _staged_user_id: &str) -> Result<VelopackAssetFeed, Error> {
+        Err(Error::NotSupported("no feed".into()))
+    }
+    fn download_release_entry(&self, _asset: &VelopackAsset, local_file: &Path, _progress: Option<Sender<i16>>) -> Result<(), Error> {
+        std::fs::write(local_file, &self.body)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn download_with_hashes_falls_back_to_download_release_entry() {
+    let body = vec![0xDE, 0xAD, 0xBE, 0xEF];
+    let source = FallbackSource::new()
+        .with_source(
+            "primary",
+            FailingSource {
+                calls: Arc::new(AtomicUsize::new(0)),
+            },
+        )
+        .with_source("mirror", WritingSource { body: body.clone() });
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("downloaded.nupkg");
+    let hashes = source.download_release_entry_with_hashes(&sample_asset(), &dest, None).unwrap();
+    assert!(
+        hashes.is_none(),
+        "a source which does not hash downloads should leave it to UpdateManager"
+    );
+    assert_eq!(std::fs::read(&dest).unwrap(), body);
+}
+
+#[test]
+fn failed_source_is_skipped_during_cooldown() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mirror = feed_server();
+    let source = FallbackSource::new()
+        .with_source("primary", FailingSource { calls: calls.clone() })
+        .with_source("mirror", HttpSource::new(mirror.url()))
+        .with_cooldown(Duration::from_secs(60));
+
+    source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn failed_source_is_retried_after_cooldown() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mirror = feed_server();
+    let source = FallbackSource::new()
+        .with_source("primary", FailingSource { calls: calls.clone() })
+        .with_source("mirror", HttpSource::new(mirror.url()))
+        .with_cooldown(Duration::ZERO);
+
+    source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn all_sources_failing_returns_error() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let source = FallbackSource::new()
+        .with_source("primary", FailingSource { calls: calls.clone() })
+        .with_source("mirror", FailingSource { calls: calls.clone() })
+        .with_cooldown(Duration::from_secs(60));
+
+    assert!(source.get_release_feed("stable", &test_manifest(), "").is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    // every source is cooling down, so they should all be tried again rather than failing immediately
+    assert!(source.get_release_feed("stable", &test_manifest(), "").is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+fn empty_source_list_returns_error() {
+    let source = FallbackSource::new();
+    assert!(source.get_release_feed("stable", &test_manifest(), "").is_err());
+}
+
+/// A source that always reports that the download was cancelled.
+struct CancelledSource;
+
+impl UpdateSource for CancelledSource {
+    fn get_release_feed(&self, _channel: &str, _app: &Manifest, _staged_user_id: &str) -> Result<VelopackAssetFeed, Error> {
+        Err(Error::Cancelled)
+    }
+    fn download_release_entry(&self, _asset: &VelopackAsset, _local_file: &Path, _progress: Option<Sender<i16>>) -> Result<(), Error> {
+        Err(Error::Cancelled)
+    }
+}
+
+#[test]
+fn cancelled_download_does_not_fall_back() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let source = FallbackSource::new()
+        .with_source("primary", CancelledSource)
+        .with_source("mirror", FailingSource { calls: calls.clone() });
+
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("downloaded.nupkg");
+    let result = source.download_release_entry(&sample_asset(), &dest, None);
+    assert!(matches!(result, Err(Error::Cancelled)));
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+}
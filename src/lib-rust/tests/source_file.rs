@@ -3,6 +3,7 @@ mod common;
 use common::*;
 use std::sync::mpsc;
 use velopack::sources::{FileSource, UpdateSource};
+use velopack::{HashAlgorithm, SignatureVerifier};
 
 #[test]
 fn feed_success() {
@@ -51,10 +52,20 @@ fn download_success() {
     let asset = sample_asset();
 
     let dest = dir.path().join("downloaded.nupkg");
-    source.download_release_entry(&asset, &dest, None).unwrap();
+    let hashes = source.download_release_entry_with_hashes(&asset, &dest, None).unwrap();
 
     let downloaded = std::fs::read(&dest).unwrap();
     assert_eq!(downloaded, content);
+
+    // the copy is hashed as it is written, but only with the algorithm of the asset's strongest checksum
+    let hashes = hashes.expect("FileSource should hash the package while copying it");
+    assert_eq!(
+        hashes.get(HashAlgorithm::Sha512),
+        Some("039DF814184B722AECFCC8A49E29FB376215F12359B2862BB6E0CA43B8A8C8E5315777B0D189604FBFD2A2998B78F1E5500A0AC271DC0D0A29FC019A7247A132")
+    );
+    assert_eq!(hashes.get(HashAlgorithm::Sha1), None);
+    assert_eq!(hashes.get(HashAlgorithm::Sha256), None);
+    assert_eq!(hashes.get(HashAlgorithm::Blake3), None);
 }
 
 #[test]
@@ -85,3 +96,37 @@ fn download_missing_file() {
     let result = source.download_release_entry(&asset, &dest, None);
     assert!(result.is_err());
 }
+
+#[test]
+fn signed_feed_is_verified() {
+    let key = feed_key_pair(1);
+    let dir = tempfile::tempdir().unwrap();
+    let json = sample_feed_json();
+    std::fs::write(dir.path().join("releases.stable.json"), &json).unwrap();
+
+    let mut source = FileSource::new(dir.path());
+    assert!(source.set_feed_verifier(&SignatureVerifier::new(&[feed_public_key(&key)]).unwrap()));
+
+    // missing signature file
+    assert!(source.get_release_feed("stable", &test_manifest(), "").is_err());
+
+    let signature = sign_feed(&key, "releases.stable.json", json.as_bytes());
+    std::fs::write(dir.path().join("releases.stable.json.sig"), &signature).unwrap();
+    let feed = source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    assert_eq!(feed.Assets.len(), 1);
+
+    // a feed signed for another channel
+    std::fs::write(
+        dir.path().join("releases.stable.json.sig"),
+        sign_feed(&key, "releases.beta.json", json.as_bytes()),
+    )
+    .unwrap();
+    let result = source.get_release_feed("stable", &test_manifest(), "");
+    assert!(matches!(result, Err(velopack::Error::SignatureInvalid(..))));
+
+    // feed modified after signing
+    std::fs::write(dir.path().join("releases.stable.json.sig"), &signature).unwrap();
+    std::fs::write(dir.path().join("releases.stable.json"), json.replace("2.0.0", "9.0.0")).unwrap();
+    let result = source.get_release_feed("stable", &test_manifest(), "");
+    assert!(matches!(result, Err(velopack::Error::SignatureInvalid(..))));
+}
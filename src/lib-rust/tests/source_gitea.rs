@@ -182,3 +182,56 @@ fn missing_browser_download_url() {
     let result = source.download_release_entry(&asset, &dest, None);
     assert!(result.is_err());
 }
+
+#[test]
+fn follows_link_pagination() {
+    let server = MockHttpServer::empty();
+    let older_releases_json = format!(
+        r#"[{{"name":"v1.0.0","prerelease":false,"published_at":"2023-01-01T00:00:00Z","assets":[{{"browser_download_url":"{}/testuser/testrepo/releases/download/v1.0.0/releases.stable.json","name":"releases.stable.json"}}]}}]"#,
+        server.url()
+    );
+    let newer_releases_json = format!(
+        r#"[{{"name":"v2.1.0","prerelease":false,"published_at":"2024-02-01T00:00:00Z","assets":[{{"browser_download_url":"{}/testuser/testrepo/releases/download/v2.1.0/TestApp-2.1.0-full.nupkg","name":"TestApp-2.1.0-full.nupkg"}}]}}]"#,
+        server.url()
+    );
+    let next_link = format!(
+        "<{}/api/v1/repos/testuser/testrepo/releases?limit=10&draft=false&page=2>; rel=\"next\"",
+        server.url()
+    );
+
+    server.add_route(MockRoute {
+        path_contains: "/api/v1/repos/testuser/testrepo/releases?limit=10&draft=false&page=2".into(),
+        response_code: 200,
+        response_body: older_releases_json.into_bytes(),
+        expected_headers: vec![],
+    });
+    server.add_route_with_headers(
+        MockRoute {
+            path_contains: "/api/v1/repos/testuser/testrepo/releases?".into(),
+            response_code: 200,
+            response_body: newer_releases_json.into_bytes(),
+            expected_headers: vec![],
+        },
+        &[("Link", &next_link)],
+    );
+    server.add_route(MockRoute {
+        path_contains: "v1.0.0/releases.stable.json".into(),
+        response_code: 200,
+        response_body: serde_json::json!({"Assets":[{"PackageId":"TestApp","Version":"1.0.0","Type":"Full","FileName":"TestApp-1.0.0-full.nupkg"}]})
+            .to_string()
+            .into_bytes(),
+        expected_headers: vec![],
+    });
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![],
+    });
+
+    // the first page has no release feed, so the next page is requested
+    let source = GiteaSource::new(&format!("{}/testuser/testrepo", server.url()), None, false).with_max_pages(2);
+    let feed = source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    let versions: Vec<&str> = feed.Assets.iter().map(|a| a.Version.as_str()).collect();
+    assert_eq!(versions, vec!["1.0.0"]);
+}
@@ -2,7 +2,9 @@ mod common;
 
 use common::*;
 use std::sync::mpsc;
+use std::time::Duration;
 use velopack::sources::{GithubSource, UpdateSource};
+use velopack::{Error, SignatureVerifier};
 
 fn github_releases_json(server_url: &str) -> String {
     format!(
@@ -135,6 +137,68 @@ fn includes_prereleases() {
     assert!(feed.Assets.len() >= 2);
 }
 
+fn github_signed_releases_json(server_url: &str) -> String {
+    let signature_asset = format!(
+        r#""name": "releases.stable.json"
+      }},
+      {{
+        "url": "{server_url}/api/v3/repos/testuser/testrepo/releases/assets/4",
+        "browser_download_url": "{server_url}/testuser/testrepo/releases/download/v2.0.0/releases.stable.json.sig",
+        "name": "releases.stable.json.sig"
+      }},"#
+    );
+    github_releases_json(server_url).replacen(
+        r#""name": "releases.stable.json"
+      },"#,
+        &signature_asset,
+        1,
+    )
+}
+
+fn get_signed_release_feed(releases_json: fn(&str) -> String, signature: String) -> Result<velopack::VelopackAssetFeed, Error> {
+    let key = feed_key_pair(1);
+    let server = MockHttpServer::empty();
+    server.add_route(MockRoute {
+        path_contains: "/api/v3/repos/testuser/testrepo/releases?".into(),
+        response_code: 200,
+        response_body: releases_json(&server.url()).into_bytes(),
+        expected_headers: vec![],
+    });
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json.sig".into(),
+        response_code: 200,
+        response_body: signature.into_bytes(),
+        expected_headers: vec![],
+    });
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![],
+    });
+
+    let mut source = GithubSource::new(&format!("{}/testuser/testrepo", server.url()), None, false);
+    assert!(source.set_feed_verifier(&SignatureVerifier::new(&[feed_public_key(&key)]).unwrap()));
+    source.get_release_feed("stable", &test_manifest(), "")
+}
+
+#[test]
+fn verifies_release_feed_signatures() {
+    let key = feed_key_pair(1);
+    let signature = sign_feed(&key, "releases.stable.json", sample_feed_json().as_bytes());
+    let feed = get_signed_release_feed(github_signed_releases_json, signature.clone()).unwrap();
+    assert_eq!(feed.Assets.len(), 1);
+
+    // a release which does not have a signature fails the feed, instead of being skipped
+    let result = get_signed_release_feed(github_releases_json, signature);
+    assert!(matches!(result, Err(Error::SignatureInvalid(..))));
+
+    // a feed signed for another channel
+    let signature = sign_feed(&key, "releases.beta.json", sample_feed_json().as_bytes());
+    let result = get_signed_release_feed(github_signed_releases_json, signature);
+    assert!(matches!(result, Err(Error::SignatureInvalid(..))));
+}
+
 #[test]
 fn uses_browser_download_url_without_token() {
     let server = MockHttpServer::empty();
@@ -246,3 +310,219 @@ fn no_matching_asset() {
     let err = format!("{}", result.unwrap_err());
     assert!(err.contains("Could not find asset"), "Unexpected error: {}", err);
 }
+
+fn github_older_releases_json(server_url: &str) -> String {
+    format!(
+        r#"[
+  {{
+    "name": "v1.0.0",
+    "prerelease": false,
+    "published_at": "2023-01-01T00:00:00Z",
+    "assets": [
+      {{
+        "url": "{server_url}/api/v3/repos/testuser/testrepo/releases/assets/4",
+        "browser_download_url": "{server_url}/testuser/testrepo/releases/download/v1.0.0/releases.stable.json",
+        "name": "releases.stable.json"
+      }}
+    ]
+  }}
+]"#
+    )
+}
+
+fn github_older_feed_json() -> String {
+    serde_json::json!({"Assets":[{"PackageId":"TestApp","Version":"1.0.0","Type":"Full","FileName":"TestApp-1.0.0-full.nupkg","SHA1":"","SHA256":"","Size":0}]}).to_string()
+}
+
+fn github_releases_without_feed_json(server_url: &str) -> String {
+    format!(
+        r#"[
+  {{
+    "name": "v2.1.0",
+    "prerelease": false,
+    "published_at": "2024-02-01T00:00:00Z",
+    "assets": [
+      {{
+        "url": "{server_url}/api/v3/repos/testuser/testrepo/releases/assets/5",
+        "browser_download_url": "{server_url}/testuser/testrepo/releases/download/v2.1.0/TestApp-2.1.0-full.nupkg",
+        "name": "TestApp-2.1.0-full.nupkg"
+      }}
+    ]
+  }}
+]"#
+    )
+}
+
+fn paginated_server(first_page_json: fn(&str) -> String) -> MockHttpServer {
+    let server = MockHttpServer::empty();
+    let next_link = format!(
+        "<{}/api/v3/repos/testuser/testrepo/releases?per_page=10&page=2>; rel=\"next\"",
+        server.url()
+    );
+    server.add_route(MockRoute {
+        path_contains: "/api/v3/repos/testuser/testrepo/releases?per_page=10&page=2".into(),
+        response_code: 200,
+        response_body: github_older_releases_json(&server.url()).into_bytes(),
+        expected_headers: vec![],
+    });
+    server.add_route_with_headers(
+        MockRoute {
+            path_contains: "/api/v3/repos/testuser/testrepo/releases?".into(),
+            response_code: 200,
+            response_body: first_page_json(&server.url()).into_bytes(),
+            expected_headers: vec![],
+        },
+        &[("Link", &next_link)],
+    );
+    server.add_route(MockRoute {
+        path_contains: "v1.0.0/releases.stable.json".into(),
+        response_code: 200,
+        response_body: github_older_feed_json().into_bytes(),
+        expected_headers: vec![],
+    });
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![],
+    });
+    server
+}
+
+#[test]
+fn follows_link_pagination_until_feed_is_found() {
+    let server = paginated_server(github_releases_without_feed_json);
+    let source = GithubSource::new(&format!("{}/testuser/testrepo", server.url()), None, false).with_max_pages(5);
+    let feed = source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    let versions: Vec<&str> = feed.Assets.iter().map(|a| a.Version.as_str()).collect();
+    assert_eq!(versions, vec!["1.0.0"]);
+}
+
+#[test]
+fn pagination_stops_once_feed_is_found() {
+    let server = paginated_server(github_releases_json);
+    let source = GithubSource::new(&format!("{}/testuser/testrepo", server.url()), None, false).with_max_pages(5);
+    let feed = source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    let versions: Vec<&str> = feed.Assets.iter().map(|a| a.Version.as_str()).collect();
+    assert_eq!(versions, vec!["2.0.0"]);
+}
+
+#[test]
+fn pagination_requests_one_page_by_default() {
+    let server = paginated_server(github_releases_without_feed_json);
+    let source = GithubSource::new(&format!("{}/testuser/testrepo", server.url()), None, false);
+    let feed = source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    assert!(feed.Assets.is_empty());
+}
+
+#[test]
+fn download_uses_releases_listed_by_feed() {
+    let body = vec![0xCA, 0xFE, 0xBA, 0xBE];
+    let server = MockHttpServer::empty();
+    // the releases can only be listed once, so the download must use the releases found while fetching the feed
+    server.add_route_once(
+        MockRoute {
+            path_contains: "/api/v3/repos/testuser/testrepo/releases?".into(),
+            response_code: 200,
+            response_body: github_releases_json(&server.url()).into_bytes(),
+            expected_headers: vec![],
+        },
+        &[],
+    );
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![],
+    });
+    server.add_route(MockRoute {
+        path_contains: "TestApp-2.0.0-full.nupkg".into(),
+        response_code: 200,
+        response_body: body.clone(),
+        expected_headers: vec![],
+    });
+
+    let source = GithubSource::new(&format!("{}/testuser/testrepo", server.url()), None, false);
+    let feed = source.get_release_feed("stable", &test_manifest(), "").unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("downloaded.nupkg");
+    source.download_release_entry(&feed.Assets[0], &dest, None).unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), body);
+}
+
+#[test]
+fn rate_limited_request_is_not_retried_by_default() {
+    let server = MockHttpServer::empty();
+    server.add_route_once(
+        MockRoute {
+            path_contains: "/api/v3/repos/testuser/testrepo/releases?".into(),
+            response_code: 429,
+            response_body: b"Too Many Requests".to_vec(),
+            expected_headers: vec![],
+        },
+        &[("Retry-After", "1")],
+    );
+    server.add_route(MockRoute {
+        path_contains: "/api/v3/repos/testuser/testrepo/releases?".into(),
+        response_code: 200,
+        response_body: github_releases_json(&server.url()).into_bytes(),
+        expected_headers: vec![],
+    });
+
+    let source = GithubSource::new(&format!("{}/testuser/testrepo", server.url()), None, false);
+    let result = source.get_release_feed("stable", &test_manifest(), "");
+    assert!(matches!(result, Err(Error::RateLimited(_, Some(d))) if d == Duration::from_secs(1)));
+}
+
+#[test]
+fn retries_after_rate_limit() {
+    let server = MockHttpServer::empty();
+    server.add_route_once(
+        MockRoute {
+            path_contains: "/api/v3/repos/testuser/testrepo/releases?".into(),
+            response_code: 429,
+            response_body: b"Too Many Requests".to_vec(),
+            expected_headers: vec![],
+        },
+        &[("Retry-After", "1")],
+    );
+    server.add_route(MockRoute {
+        path_contains: "/api/v3/repos/testuser/testrepo/releases?".into(),
+        response_code: 200,
+        response_body: github_releases_json(&server.url()).into_bytes(),
+        expected_headers: vec![],
+    });
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![],
+    });
+
+    let source = GithubSource::new(&format!("{}/testuser/testrepo", server.url()), None, false).with_max_rate_limit_wait(Duration::from_secs(5));
+    let feed = source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    assert_eq!(feed.Assets.len(), 1);
+}
+
+#[test]
+fn exhausted_rate_limit_returns_rate_limited_error() {
+    let server = MockHttpServer::empty();
+    let reset = (chrono::Utc::now().timestamp() + 3600).to_string();
+    server.add_route_with_headers(
+        MockRoute {
+            path_contains: "/api/v3/repos/testuser/testrepo/releases?".into(),
+            response_code: 403,
+            response_body: br#"{"message":"API rate limit exceeded"}"#.to_vec(),
+            expected_headers: vec![],
+        },
+        &[("X-RateLimit-Remaining", "0"), ("X-RateLimit-Reset", &reset)],
+    );
+
+    let source = GithubSource::new(&format!("{}/testuser/testrepo", server.url()), None, false).with_max_rate_limit_wait(Duration::from_secs(5));
+    let result = source.get_release_feed("stable", &test_manifest(), "");
+    match result {
+        Err(Error::RateLimited(_, Some(delay))) => assert!(delay > Duration::from_secs(3000)),
+        other => panic!("Expected a rate limit error, got {:?}", other.map(|f| f.Assets.len())),
+    }
+}
@@ -2,7 +2,9 @@ mod common;
 
 use common::*;
 use std::sync::mpsc;
+use std::time::Duration;
 use velopack::sources::{GitlabSource, UpdateSource};
+use velopack::Error;
 
 fn gitlab_releases_json(server_url: &str) -> String {
     format!(
@@ -216,3 +218,71 @@ fn download_entry_success() {
     let progress: Vec<i16> = rx.try_iter().collect();
     assert!(!progress.is_empty());
 }
+
+#[test]
+fn follows_link_pagination() {
+    let server = MockHttpServer::empty();
+    let older_releases_json = format!(
+        r#"[{{"name":"v1.0.0","upcoming_release":false,"released_at":"2023-01-01T00:00:00Z","assets":{{"count":1,"links":[{{"name":"releases.stable.json","url":"{0}/api/url/v1/releases.stable.json","direct_asset_url":"{0}/direct/v1/releases.stable.json"}}]}}}}]"#,
+        server.url()
+    );
+    let newer_releases_json = format!(
+        r#"[{{"name":"v2.1.0","upcoming_release":false,"released_at":"2024-02-01T00:00:00Z","assets":{{"count":1,"links":[{{"name":"TestApp-2.1.0-full.nupkg","url":"{0}/api/url/TestApp-2.1.0-full.nupkg","direct_asset_url":"{0}/direct/TestApp-2.1.0-full.nupkg"}}]}}}}]"#,
+        server.url()
+    );
+    let next_link = format!("<{}/api/v4/projects/12345/releases?per_page=10&page=2>; rel=\"next\"", server.url());
+
+    server.add_route(MockRoute {
+        path_contains: "/releases?per_page=10&page=2".into(),
+        response_code: 200,
+        response_body: older_releases_json.into_bytes(),
+        expected_headers: vec![],
+    });
+    server.add_route_with_headers(
+        MockRoute {
+            path_contains: "/releases?per_page=".into(),
+            response_code: 200,
+            response_body: newer_releases_json.into_bytes(),
+            expected_headers: vec![],
+        },
+        &[("Link", &next_link)],
+    );
+    server.add_route(MockRoute {
+        path_contains: "/direct/v1/releases.stable.json".into(),
+        response_code: 200,
+        response_body: serde_json::json!({"Assets":[{"PackageId":"TestApp","Version":"1.0.0","Type":"Full","FileName":"TestApp-1.0.0-full.nupkg"}]})
+            .to_string()
+            .into_bytes(),
+        expected_headers: vec![],
+    });
+    server.add_route(MockRoute {
+        path_contains: "/direct/releases.stable.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![],
+    });
+
+    // the first page has no release feed, so the next page is requested
+    let source = GitlabSource::new(&format!("{}/api/v4/projects/12345", server.url()), None, false).with_max_pages(2);
+    let feed = source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    let versions: Vec<&str> = feed.Assets.iter().map(|a| a.Version.as_str()).collect();
+    assert_eq!(versions, vec!["1.0.0"]);
+}
+
+#[test]
+fn long_retry_after_returns_rate_limited_error() {
+    let server = MockHttpServer::empty();
+    server.add_route_with_headers(
+        MockRoute {
+            path_contains: "/releases?per_page=".into(),
+            response_code: 429,
+            response_body: b"Retry later".to_vec(),
+            expected_headers: vec![],
+        },
+        &[("Retry-After", "120")],
+    );
+
+    let source = GitlabSource::new(&format!("{}/api/v4/projects/12345", server.url()), None, false).with_max_rate_limit_wait(Duration::from_secs(10));
+    let result = source.get_release_feed("stable", &test_manifest(), "");
+    assert!(matches!(result, Err(Error::RateLimited(_, Some(d))) if d == Duration::from_secs(120)));
+}
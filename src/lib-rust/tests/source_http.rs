@@ -1,8 +1,10 @@
 mod common;
 
 use common::*;
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
 use velopack::sources::{HttpSource, UpdateSource};
+use velopack::{Error, HttpClientConfig, SignatureVerifier};
 
 #[test]
 fn feed_success() {
@@ -102,3 +104,217 @@ fn trailing_slash_handling() {
     let feed = source_with_slash.get_release_feed("stable", &manifest, "").unwrap();
     assert_eq!(feed.Assets.len(), 1);
 }
+
+#[test]
+fn feed_uses_http_config() {
+    let server = MockHttpServer::empty();
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![("User-Agent".into(), "MyApp/1.2.3".into())],
+    });
+
+    let config = HttpClientConfig {
+        UserAgent: Some("MyApp/1.2.3".into()),
+        ..Default::default()
+    };
+    let source = HttpSource::new(server.url()).with_http_config(config);
+    let manifest = test_manifest();
+    let feed = source.get_release_feed("stable", &manifest, "").unwrap();
+    assert_eq!(feed.Assets.len(), 1);
+}
+
+#[test]
+fn download_uses_default_http_config() {
+    let body = vec![0xDE, 0xAD, 0xBE, 0xEF];
+    let server = MockHttpServer::empty();
+    server.add_route(MockRoute {
+        path_contains: "TestApp-2.0.0-full.nupkg".into(),
+        response_code: 200,
+        response_body: body.clone(),
+        expected_headers: vec![("User-Agent".into(), "MyApp/1.2.3".into())],
+    });
+
+    let config = HttpClientConfig {
+        UserAgent: Some("MyApp/1.2.3".into()),
+        ..Default::default()
+    };
+    let mut source = HttpSource::new(server.url());
+    source.set_default_http_config(&config);
+
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("downloaded.nupkg");
+    source.download_release_entry(&sample_asset(), &dest, None).unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), body);
+}
+
+#[test]
+fn explicit_http_config_is_not_replaced_by_default() {
+    let server = MockHttpServer::empty();
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![("User-Agent".into(), "Explicit/1.0".into())],
+    });
+
+    let explicit = HttpClientConfig {
+        UserAgent: Some("Explicit/1.0".into()),
+        ..Default::default()
+    };
+    let default = HttpClientConfig {
+        UserAgent: Some("Default/1.0".into()),
+        ..Default::default()
+    };
+    let mut source = HttpSource::new(server.url()).with_http_config(explicit);
+    source.set_default_http_config(&default);
+
+    let feed = source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    assert_eq!(feed.Assets.len(), 1);
+}
+
+#[test]
+fn feed_sends_custom_headers_and_basic_auth() {
+    let server = MockHttpServer::empty();
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![
+            ("X-Api-Key".into(), "secret-key".into()),
+            ("Authorization".into(), "Basic dXNlcjpwYXNz".into()),
+        ],
+    });
+
+    let source = HttpSource::new(server.url())
+        .with_header("X-Api-Key", "secret-key")
+        .with_basic_auth("user", "pass");
+    let feed = source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    assert_eq!(feed.Assets.len(), 1);
+}
+
+#[test]
+fn download_sends_bearer_token() {
+    let body = vec![0xDE, 0xAD, 0xBE, 0xEF];
+    let server = MockHttpServer::empty();
+    server.add_route(MockRoute {
+        path_contains: "TestApp-2.0.0-full.nupkg".into(),
+        response_code: 200,
+        response_body: body.clone(),
+        expected_headers: vec![("Authorization".into(), "Bearer static-token".into())],
+    });
+
+    let source = HttpSource::new(server.url()).with_bearer_token("static-token");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("downloaded.nupkg");
+    source.download_release_entry(&sample_asset(), &dest, None).unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), body);
+}
+
+#[test]
+fn token_provider_is_called_for_every_request() {
+    let body = vec![0xDE, 0xAD, 0xBE, 0xEF];
+    let server = MockHttpServer::empty();
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![("Authorization".into(), "Bearer token-1".into())],
+    });
+    server.add_route(MockRoute {
+        path_contains: "TestApp-2.0.0-full.nupkg".into(),
+        response_code: 200,
+        response_body: body.clone(),
+        expected_headers: vec![("Authorization".into(), "Bearer token-2".into())],
+    });
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let calls_clone = calls.clone();
+    let source = HttpSource::new(server.url()).with_token_provider(move || {
+        let n = calls_clone.fetch_add(1, Ordering::SeqCst) + 1;
+        Ok(format!("token-{}", n))
+    });
+
+    source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("downloaded.nupkg");
+    source.download_release_entry(&sample_asset(), &dest, None).unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), body);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn token_provider_error_fails_request() {
+    let server = MockHttpServer::empty();
+    let source = HttpSource::new(server.url()).with_token_provider(|| Err(Error::Other("token expired".into())));
+    let result = source.get_release_feed("stable", &test_manifest(), "");
+    assert!(result.is_err());
+}
+
+fn add_signed_feed_routes(server: &MockHttpServer, signature: String) {
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json.sig".into(),
+        response_code: 200,
+        response_body: signature.into_bytes(),
+        expected_headers: vec![],
+    });
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![],
+    });
+}
+
+#[test]
+fn signed_feed_is_accepted() {
+    let old_key = feed_key_pair(1);
+    let new_key = feed_key_pair(2);
+    let server = MockHttpServer::empty();
+    add_signed_feed_routes(&server, sign_feed(&new_key, "releases.stable.json", sample_feed_json().as_bytes()));
+
+    let mut source = HttpSource::new(server.url());
+    let verifier = SignatureVerifier::new(&[feed_public_key(&old_key), feed_public_key(&new_key)]).unwrap();
+    assert!(source.set_feed_verifier(&verifier));
+
+    let feed = source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    assert_eq!(feed.Assets.len(), 1);
+}
+
+#[test]
+fn feed_signed_by_untrusted_key_is_rejected() {
+    let trusted = feed_key_pair(1);
+    let untrusted = feed_key_pair(3);
+    let server = MockHttpServer::empty();
+    add_signed_feed_routes(&server, sign_feed(&untrusted, "releases.stable.json", sample_feed_json().as_bytes()));
+
+    let mut source = HttpSource::new(server.url());
+    source.set_feed_verifier(&SignatureVerifier::new(&[feed_public_key(&trusted)]).unwrap());
+
+    let result = source.get_release_feed("stable", &test_manifest(), "");
+    assert!(matches!(result, Err(Error::SignatureInvalid(..))));
+}
+
+#[test]
+fn unsigned_feed_is_rejected_when_verifying() {
+    let server = MockHttpServer::empty();
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json.sig".into(),
+        response_code: 404,
+        response_body: b"Not Found".to_vec(),
+        expected_headers: vec![],
+    });
+    server.add_route(MockRoute {
+        path_contains: "releases.stable.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![],
+    });
+
+    let mut source = HttpSource::new(server.url());
+    source.set_feed_verifier(&SignatureVerifier::new(&[feed_public_key(&feed_key_pair(1))]).unwrap());
+
+    let result = source.get_release_feed("stable", &test_manifest(), "");
+    assert!(result.is_err());
+}
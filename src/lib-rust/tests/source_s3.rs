@@ -0,0 +1,84 @@
+mod common;
+
+use common::*;
+use velopack::sources::{S3Source, UpdateSource};
+
+fn mock_s3_source(server: &MockHttpServer) -> S3Source {
+    S3Source::new("my-bucket", "us-east-1", "AKIDEXAMPLE", "secret")
+        .unwrap()
+        .with_endpoint(&server.url())
+        .unwrap()
+        .with_path_style(true)
+}
+
+#[test]
+fn feed_success() {
+    let server = MockHttpServer::empty();
+    server.add_route(MockRoute {
+        path_contains: "/my-bucket/releases.stable.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![
+            ("Authorization".into(), "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/".into()),
+            (
+                "x-amz-content-sha256".into(),
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".into(),
+            ),
+        ],
+    });
+
+    let source = mock_s3_source(&server);
+    let feed = source.get_release_feed("stable", &test_manifest(), "").unwrap();
+    assert_eq!(feed.Assets.len(), 1);
+    assert_eq!(feed.Assets[0].PackageId, "TestApp");
+}
+
+#[test]
+fn feed_uses_prefix_and_session_token() {
+    let server = MockHttpServer::empty();
+    server.add_route(MockRoute {
+        path_contains: "/my-bucket/apps/test/releases.beta.json".into(),
+        response_code: 200,
+        response_body: sample_feed_json().into_bytes(),
+        expected_headers: vec![("x-amz-security-token".into(), "session-token".into())],
+    });
+
+    let source = mock_s3_source(&server).with_prefix("apps/test/").with_session_token("session-token");
+    let feed = source.get_release_feed("beta", &test_manifest(), "").unwrap();
+    assert_eq!(feed.Assets.len(), 1);
+}
+
+#[test]
+fn feed_access_denied() {
+    let server = MockHttpServer::empty();
+    server.add_route(MockRoute {
+        path_contains: "/my-bucket/releases.stable.json".into(),
+        response_code: 403,
+        response_body: b"<Error><Code>AccessDenied</Code></Error>".to_vec(),
+        expected_headers: vec![],
+    });
+
+    let source = mock_s3_source(&server);
+    let result = source.get_release_feed("stable", &test_manifest(), "");
+    assert!(result.is_err());
+}
+
+#[test]
+fn download_success() {
+    let body = vec![0xDE, 0xAD, 0xBE, 0xEF, 0x01, 0x02, 0x03, 0x04];
+    let server = MockHttpServer::empty();
+    server.add_route(MockRoute {
+        path_contains: "/my-bucket/TestApp-2.0.0-full.nupkg".into(),
+        response_code: 200,
+        response_body: body.clone(),
+        expected_headers: vec![("Authorization".into(), "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/".into())],
+    });
+
+    let source = mock_s3_source(&server);
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("downloaded.nupkg");
+    source.download_release_entry(&sample_asset(), &dest, None).unwrap();
+
+    let downloaded = std::fs::read(&dest).unwrap();
+    assert_eq!(downloaded, body);
+}
@@ -56,6 +56,10 @@ public class PackTask : MSBuildAsyncTask
 
     public bool NoInst { get; private set; }
 
+    public string? SigningKey { get; set; }
+
+    public string? SigningPublicKeys { get; set; }
+
     public string? InstWelcome { get; set; }
 
     public string? InstReadme { get; set; }
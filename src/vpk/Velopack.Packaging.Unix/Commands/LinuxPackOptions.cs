@@ -37,6 +37,10 @@ public class LinuxPackOptions : IPackOptions
 
     public bool NoInst { get; set; }
 
+    public string SigningKey { get; set; }
+
+    public string SigningPublicKeys { get; set; }
+
     public string Categories { get; set; }
 
     public string Compression { get; set; } = AppImageTool.DefaultCompressionAlgorithm;
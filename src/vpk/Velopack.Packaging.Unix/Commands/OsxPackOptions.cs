@@ -40,4 +40,8 @@ public class OsxPackOptions : OsxBundleOptions, IPackOptions
     public string Exclude { get; set; } = @".*\.pdb";
 
     public bool NoDefaultExclude { get; set; }
+
+    public string SigningKey { get; set; }
+
+    public string SigningPublicKeys { get; set; }
 }
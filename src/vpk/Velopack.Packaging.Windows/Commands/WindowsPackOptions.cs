@@ -24,6 +24,10 @@ public class WindowsPackOptions : WindowsReleasifyOptions, INugetPackCommand, IP
 
     public bool NoInst { get; set; }
 
+    public string SigningKey { get; set; }
+
+    public string SigningPublicKeys { get; set; }
+
     public string Shortcuts { get; set; } = "Desktop,StartMenuRoot";
 
     public string InstWelcome { get; set; }
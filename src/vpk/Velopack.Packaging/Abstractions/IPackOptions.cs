@@ -12,4 +12,6 @@ public interface IPackOptions : INugetPackCommand, IPlatformOptions
     bool NoDefaultExclude { get; set; }
     bool NoPortable { get; set; }
     bool NoInst { get; set; }
+    string SigningKey { get; set; }
+    string SigningPublicKeys { get; set; }
 }
@@ -13,6 +13,7 @@ public PackOptionsValidator()
         RuleFor(x => x.PackDirectory).NotEmpty();
         RuleFor(x => x.ReleaseNotes).MustBeExistingFile();
         RuleFor(x => x.Icon).MustBeExistingFile();
+        RuleFor(x => x.SigningKey).MustBeExistingFile();
         RuleFor(x => x.Exclude).MustBeValidRegex();
         RuleFor(x => x.Channel).MustBeValidNuGetId();
         RuleFor(x => x.TargetRuntime).MustBeSupportedRid();
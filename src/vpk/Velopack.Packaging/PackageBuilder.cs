@@ -1,8 +1,13 @@
-﻿using System.Security;
+﻿using System.IO.Compression;
+using System.Security;
+using System.Security.Cryptography;
+using System.Text;
 using System.Text.RegularExpressions;
 using FluentValidation;
 using Markdig;
 using Microsoft.Extensions.Logging;
+using Org.BouncyCastle.Crypto.Parameters;
+using Org.BouncyCastle.Crypto.Signers;
 using Velopack.Core;
 using Velopack.Core.Abstractions;
 using Velopack.NuGet;
@@ -30,6 +35,10 @@ public abstract class PackageBuilder<T, TValidator> : ValidatedCommand<T, TValid
 
     protected Dictionary<string, string> ExtraNuspecMetadata { get; } = new();
 
+    protected const string FileManifestFileName = "velopack.files";
+
+    protected const string SignatureFileName = "velopack.sig";
+
     private readonly Regex REGEX_EXCLUDES = new(@".*[\\\/]createdump.*|.*\.vshost\..*|.*\.nupkg$", RegexOptions.IgnoreCase | RegexOptions.Compiled);
 
     public PackageBuilder(RuntimeOs supportedOs, ILogger logger, IFancyConsole console)
@@ -241,6 +250,8 @@ void addMetadata(string key, string value, bool cdata = false)
             addMetadata("machineArchitecture", rid.Architecture.ToString());
         }
 
+        addMetadata("signingPublicKeys", String.Join(";", GetSigningPublicKeys()));
+
         string nuspec = $"""
             <?xml version="1.0" encoding="utf-8"?>
             <package xmlns="http://schemas.microsoft.com/packaging/2010/07/nuspec.xsd">
@@ -298,9 +309,11 @@ protected virtual async Task CreateReleasePackage(Action<int> progress, string p
             File.Copy(kvp.Value, Path.Combine(stagingDir.FullName, kvp.Key), true);
         }
 
+        WriteFileManifest(stagingDir);
         AddContentTypesAndRel(nuspecPath);
 
         await EasyZip.CreateZipFromDirectoryAsync(Log.ToVelopackLogger(), outputPath, stagingDir.FullName, CoreUtil.CreateProgressDelegate(progress, 30, 100));
+        SignReleasePackage(outputPath);
         progress(100);
     }
 
@@ -369,6 +382,105 @@ void CopyFilesInternal(DirectoryInfo source, DirectoryInfo target)
         }
     }
 
+    /// <summary>
+    /// Writes a manifest containing the SHA256 of every file in the package, which is used by the updater
+    /// to verify (and repair) the installed files. Each line is in the format '{sha256}  {path}'.
+    /// </summary>
+    protected virtual void WriteFileManifest(DirectoryInfo stagingDir)
+    {
+        var lines = stagingDir.EnumerateFiles("*", SearchOption.AllDirectories)
+            .Select(f => (Name: Path.GetRelativePath(stagingDir.FullName, f.FullName).Replace('\\', '/'), Info: f))
+            .OrderBy(f => f.Name, StringComparer.Ordinal)
+            .Select(f => {
+                using var stream = f.Info.OpenRead();
+                return $"{Convert.ToHexString(SHA256.HashData(stream))}  {f.Name}\n";
+            });
+        File.WriteAllText(Path.Combine(stagingDir.FullName, FileManifestFileName), String.Concat(lines));
+    }
+
+    /// <summary>
+    /// Returns the base64 encoded Ed25519 public keys which installed apps will trust to sign future updates. This is
+    /// the public key of the signing key (if any), and any additional keys from the signingPublicKeys option.
+    /// </summary>
+    protected virtual string[] GetSigningPublicKeys()
+    {
+        var keys = (Options.SigningPublicKeys ?? "")
+            .Split(new[] { ';', ',', ' ' }, StringSplitOptions.RemoveEmptyEntries | StringSplitOptions.TrimEntries);
+        if (String.IsNullOrEmpty(Options.SigningKey)) {
+            return keys;
+        }
+
+        var publicKey = Convert.ToBase64String(ReadSigningKey().GeneratePublicKey().GetEncoded());
+        return keys.Prepend(publicKey).Distinct().ToArray();
+    }
+
+    private Ed25519PrivateKeyParameters ReadSigningKey()
+    {
+        byte[] seed;
+        try {
+            seed = Convert.FromBase64String(File.ReadAllText(Options.SigningKey).Trim());
+        } catch (FormatException ex) {
+            throw new UserInfoException($"The signing key '{Options.SigningKey}' is not valid base64: {ex.Message}");
+        }
+
+        if (seed.Length != Ed25519PrivateKeyParameters.KeySize) {
+            throw new UserInfoException(
+                $"The signing key '{Options.SigningKey}' must be a {Ed25519PrivateKeyParameters.KeySize} byte Ed25519 private key, but it was {seed.Length} bytes.");
+        }
+
+        return new Ed25519PrivateKeyParameters(seed, 0);
+    }
+
+    /// <summary>
+    /// Signs the release package with the signing key (if any), and adds the base64 encoded signature to the package
+    /// as 'velopack.sig'. The signature is made over one '{sha256}  {name}' line for every entry in the package, sorted
+    /// by name. This covers the name and contents of every entry (symlinks are stored as an entry containing the link
+    /// target, so they are covered too), but not other entry metadata such as unix permissions or timestamps.
+    /// Packages with entry names containing control characters, or with duplicate entry names, can not be signed.
+    /// </summary>
+    protected virtual void SignReleasePackage(string packagePath)
+    {
+        if (String.IsNullOrEmpty(Options.SigningKey)) {
+            return;
+        }
+
+        var signer = new Ed25519Signer();
+        signer.Init(true, ReadSigningKey());
+
+        using var archive = ZipFile.Open(packagePath, ZipArchiveMode.Update);
+
+        // the payload would be ambiguous if a name could contain a line break, or if two entries had the same name.
+        var names = new HashSet<string>(StringComparer.Ordinal);
+        foreach (var e in archive.Entries) {
+            if (e.FullName.Any(Char.IsControl)) {
+                var printable = new string(e.FullName.Select(c => Char.IsControl(c) ? '?' : c).ToArray());
+                throw new UserInfoException($"Can not sign the release package, because the entry '{printable}' has a name containing control characters.");
+            }
+            if (!names.Add(e.FullName)) {
+                throw new UserInfoException($"Can not sign the release package, because it contains more than one entry named '{e.FullName}'.");
+            }
+        }
+
+        var lines = archive.Entries
+            .Where(e => !e.FullName.EndsWith("/") && e.FullName != SignatureFileName)
+            .OrderBy(e => e.FullName, StringComparer.Ordinal)
+            .Select(e => {
+                using var stream = e.Open();
+                return $"{Convert.ToHexString(SHA256.HashData(stream))}  {e.FullName}\n";
+            })
+            .ToArray();
+        var payload = Encoding.UTF8.GetBytes(String.Concat(lines));
+        signer.BlockUpdate(payload, 0, payload.Length);
+
+        var entry = archive.CreateEntry(SignatureFileName);
+        entry.LastWriteTime = EasyZip.ZipFormatMinDate;
+        using (var writer = new StreamWriter(entry.Open())) {
+            writer.Write(Convert.ToBase64String(signer.GenerateSignature()));
+        }
+
+        Log.Info($"Signed release package with {lines.Length} files.");
+    }
+
     protected virtual void AddContentTypesAndRel(string nuspecPath)
     {
         var rootDirectory = Path.GetDirectoryName(nuspecPath);
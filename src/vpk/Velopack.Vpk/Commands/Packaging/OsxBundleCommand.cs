@@ -13,6 +13,8 @@ public OsxBundleCommand()
         RemoveOption(NoInstOption);
         RemoveOption(ReleaseNotesOption);
         RemoveOption(DeltaModeOption);
+        RemoveOption(SigningKeyOption);
+        RemoveOption(SigningPublicKeysOption);
     }
 
     public OsxBundleCommand(string name, string description)
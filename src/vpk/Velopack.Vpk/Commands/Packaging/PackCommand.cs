@@ -58,6 +58,14 @@ public abstract class PackCommand : PlatformCommand
 
     protected Option<bool> NoInstOption { get; private set; }
 
+    public string SigningKey { get; private set; }
+
+    protected Option<FileInfo> SigningKeyOption { get; private set; }
+
+    public string SigningPublicKeys { get; private set; }
+
+    protected Option<string> SigningPublicKeysOption { get; private set; }
+
     public PackCommand(string name, string description, RuntimeOs targetOs = RuntimeOs.Unknown)
         : base(name, description, targetOs)
     {
@@ -115,5 +123,13 @@ public PackCommand(string name, string description, RuntimeOs targetOs = Runtime
         NoInstOption = AddOption<bool>((v) => NoInst = v, ["--noInst"])
             .SetDescription("Skip generating an installer package.")
             .SetHidden(true);
+
+        SigningKeyOption = AddOption<FileInfo>((v) => SigningKey = v.ToFullNameOrNull(), ["--signingKey"])
+            .SetDescription("File with a base64 encoded Ed25519 private key, used to sign the release package. Installed apps will only apply updates signed by a trusted key.")
+            .SetArgumentHelpName("PATH");
+
+        SigningPublicKeysOption = AddOption<string>((v) => SigningPublicKeys = v, ["--signingPublicKeys"])
+            .SetDescription("Additional base64 encoded Ed25519 public keys (separated by ';') trusted to sign future updates, eg. to rotate the signing key.")
+            .SetArgumentHelpName("KEYS");
     }
 }